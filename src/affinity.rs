@@ -0,0 +1,207 @@
+use crate::wrap;
+
+/// Get the CPUs that dyno is allowed to run on.
+///
+/// # Errors
+///
+/// If the affinity of the process cannot be read.
+///
+#[cfg(target_os = "linux")]
+pub fn available_cpus() -> crate::Result<Vec<usize>> {
+    // SAFETY: an all-zero `cpu_set_t` is a valid, empty set
+    let mut set: libc::cpu_set_t = unsafe { std::mem::zeroed() };
+
+    // SAFETY: `set` is a valid `cpu_set_t` of the given size
+    let result = unsafe {
+        libc::sched_getaffinity(
+            0,
+            std::mem::size_of::<libc::cpu_set_t>(),
+            std::ptr::from_mut(&mut set),
+        )
+    };
+
+    if result != 0 {
+        return Err(Box::new(wrap!(std::io::Error::last_os_error().into())));
+    }
+
+    // SAFETY: `set` was filled in by `sched_getaffinity`
+    Ok((0..libc::CPU_SETSIZE as usize)
+        .filter(|cpu| unsafe { libc::CPU_ISSET(*cpu, &set) })
+        .collect())
+}
+
+/// CPU affinity is only available on Linux.
+///
+/// # Errors
+///
+/// Always, since the affinity of a process cannot be set on this platform.
+///
+#[cfg(not(target_os = "linux"))]
+pub fn available_cpus() -> crate::Result<Vec<usize>> {
+    Err(Box::new(wrap!(
+        "CPU affinity is only supported on Linux".into()
+    )))
+}
+
+/// Parse a list of CPUs, such as `0,2` or `4-7`, the way `taskset --cpu-list` does.
+///
+/// # Arguments
+///
+/// * `list` - Comma separated CPUs and inclusive ranges of CPUs.
+///
+/// # Returns
+///
+/// The sorted CPUs of the list, without duplicates.
+///
+/// # Errors
+///
+/// If the list is empty, or contains anything other than CPUs and ranges of CPUs.
+///
+pub fn parse_cpu_list(list: &str) -> Result<Vec<usize>, String> {
+    let invalid =
+        || format!("Invalid CPU list \"{list}\", expected CPUs such as \"0,2\" or \"4-7\"");
+
+    let mut cpus = std::collections::BTreeSet::new();
+
+    for part in list.split(',').map(str::trim) {
+        let (first, last) = part.split_once('-').unwrap_or((part, part));
+        let first = first.trim().parse::<usize>().map_err(|_| invalid())?;
+        let last = last.trim().parse::<usize>().map_err(|_| invalid())?;
+
+        if first > last {
+            return Err(invalid());
+        }
+
+        cpus.extend(first..=last);
+    }
+
+    Ok(cpus.into_iter().collect())
+}
+
+/// Split the CPUs into disjoint sets of consecutive CPUs, one for each job.
+///
+/// The CPUs that do not divide evenly are given to the first sets.
+///
+/// # Arguments
+///
+/// * `cpus` - The available CPUs.
+///
+/// * `jobs` - The number of sets to split the CPUs into.
+///
+/// # Returns
+///
+/// The sets, or `None` if there are fewer CPUs than jobs.
+///
+pub fn split(cpus: &[usize], jobs: usize) -> Option<Vec<Vec<usize>>> {
+    if jobs == 0 || cpus.len() < jobs {
+        return None;
+    }
+
+    let size = cpus.len() / jobs;
+    let remainder = cpus.len() % jobs;
+
+    let mut sets = Vec::with_capacity(jobs);
+    let mut start = 0;
+
+    for job in 0..jobs {
+        let end = start + size + usize::from(job < remainder);
+        sets.push(cpus[start..end].to_vec());
+        start = end;
+    }
+
+    Some(sets)
+}
+
+/// Pin a command and every process it spawns to a set of CPUs.
+///
+/// The affinity is set in the child before it executes the command, and is inherited by
+/// everything the command spawns.
+///
+/// # Arguments
+///
+/// * `command` - The command to pin, before it is spawned.
+///
+/// * `cpus` - The CPUs to pin the command to.
+///
+#[cfg(target_os = "linux")]
+pub fn pin(command: &mut std::process::Command, cpus: &[usize]) {
+    use std::os::unix::process::CommandExt;
+
+    // SAFETY: an all-zero `cpu_set_t` is a valid, empty set
+    let mut set: libc::cpu_set_t = unsafe { std::mem::zeroed() };
+
+    for cpu in cpus {
+        // SAFETY: `set` is a valid `cpu_set_t`, and `CPU_SET` ignores CPUs beyond its size
+        unsafe { libc::CPU_SET(*cpu, &mut set) };
+    }
+
+    // SAFETY: `sched_setaffinity` is async-signal-safe, and the set is only read by the child
+    unsafe {
+        command.pre_exec(move || {
+            if libc::sched_setaffinity(
+                0,
+                std::mem::size_of::<libc::cpu_set_t>(),
+                std::ptr::from_ref(&set),
+            ) != 0
+            {
+                return Err(std::io::Error::last_os_error());
+            }
+
+            Ok(())
+        });
+    }
+}
+
+/// CPU affinity is only available on Linux, so the command is left unpinned.
+#[cfg(not(target_os = "linux"))]
+pub fn pin(_command: &mut std::process::Command, _cpus: &[usize]) {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_split_cpus() {
+        let cpus = (0..10).collect::<Vec<_>>();
+
+        assert_eq!(
+            split(&cpus, 3),
+            Some(vec![vec![0, 1, 2, 3], vec![4, 5, 6], vec![7, 8, 9]])
+        );
+        assert_eq!(split(&cpus, 1), Some(vec![cpus.clone()]));
+        assert_eq!(split(&cpus, 11), None);
+        assert_eq!(split(&cpus, 0), None);
+    }
+
+    #[test]
+    fn test_parse_cpu_list() {
+        assert_eq!(parse_cpu_list("3"), Ok(vec![3]));
+        assert_eq!(parse_cpu_list("4-6, 0,5"), Ok(vec![0, 4, 5, 6]));
+        assert!(parse_cpu_list("").is_err());
+        assert!(parse_cpu_list("6-4").is_err());
+        assert!(parse_cpu_list("0,a").is_err());
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn test_pin_command() -> crate::Result<()> {
+        let cpus = available_cpus()?;
+        let cpu = cpus[cpus.len() - 1];
+
+        let mut command = std::process::Command::new("cat");
+        command.arg("/proc/self/status");
+        pin(&mut command, &[cpu]);
+
+        let output = command.output().map_err(|e| wrap!(e.into()))?;
+        let status = String::from_utf8_lossy(&output.stdout);
+
+        let allowed = status
+            .lines()
+            .find_map(|line| line.strip_prefix("Cpus_allowed_list:"))
+            .map(str::trim);
+
+        assert_eq!(allowed, Some(cpu.to_string().as_str()));
+
+        Ok(())
+    }
+}
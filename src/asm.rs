@@ -0,0 +1,358 @@
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+/// The latest version of the asm information schema understood by dyno
+///
+/// The information printed by forc before the schema was versioned is version `0`.
+pub const ASM_SCHEMA_VERSION: u32 = 1;
+
+/// The assembly information of a compiled program, reported by forc after a build
+///
+/// Every field is optional in the reported JSON, and fields that dyno does not know are kept
+/// in `extra` so that they survive a round trip through a stored run.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct AsmInformation {
+    /// The version of the schema of the information
+    pub schema_version: u32,
+    /// The size of the bytecode in bytes
+    pub bytecode_size: u64,
+    /// The information of the data section
+    pub data_section: DataSectionInformation,
+    /// The entries of the program, such as contract methods or the main function of a script
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub entries: Vec<EntryInformation>,
+    /// The functions of the program, including the ones that are not entries
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub functions: Vec<FunctionInformation>,
+    /// The information of the program
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub program: Option<ProgramInformation>,
+    /// The fields that are not part of the schema
+    #[serde(flatten)]
+    pub extra: BTreeMap<String, serde_json::Value>,
+}
+
+/// The information of the data section of a compiled program
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct DataSectionInformation {
+    /// The total size of the data section in bytes
+    pub size: u64,
+    /// The used size of the data section in bytes
+    pub used: u64,
+    /// The values stored in the data section
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub value_pairs: Vec<DataSectionValue>,
+    /// The fields that are not part of the schema
+    #[serde(flatten)]
+    pub extra: BTreeMap<String, serde_json::Value>,
+}
+
+/// A single value stored in the data section of a compiled program
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct DataSectionValue {
+    /// The name of the constant holding the value, if known
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    /// The size of the value in bytes, if reported
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub size: Option<u64>,
+    /// The fields that are not part of the schema, such as the value itself
+    #[serde(flatten)]
+    pub extra: BTreeMap<String, serde_json::Value>,
+}
+
+impl DataSectionValue {
+    /// Returns the key identifying the value across builds.
+    ///
+    /// Named values are identified by their name, and unnamed values by the value itself.
+    #[must_use]
+    pub fn key(&self) -> String {
+        match &self.name {
+            Some(name) => name.clone(),
+            None => self
+                .extra
+                .get("value")
+                .map_or_else(|| "<unknown>".to_string(), ToString::to_string),
+        }
+    }
+}
+
+/// The information of a single entry of a compiled program
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct EntryInformation {
+    /// The name of the entry
+    pub name: String,
+    /// The size of the bytecode of the entry in bytes, if reported
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub size: Option<u64>,
+    /// The fields that are not part of the schema
+    #[serde(flatten)]
+    pub extra: BTreeMap<String, serde_json::Value>,
+}
+
+/// The information of a single function of a compiled program
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct FunctionInformation {
+    /// The name of the function
+    pub name: String,
+    /// The size of the bytecode of the function in bytes
+    pub size: u64,
+    /// The fields that are not part of the schema
+    #[serde(flatten)]
+    pub extra: BTreeMap<String, serde_json::Value>,
+}
+
+/// The information of a compiled program
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ProgramInformation {
+    /// The kind of the program, i.e. `contract`, `script`, `predicate` or `library`
+    pub kind: String,
+    /// The fields that are not part of the schema
+    #[serde(flatten)]
+    pub extra: BTreeMap<String, serde_json::Value>,
+}
+
+impl AsmInformation {
+    /// Parse the asm information reported by forc.
+    ///
+    /// # Arguments
+    ///
+    /// * `data` - The JSON value reported by forc.
+    ///
+    /// # Errors
+    ///
+    /// If the information is not a JSON object.
+    ///
+    /// If the schema version is newer than [`ASM_SCHEMA_VERSION`].
+    ///
+    pub fn parse(data: serde_json::Value) -> Result<Self, String> {
+        let information: Self = serde_json::from_value(data).map_err(|e| e.to_string())?;
+
+        if information.schema_version > ASM_SCHEMA_VERSION {
+            return Err(format!(
+                "Unsupported asm information schema version {}, the latest supported version is {ASM_SCHEMA_VERSION}",
+                information.schema_version
+            ));
+        }
+
+        Ok(information)
+    }
+
+    /// Returns the number of values stored in the data section.
+    #[must_use]
+    pub fn data_section_values(&self) -> usize {
+        self.data_section.value_pairs.len()
+    }
+
+    /// Returns the kind of the program, if reported.
+    #[must_use]
+    pub fn program_kind(&self) -> Option<&str> {
+        self.program.as_ref().map(|program| program.kind.as_str())
+    }
+}
+
+/// The kind of an item of the bytecode whose size is compared
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SizeItem {
+    /// An entry of the program
+    Entry,
+    /// A function of the program
+    Function,
+    /// A value of the data section
+    Constant,
+}
+
+impl std::fmt::Display for SizeItem {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SizeItem::Entry => write!(f, "Entry"),
+            SizeItem::Function => write!(f, "Function"),
+            SizeItem::Constant => write!(f, "Constant"),
+        }
+    }
+}
+
+/// The change in size of a single item of the bytecode between two builds
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct SizeChange {
+    /// The kind of the item
+    pub item: SizeItem,
+    /// The name of the item
+    pub name: String,
+    /// The size of the item in the previous build, or `None` if it was added
+    pub previous: Option<u64>,
+    /// The size of the item in the current build, or `None` if it was removed
+    pub current: Option<u64>,
+}
+
+impl SizeChange {
+    /// Returns the change in size in bytes, counting missing items as empty.
+    #[must_use]
+    pub fn delta(&self) -> i128 {
+        i128::from(self.current.unwrap_or(0)) - i128::from(self.previous.unwrap_or(0))
+    }
+}
+
+/// Compare the size of every entry, function and data section value of two builds.
+///
+/// Items are matched by their kind and name, and items whose size did not change are left
+/// out. Entries without a reported size are skipped.
+///
+/// # Arguments
+///
+/// * `previous` - The asm information of the previous build.
+///
+/// * `current` - The asm information of the current build.
+///
+/// # Returns
+///
+/// The changes, from the largest growth to the largest shrink.
+///
+#[must_use]
+pub fn diff_sizes(previous: &AsmInformation, current: &AsmInformation) -> Vec<SizeChange> {
+    let previous_sizes = item_sizes(previous);
+    let mut current_sizes = item_sizes(current);
+
+    let mut sizes = previous_sizes
+        .into_iter()
+        .map(|(key, size)| {
+            let current = current_sizes.remove(&key);
+            (key, (Some(size), current))
+        })
+        .collect::<BTreeMap<_, _>>();
+
+    sizes.extend(
+        current_sizes
+            .into_iter()
+            .map(|(key, size)| (key, (None, Some(size)))),
+    );
+
+    let mut changes = sizes
+        .into_iter()
+        .map(|((item, name), (previous, current))| SizeChange {
+            item,
+            name,
+            previous,
+            current,
+        })
+        .filter(|change| change.previous != change.current)
+        .collect::<Vec<_>>();
+
+    changes.sort_by_key(|change| std::cmp::Reverse(change.delta()));
+
+    changes
+}
+
+/// Get the size of every sized entry, function and data section value of a build.
+///
+/// Values with the same key are summed, such as repeated unnamed constants.
+fn item_sizes(information: &AsmInformation) -> BTreeMap<(SizeItem, String), u64> {
+    let entries = information
+        .entries
+        .iter()
+        .filter_map(|entry| Some(((SizeItem::Entry, entry.name.clone()), entry.size?)));
+
+    let functions = information
+        .functions
+        .iter()
+        .map(|function| ((SizeItem::Function, function.name.clone()), function.size));
+
+    let constants = information
+        .data_section
+        .value_pairs
+        .iter()
+        .filter_map(|value| Some(((SizeItem::Constant, value.key()), value.size?)));
+
+    let mut sizes = BTreeMap::new();
+
+    for (key, size) in entries.chain(functions).chain(constants) {
+        *sizes.entry(key).or_insert(0) += size;
+    }
+
+    sizes
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_asm_information() {
+        // The unversioned information of forc
+        let information = AsmInformation::parse(serde_json::json!({
+            "bytecode_size": 1024,
+            "data_section": { "size": 64, "used": 48, "value_pairs": [{ "value": 1 }, { "value": 2 }] },
+        }))
+        .unwrap();
+
+        assert_eq!(information.schema_version, 0);
+        assert_eq!(information.bytecode_size, 1024);
+        assert_eq!(information.data_section.used, 48);
+        assert_eq!(information.data_section_values(), 2);
+        assert_eq!(information.program_kind(), None);
+
+        let information = AsmInformation::parse(serde_json::json!({
+            "schema_version": 1,
+            "bytecode_size": 8,
+            "entries": [{ "name": "main", "size": 4, "selector": "0x1" }],
+            "program": { "kind": "script" },
+            "source_hash": "abc",
+        }))
+        .unwrap();
+
+        assert_eq!(information.entries[0].name, "main");
+        assert_eq!(information.entries[0].size, Some(4));
+        assert_eq!(information.entries[0].extra["selector"], "0x1");
+        assert_eq!(information.program_kind(), Some("script"));
+        assert_eq!(information.extra["source_hash"], "abc");
+
+        // Unknown fields survive a round trip
+        let round_trip: AsmInformation =
+            serde_json::from_value(serde_json::to_value(&information).unwrap()).unwrap();
+        assert_eq!(round_trip, information);
+
+        assert!(AsmInformation::parse(serde_json::json!({ "schema_version": 2 })).is_err());
+        assert!(AsmInformation::parse(serde_json::json!([1, 2])).is_err());
+    }
+
+    #[test]
+    fn test_diff_sizes() {
+        let previous = AsmInformation::parse(serde_json::json!({
+            "entries": [{ "name": "transfer", "size": 100 }, { "name": "mint", "size": 40 }],
+            "functions": [{ "name": "hash", "size": 20 }, { "name": "unused", "size": 8 }],
+            "data_section": { "value_pairs": [{ "name": "OWNER", "size": 32 }, { "value": 1, "size": 8 }] },
+        }))
+        .unwrap();
+
+        let current = AsmInformation::parse(serde_json::json!({
+            "entries": [{ "name": "transfer", "size": 160 }, { "name": "mint", "size": 40 }, { "name": "burn" }],
+            "functions": [{ "name": "hash", "size": 12 }, { "name": "sqrt", "size": 24 }],
+            "data_section": { "value_pairs": [{ "name": "OWNER", "size": 32 }, { "value": 1, "size": 8 }] },
+        }))
+        .unwrap();
+
+        let changes = diff_sizes(&previous, &current);
+
+        assert_eq!(
+            changes
+                .iter()
+                .map(|change| (change.item, change.name.as_str(), change.delta()))
+                .collect::<Vec<_>>(),
+            vec![
+                (SizeItem::Entry, "transfer", 60),
+                (SizeItem::Function, "sqrt", 24),
+                (SizeItem::Function, "hash", -8),
+                (SizeItem::Function, "unused", -8),
+            ]
+        );
+        assert_eq!(changes[1].previous, None);
+        assert_eq!(changes[3].current, None);
+    }
+}
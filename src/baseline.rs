@@ -0,0 +1,422 @@
+use serde::Deserialize;
+use std::path::{Path, PathBuf};
+
+use crate::types::Benchmarks;
+use crate::wrap;
+
+/// The run that the current benchmarks are compared against
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Baseline {
+    /// A run file
+    File(PathBuf),
+    /// The latest run of a compiler binary, by MD5 hash
+    CompilerHash(String),
+    /// The latest run of a forc version
+    ForcVersion(String),
+    /// The latest run recorded with a label, usually a git ref
+    Label(String),
+    /// A run stored in the database, by id
+    Database(i32),
+    /// A pinned golden baseline, by name
+    Golden(String),
+    /// A run file path, compiler hash, forc version or label, tried in that order
+    Any(String),
+}
+
+impl std::str::FromStr for Baseline {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+
+        let non_empty = |kind: &str, value: &str| {
+            if value.is_empty() {
+                Err(format!("Invalid baseline \"{s}\", expected a {kind}"))
+            } else {
+                Ok(value.to_string())
+            }
+        };
+
+        let Some((kind, value)) = s.split_once(':') else {
+            return Ok(Baseline::Any(non_empty("value", s)?));
+        };
+
+        match kind {
+            "file" => Ok(Baseline::File(PathBuf::from(non_empty("path", value)?))),
+            "hash" => Ok(Baseline::CompilerHash(non_empty("compiler hash", value)?)),
+            "version" => Ok(Baseline::ForcVersion(non_empty("forc version", value)?)),
+            "label" => Ok(Baseline::Label(non_empty("label", value)?)),
+            "golden" => Ok(Baseline::Golden(validate_golden_name(value)?)),
+            "db" => value
+                .parse()
+                .map(Baseline::Database)
+                .map_err(|_| format!("Invalid baseline \"{s}\", expected a database run id")),
+            // Anything else, such as a Windows drive letter, is treated as a bare value
+            _ => Ok(Baseline::Any(s.to_string())),
+        }
+    }
+}
+
+impl std::fmt::Display for Baseline {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Baseline::File(path) => write!(f, "file {}", path.display()),
+            Baseline::CompilerHash(hash) => write!(f, "compiler hash {hash}"),
+            Baseline::ForcVersion(version) => write!(f, "forc version {version}"),
+            Baseline::Label(label) => write!(f, "label {label}"),
+            Baseline::Database(id) => write!(f, "database run {id}"),
+            Baseline::Golden(name) => write!(f, "golden baseline {name}"),
+            Baseline::Any(value) => write!(f, "{value}"),
+        }
+    }
+}
+
+/// Check that a golden baseline name can be used as a file name
+///
+/// # Errors
+///
+/// If the name is empty or contains anything other than alphanumerics, `-`, `_` and `.`
+///
+pub fn validate_golden_name(name: &str) -> Result<String, String> {
+    let valid = !name.is_empty()
+        && !name.starts_with('.')
+        && name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.'));
+
+    if valid {
+        Ok(name.to_string())
+    } else {
+        Err(format!(
+            "Invalid golden baseline name \"{name}\", expected alphanumerics, '-', '_' or '.'"
+        ))
+    }
+}
+
+/// The fields of a run file used to select a baseline, without the benchmark data
+#[derive(Deserialize)]
+pub struct RunHeader {
+    pub forc_version: String,
+    pub compiler_hash: String,
+    pub benchmarks_datetime: String,
+    #[serde(default)]
+    pub label: Option<String>,
+}
+
+/// A field of a run that a baseline can be selected by
+#[derive(Clone, Copy)]
+enum Field {
+    CompilerHash,
+    ForcVersion,
+    Label,
+}
+
+impl Field {
+    /// The name of the field in the serialized run
+    fn key(self) -> &'static str {
+        match self {
+            Field::CompilerHash => "compiler_hash",
+            Field::ForcVersion => "forc_version",
+            Field::Label => "label",
+        }
+    }
+
+    /// Check if the field of a run matches the value
+    fn matches(self, header: &RunHeader, value: &str) -> bool {
+        match self {
+            Field::CompilerHash => header.compiler_hash.eq_ignore_ascii_case(value),
+            Field::ForcVersion => header.forc_version == value,
+            Field::Label => header.label.as_deref() == Some(value),
+        }
+    }
+}
+
+/// Get the path of a golden baseline
+pub fn golden_path(output_folder: &Path, name: &str) -> PathBuf {
+    output_folder
+        .join(crate::BENCHMARKS_GOLDEN_FOLDER)
+        .join(format!("{name}.{}", crate::EXPORT_FILE_TYPE_JSON))
+}
+
+/// Pin a run as a named golden baseline, replacing any previous run pinned with the same name
+///
+/// # Arguments
+///
+/// * `benchmarks` - The run to pin
+///
+/// * `output_folder` - The benchmarks output folder
+///
+/// * `name` - The name of the golden baseline
+///
+/// # Errors
+///
+/// If the golden folder cannot be created.
+///
+/// If the run cannot be stored.
+///
+pub fn pin_golden(benchmarks: &Benchmarks, output_folder: &Path, name: &str) -> crate::Result<()> {
+    let golden_folder = output_folder.join(crate::BENCHMARKS_GOLDEN_FOLDER);
+
+    if !golden_folder.exists() {
+        std::fs::create_dir_all(&golden_folder).map_err(|e| wrap!(e.into()))?;
+    }
+
+    let path = golden_path(output_folder, name);
+    crate::utils::store_item(benchmarks, &path.to_string_lossy()).map_err(|e| wrap!(e))?;
+
+    println!("Pinned golden baseline : {name}");
+
+    Ok(())
+}
+
+/// Resolve a baseline to the run it selects
+///
+/// Runs selected by compiler hash, forc version or label are looked up in the runs folder first,
+/// newest first, and then in the database if a client is given.
+///
+/// # Arguments
+///
+/// * `baseline` - The baseline to resolve
+///
+/// * `output_folder` - The benchmarks output folder
+///
+/// * `client` - The database client, if database support is enabled
+///
+/// # Errors
+///
+/// If no run matches the baseline.
+///
+/// If a database run is selected without database support.
+///
+/// If the selected run cannot be read or deserialized.
+///
+pub async fn resolve(
+    baseline: &Baseline,
+    output_folder: &Path,
+    client: Option<&tokio_postgres::Client>,
+) -> crate::Result<Benchmarks> {
+    let found = match baseline {
+        Baseline::File(path) => Some(read_run(path).map_err(|e| wrap!(e))?),
+        Baseline::Golden(name) => {
+            let path = golden_path(output_folder, name);
+            if !path.exists() {
+                return Err(Box::new(wrap!(format!(
+                    "Golden baseline \"{name}\" has not been pinned, expected {}",
+                    path.display()
+                )
+                .into())));
+            }
+            Some(read_run(&path).map_err(|e| wrap!(e))?)
+        }
+        Baseline::Database(id) => {
+            let Some(client) = client else {
+                return Err(Box::new(wrap!(format!(
+                    "Baseline {baseline} requires database support"
+                )
+                .into())));
+            };
+            crate::database::get_benchmarks_by_id(client, *id)
+                .await
+                .map_err(|e| wrap!(e))?
+        }
+        Baseline::CompilerHash(value) => {
+            find_run(output_folder, client, &[Field::CompilerHash], value)
+                .await
+                .map_err(|e| wrap!(e))?
+        }
+        Baseline::ForcVersion(value) => {
+            find_run(output_folder, client, &[Field::ForcVersion], value)
+                .await
+                .map_err(|e| wrap!(e))?
+        }
+        Baseline::Label(value) => find_run(output_folder, client, &[Field::Label], value)
+            .await
+            .map_err(|e| wrap!(e))?,
+        Baseline::Any(value) if Path::new(value).is_file() => {
+            Some(read_run(Path::new(value)).map_err(|e| wrap!(e))?)
+        }
+        Baseline::Any(value) => find_run(
+            output_folder,
+            client,
+            &[Field::CompilerHash, Field::ForcVersion, Field::Label],
+            value,
+        )
+        .await
+        .map_err(|e| wrap!(e))?,
+    };
+
+    let Some(found) = found else {
+        return Err(Box::new(wrap!(format!(
+            "No run matches the baseline {baseline}"
+        )
+        .into())));
+    };
+
+    Ok(found)
+}
+
+/// Read and deserialize a run file
+fn read_run(path: &Path) -> crate::Result<Benchmarks> {
+    let run = std::fs::read_to_string(path).map_err(|e| wrap!(e.into()))?;
+    Ok(serde_json::from_str(&run).map_err(|e| wrap!(e.into()))?)
+}
+
+/// Find the newest run where any of the fields matches the value, trying each field in order
+async fn find_run(
+    output_folder: &Path,
+    client: Option<&tokio_postgres::Client>,
+    fields: &[Field],
+    value: &str,
+) -> crate::Result<Option<Benchmarks>> {
+    let runs_folder = output_folder.join(crate::BENCHMARKS_RUN_FOLDER);
+
+    let mut runs = if runs_folder.exists() {
+        crate::utils::get_files_in_dir(&runs_folder, crate::EXPORT_FILE_TYPE_JSON)
+            .map_err(|e| wrap!(e))?
+    } else {
+        vec![]
+    };
+
+    // Sort the runs newest first
+    runs.sort_by_key(|path| {
+        std::cmp::Reverse(
+            std::fs::metadata(path)
+                .and_then(|metadata| metadata.modified())
+                .unwrap_or(std::time::SystemTime::UNIX_EPOCH),
+        )
+    });
+
+    // Read only the headers of the runs, skipping files that are not runs
+    let headers: Vec<(PathBuf, RunHeader)> = runs
+        .into_iter()
+        .filter_map(|path| {
+            let run = std::fs::read_to_string(&path).ok()?;
+            let header = serde_json::from_str(&run).ok()?;
+            Some((path, header))
+        })
+        .collect();
+
+    for field in fields {
+        if let Some((path, _)) = headers
+            .iter()
+            .find(|(_, header)| field.matches(header, value))
+        {
+            return Ok(Some(read_run(path).map_err(|e| wrap!(e))?));
+        }
+    }
+
+    if let Some(client) = client {
+        for field in fields {
+            // Compiler hashes are stored as uppercase hexadecimal
+            let value = match field {
+                Field::CompilerHash => value.to_ascii_uppercase(),
+                Field::ForcVersion | Field::Label => value.to_string(),
+            };
+
+            let found = crate::database::find_latest_benchmarks(client, field.key(), &value)
+                .await
+                .map_err(|e| wrap!(e))?;
+
+            if found.is_some() {
+                return Ok(found);
+            }
+        }
+    }
+
+    Ok(None)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_baseline() {
+        assert_eq!(
+            "hash:ABC123".parse(),
+            Ok(Baseline::CompilerHash("ABC123".to_string()))
+        );
+        assert_eq!(
+            "version:0.61.0".parse(),
+            Ok(Baseline::ForcVersion("0.61.0".to_string()))
+        );
+        assert_eq!(
+            "label:refs/heads/master".parse(),
+            Ok(Baseline::Label("refs/heads/master".to_string()))
+        );
+        assert_eq!("db:42".parse(), Ok(Baseline::Database(42)));
+        assert_eq!(
+            "golden:master".parse(),
+            Ok(Baseline::Golden("master".to_string()))
+        );
+        assert_eq!(
+            "runs/a.json".parse(),
+            Ok(Baseline::Any("runs/a.json".to_string()))
+        );
+
+        assert!("db:latest".parse::<Baseline>().is_err());
+        assert!("golden:../escape".parse::<Baseline>().is_err());
+        assert!("hash:".parse::<Baseline>().is_err());
+    }
+
+    #[tokio::test]
+    async fn test_resolve_baseline() -> crate::Result<()> {
+        let output_folder =
+            std::env::temp_dir().join(format!("dyno-baseline-{}", std::process::id()));
+        let runs_folder = output_folder.join(crate::BENCHMARKS_RUN_FOLDER);
+        std::fs::create_dir_all(&runs_folder).map_err(|e| wrap!(e.into()))?;
+
+        let run = |forc_version: &str, compiler_hash: &str, label: Option<&str>| Benchmarks {
+            total_time: std::time::Duration::from_secs(1),
+            system_specs: crate::types::SystemSpecs::default(),
+            benchmarks: vec![],
+            forc_version: forc_version.to_string(),
+            compiler_hash: compiler_hash.to_string(),
+            benchmarks_datetime: String::new(),
+            target: PathBuf::new(),
+            label: label.map(ToString::to_string),
+            jobs: None,
+            environment: crate::environment::Environment::default(),
+        };
+
+        let older = runs_folder.join("older.json");
+        crate::utils::store_item(
+            &run("0.60.0", "AAAA", Some("v0.60.0")),
+            &older.to_string_lossy(),
+        )
+        .map_err(|e| wrap!(e))?;
+        let newer = runs_folder.join("newer.json");
+        crate::utils::store_item(
+            &run("0.61.0", "BBBB", Some("master")),
+            &newer.to_string_lossy(),
+        )
+        .map_err(|e| wrap!(e))?;
+
+        let resolved = resolve(&"hash:aaaa".parse()?, &output_folder, None).await?;
+        assert_eq!(resolved.forc_version, "0.60.0");
+
+        let resolved = resolve(&"master".parse()?, &output_folder, None).await?;
+        assert_eq!(resolved.compiler_hash, "BBBB");
+
+        let resolved = resolve(&Baseline::File(older), &output_folder, None).await?;
+        assert_eq!(resolved.compiler_hash, "AAAA");
+
+        assert!(resolve(&"golden:master".parse()?, &output_folder, None)
+            .await
+            .is_err());
+        pin_golden(&run("0.59.0", "CCCC", None), &output_folder, "master")?;
+        let resolved = resolve(&"golden:master".parse()?, &output_folder, None).await?;
+        assert_eq!(resolved.compiler_hash, "CCCC");
+
+        assert!(resolve(&"db:1".parse()?, &output_folder, None)
+            .await
+            .is_err());
+        assert!(resolve(&"version:9.9.9".parse()?, &output_folder, None)
+            .await
+            .is_err());
+
+        std::fs::remove_dir_all(&output_folder).map_err(|e| wrap!(e.into()))?;
+
+        Ok(())
+    }
+}
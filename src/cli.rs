@@ -1,5 +1,8 @@
-use clap::Parser;
-use std::path::PathBuf;
+use clap::{Args, Parser, Subcommand};
+use std::path::{Path, PathBuf};
+use std::time::Instant;
+
+use crate::wrap;
 
 #[derive(Parser)]
 #[clap(name = "Forc Performance Tool")]
@@ -8,35 +11,671 @@ use std::path::PathBuf;
 )]
 #[clap(version = "1.0")]
 #[clap(about = "Fuel Orchestrator Performance Profiling & Benchmarking", long_about = None)]
-pub struct Options {
+pub struct Cli {
+    #[clap(long, global = true)]
+    /// Path to a config file, by default `dyno.toml` or the `[dyno]` table of `Forc.toml` in the working directory (Optional)
+    pub config: Option<PathBuf>,
+
+    #[clap(subcommand)]
+    pub command: Command,
+}
+
+#[derive(Subcommand)]
+pub enum Command {
+    /// Profile and benchmark the projects of a target
+    Run(Box<RunArgs>),
+    /// Compare two stored runs without building anything
+    Compare(CompareOptions),
+    /// Render a stored stats file
+    Report(ReportOptions),
+    /// List the stored runs
+    History(HistoryOptions),
+    /// Manage the benchmarks database
+    #[clap(subcommand)]
+    Db(DbCommand),
+}
+
+#[derive(Args)]
+pub struct RunArgs {
     #[clap(short, long)]
-    /// A path to a target folder or file to compile
-    pub target: PathBuf,
+    /// A path to a target folder or file to compile, repeatable (Optional with a config file)
+    pub target: Vec<PathBuf>,
 
     #[clap(short, long)]
-    /// The path to the forc binary compiled with --features profiler
-    pub forc_path: PathBuf,
+    /// The path to the forc binary compiled with --features profiler (Optional with a config file)
+    pub forc_path: Option<PathBuf>,
 
-    #[clap(short, long, default_value = "./benchmarks")]
-    pub output_folder: PathBuf,
+    #[clap(long, conflicts_with = "baseline")]
+    /// The path to a baseline forc binary, built interleaved with `forc_path` and compared against it, which requires `--mode cold` (Optional)
+    pub baseline_forc_path: Option<PathBuf>,
+
+    #[clap(short, long)]
+    /// The benchmarks output folder (Optional) [default: ./benchmarks]
+    pub output_folder: Option<PathBuf>,
+
+    #[clap(short, long, value_name = "BOOL", num_args = 0..=1, require_equals = true, default_missing_value = "true")]
+    /// Enable printing output, the same as adding the `table` format, `=false` overrides the configuration (Optional)
+    pub print_output: Option<bool>,
 
-    #[clap[short, long, default_value = "false"]]
-    /// Enable printing output (Optional)
-    pub print_output: bool,
+    #[clap(long, value_name = "FORMAT")]
+    /// Format of the comparison, repeatable: `json` stores the stats file and `table` prints the stats (Optional) [default: json]
+    pub format: Vec<crate::types::OutputFormat>,
 
     #[clap(long)]
-    /// Flamegraph support (Optional)
-    pub flamegraph: bool,
+    /// Number of measured builds per benchmark (Optional) [default: 1]
+    pub iterations: Option<u32>,
 
     #[clap(long)]
-    /// Enable hyperfine analysis (Optional)
-    pub hyperfine: bool,
+    /// Number of unmeasured warmup builds per benchmark, at least 1 in the `warm` and `incremental` modes (Optional) [default: 0 in the `cold` mode, 1 otherwise]
+    pub warmup: Option<u32>,
+
+    #[clap(long, value_name = "MODE")]
+    /// How each build is prepared: `cold` removes the build outputs first, `warm` reuses the outputs of a priming build and `incremental` touches a source file first (Optional) [default: warm]
+    pub mode: Option<crate::types::BuildMode>,
+
+    #[clap(long, value_name = "PATH")]
+    /// Source file touched before each incremental build, relative to the project (Optional) [default: src/main.sw or src/lib.sw]
+    pub touch: Option<PathBuf>,
+
+    #[clap(long, value_name = "NAME[=COMMAND]")]
+    /// A forc command to run against each project, repeatable: a configured workload, `build`, or a definition such as `release=build --release` with optional leading `KEY=VALUE` variables (Optional) [default: the configured workloads, or build]
+    pub workload: Vec<crate::workload::WorkloadArg>,
+
+    #[clap(long, value_name = "MS")]
+    /// Interval between performance frames in milliseconds, down to 1 (Optional) [default: 100]
+    pub sampling_interval: Option<u64>,
+
+    #[clap(long, value_name = "BOOL", num_args = 0..=1, require_equals = true, default_missing_value = "true")]
+    /// Collect instructions, cycles, cache misses, branch misses, context switches and page faults per build and phase, Linux only, `=false` overrides the configuration (Optional)
+    pub counters: Option<bool>,
+
+    #[clap(long, value_name = "N")]
+    /// Number of benchmarks built concurrently, each pinned to its own set of CPUs, Linux only (Optional) [default: 1]
+    pub jobs: Option<u32>,
+
+    #[clap(long, value_name = "CPUS")]
+    /// CPUs to pin forc to, such as `2,3` or `2-5`, split between the jobs, Linux only (Optional)
+    pub cpus: Option<String>,
+
+    #[clap(long, value_name = "NICENESS", allow_negative_numbers = true)]
+    /// Scheduling priority of forc, from -20 (highest) to 19 (lowest) (Optional)
+    pub nice: Option<i32>,
+
+    #[clap(long, value_name = "CLASS[:LEVEL]")]
+    /// I/O priority of forc: `realtime`, `best-effort` or `idle`, with a level from 0 (highest) to 7, Linux only (Optional)
+    pub ionice: Option<crate::environment::IoPriority>,
+
+    #[clap(long, value_name = "GOVERNOR")]
+    /// CPU frequency governor to set for the run, such as `performance`, restored afterwards, Linux only (Optional)
+    pub governor: Option<String>,
+
+    #[clap(long, value_name = "BOOL", num_args = 0..=1, require_equals = true, default_missing_value = "true")]
+    /// Warn when the CPUs do not use the `performance` frequency governor, Linux only, `=false` overrides the configuration (Optional)
+    pub check_governor: Option<bool>,
+
+    #[clap(long, value_name = "BOOL", num_args = 0..=1, require_equals = true, default_missing_value = "true")]
+    /// Disable address space layout randomization for forc, Linux only, `=false` overrides the configuration (Optional)
+    pub disable_aslr: Option<bool>,
+
+    #[clap(long, value_name = "SECONDS")]
+    /// Time limit of the builds of each benchmark, after which the build is killed and the benchmark marked as timed out (Optional)
+    pub timeout: Option<u64>,
+
+    #[clap(long, value_name = "SECONDS")]
+    /// Time limit of the whole run, after which the remaining benchmarks are marked as timed out (Optional)
+    pub total_timeout: Option<u64>,
+
+    #[clap(long, value_name = "GLOB")]
+    /// Only profile the projects whose path relative to the target matches a glob, repeatable (Optional)
+    pub include: Vec<String>,
 
-    #[clap(long, requires = "hyperfine", default_value = "2")]
-    /// Maximum iterations for hyperfine (Optional)
+    #[clap(long, value_name = "GLOB")]
+    /// Skip the projects whose path relative to the target matches a glob, repeatable (Optional)
+    pub exclude: Vec<String>,
+
+    #[clap(long, value_name = "DEPTH")]
+    /// Maximum depth of the projects below the target, which is at depth 0 (Optional)
+    pub max_depth: Option<usize>,
+
+    #[clap(long, value_name = "PATH")]
+    /// Path to a suite manifest that tags the projects of the target (Optional)
+    pub suite: Option<PathBuf>,
+
+    #[clap(long, value_name = "TAG")]
+    /// Only profile the projects with a tag of the suite manifest, repeatable (Optional)
+    pub tag: Vec<String>,
+
+    #[clap(long, value_name = "BOOL", num_args = 0..=1, require_equals = true, default_missing_value = "true")]
+    /// Flamegraph support, `=false` overrides the configuration (Optional)
+    pub flamegraph: Option<bool>,
+
+    #[clap(long, value_name = "BOOL", num_args = 0..=1, require_equals = true, default_missing_value = "true")]
+    /// Enable hyperfine analysis, `=false` overrides the configuration (Optional)
+    pub hyperfine: Option<bool>,
+
+    #[clap(long)]
+    /// Maximum iterations for hyperfine (Optional) [default: 2]
+    pub max_iterations: Option<u32>,
+
+    #[clap(short, long, value_name = "BOOL", num_args = 0..=1, require_equals = true, default_missing_value = "true")]
+    /// Database support, `=false` overrides the configuration (Optional)
+    pub database: Option<bool>,
+
+    #[clap(long, value_name = "BASELINE")]
+    /// The run to compare against: a run file, `hash:<md5>`, `version:<forc version>`, `label:<label>`, `db:<run id>` or `golden:<name>` (Optional)
+    pub baseline: Option<crate::baseline::Baseline>,
+
+    #[clap(long)]
+    /// A label recorded with the run, such as the git ref of the compiler (Optional)
+    pub label: Option<String>,
+
+    #[clap(long, value_name = "NAME", value_parser = crate::baseline::validate_golden_name)]
+    /// Pin the run as the named golden baseline (Optional)
+    pub pin_golden: Option<String>,
+
+    #[clap(long, value_name = "METRIC=VALUE", value_parser = crate::thresholds::parse_metric_threshold)]
+    /// Regression threshold of a metric, either absolute (`time=500`) or a percentage (`time=5%`) (Optional)
+    pub threshold: Vec<(String, crate::thresholds::Threshold)>,
+
+    #[clap(long)]
+    /// Path to a JSON file with default and per project regression thresholds (Optional)
+    pub thresholds_file: Option<PathBuf>,
+}
+
+/// The settings of a run, combined from the command line and the configuration
+pub struct Options {
+    /// The paths to the target folders or files to compile
+    pub targets: Vec<PathBuf>,
+    /// The path to the forc binary
+    pub forc_path: PathBuf,
+    /// The path to a baseline forc binary for A/B mode
+    pub baseline_forc_path: Option<PathBuf>,
+    /// The benchmarks output folder
+    pub output_folder: PathBuf,
+    /// The formats of the comparison
+    pub formats: Vec<crate::types::OutputFormat>,
+    /// The number of measured builds per benchmark
+    pub iterations: u32,
+    /// The number of unmeasured warmup builds per benchmark
+    pub warmup: u32,
+    /// How each build is prepared
+    pub mode: crate::types::BuildMode,
+    /// The source file touched before each incremental build, relative to the project
+    pub touch: Option<PathBuf>,
+    /// The forc commands run against each project
+    pub workloads: Vec<crate::workload::Workload>,
+    /// The interval between performance frames
+    pub sampling_interval: std::time::Duration,
+    /// Whether to collect the performance counters of each build and phase
+    pub counters: bool,
+    /// The number of benchmarks built concurrently
+    pub jobs: u32,
+    /// The noise reduction controls of the builds
+    pub environment: crate::environment::Controls,
+    /// The time limit of the builds of each benchmark
+    pub timeout: Option<std::time::Duration>,
+    /// The time limits of specific benchmarks, keyed by project path or name
+    pub timeouts: std::collections::BTreeMap<String, std::time::Duration>,
+    /// The time limit of the whole run
+    pub total_timeout: Option<std::time::Duration>,
+    /// Globs of the project paths to profile
+    pub include: Vec<String>,
+    /// Globs of the project paths to skip
+    pub exclude: Vec<String>,
+    /// The maximum depth of the projects below the target
+    pub max_depth: Option<usize>,
+    /// The suite manifest tagging the projects
+    pub suite: crate::suite::Suite,
+    /// The tags of the projects to profile
+    pub tags: Vec<String>,
+    /// Whether to generate flamegraphs
+    pub flamegraph: bool,
+    /// Whether to run the hyperfine analysis
+    pub hyperfine: bool,
+    /// The maximum iterations of the hyperfine analysis
     pub max_iterations: u32,
+    /// The database settings, if database support is enabled
+    pub database: Option<crate::config::DatabaseConfig>,
+    /// The run to compare against
+    pub baseline: Option<crate::baseline::Baseline>,
+    /// The label recorded with the run
+    pub label: Option<String>,
+    /// The name of the golden baseline to pin the run as
+    pub pin_golden: Option<String>,
+    /// The regression thresholds
+    pub thresholds: crate::thresholds::Thresholds,
+}
+
+impl RunArgs {
+    /// Combine the command line arguments with the configuration, the command line taking precedence
+    ///
+    /// # Errors
+    ///
+    /// If no target or forc path is given on the command line or in the configuration.
+    ///
+    /// If a source file to touch is given outside of the incremental mode.
+    ///
+    /// If a workload is unknown, invalid or given twice.
+    ///
+    /// If the sampling interval is zero.
+    ///
+    /// If the number of jobs is zero.
+    ///
+    /// If the CPU list, niceness or I/O priority is invalid, or a control is not supported on
+    /// this platform.
+    ///
+    /// If a timeout is zero.
+    ///
+    /// If the configured baseline is invalid.
+    ///
+    /// If the suite manifest cannot be loaded, or a selected tag is not defined in it.
+    ///
+    /// If the thresholds file cannot be loaded.
+    ///
+    pub fn resolve(self, config: &crate::config::Config) -> crate::Result<Options> {
+        let targets = if self.target.is_empty() {
+            config.targets.clone()
+        } else {
+            self.target
+        };
+
+        if targets.is_empty() {
+            return Err(Box::new(wrap!(
+                "No target given, pass --target or set targets in the config file".into()
+            )));
+        }
+
+        let Some(forc_path) = self.forc_path.or_else(|| config.forc_path.clone()) else {
+            return Err(Box::new(wrap!(
+                "No forc path given, pass --forc-path or set forc_path in the config file".into()
+            )));
+        };
+
+        let mode = self.mode.or(config.mode).unwrap_or_default();
+        let touch = self.touch.or_else(|| config.touch.clone());
+
+        if touch.is_some() && mode != crate::types::BuildMode::Incremental {
+            return Err(Box::new(wrap!(
+                "A source file to touch is only used in the incremental mode".into()
+            )));
+        }
+
+        let workloads = crate::workload::resolve(&self.workload, &config.workloads)
+            .map_err(|e| wrap!(e.into()))?;
+
+        let sampling_interval = self.sampling_interval.or(config.sampling_interval).map_or(
+            crate::types::BenchmarkFrame::DEFAULT_SAMPLING_INTERVAL,
+            std::time::Duration::from_millis,
+        );
+
+        if sampling_interval < crate::types::BenchmarkFrame::MINIMUM_SAMPLING_INTERVAL {
+            return Err(Box::new(wrap!(
+                "The sampling interval must be at least 1 ms".into()
+            )));
+        }
+
+        let jobs = self.jobs.or(config.jobs).unwrap_or(1);
+
+        if jobs == 0 {
+            return Err(Box::new(wrap!(
+                "The number of jobs must be at least 1".into()
+            )));
+        }
+
+        let environment = crate::environment::Controls {
+            cpus: match self.cpus.as_ref().or(config.cpus.as_ref()) {
+                Some(cpus) => crate::affinity::parse_cpu_list(cpus).map_err(|e| wrap!(e.into()))?,
+                None => vec![],
+            },
+            nice: self.nice.or(config.nice),
+            io_priority: match (self.ionice, &config.ionice) {
+                (Some(io_priority), _) => Some(io_priority),
+                (None, Some(io_priority)) => {
+                    Some(io_priority.parse().map_err(|e: String| wrap!(e.into()))?)
+                }
+                (None, None) => None,
+            },
+            governor: self.governor.or_else(|| config.governor.clone()),
+            check_governor: self
+                .check_governor
+                .or(config.check_governor)
+                .unwrap_or(false),
+            disable_aslr: self.disable_aslr.or(config.disable_aslr).unwrap_or(false),
+        };
+
+        if environment
+            .nice
+            .is_some_and(|nice| !(-20..=19).contains(&nice))
+        {
+            return Err(Box::new(wrap!(
+                "The niceness must be between -20 and 19".into()
+            )));
+        }
+
+        let linux_only = [
+            ("--cpus", !environment.cpus.is_empty()),
+            ("--ionice", environment.io_priority.is_some()),
+            ("--governor", environment.governor.is_some()),
+            ("--check-governor", environment.check_governor),
+            ("--disable-aslr", environment.disable_aslr),
+        ];
+
+        if let Some((control, _)) = linux_only
+            .iter()
+            .find(|(_, enabled)| *enabled && !cfg!(target_os = "linux"))
+        {
+            return Err(Box::new(wrap!(format!(
+                "{control} is only supported on Linux"
+            )
+            .into())));
+        }
+
+        let timeout = self.timeout.or(config.timeout);
+        let total_timeout = self.total_timeout.or(config.total_timeout);
+
+        if timeout == Some(0)
+            || total_timeout == Some(0)
+            || config.timeouts.values().any(|t| *t == 0)
+        {
+            return Err(Box::new(
+                wrap!("A timeout must be at least 1 second".into()),
+            ));
+        }
+
+        let baseline_forc_path = self
+            .baseline_forc_path
+            .or_else(|| config.baseline_forc_path.clone());
+
+        // Both binaries build the same project folder, so a warm build of one binary would
+        // reuse the outputs and caches of the other
+        if baseline_forc_path.is_some() && mode != crate::types::BuildMode::Cold {
+            return Err(Box::new(wrap!(format!(
+                "Comparing against a baseline forc binary requires `--mode cold`, since both binaries share the build outputs of each project, but the mode is `{mode}`"
+            )
+            .into())));
+        }
+
+        // A/B mode compares against the baseline binary, so a configured baseline does not apply
+        let baseline = match (self.baseline, &config.baseline) {
+            (Some(baseline), _) => Some(baseline),
+            (None, Some(baseline)) if baseline_forc_path.is_none() => {
+                Some(baseline.parse().map_err(|e: String| wrap!(e.into()))?)
+            }
+            (None, _) => None,
+        };
+
+        let thresholds = crate::thresholds::Thresholds::resolve(
+            &config.thresholds,
+            self.thresholds_file
+                .as_deref()
+                .or(config.thresholds_file.as_deref()),
+            &self.threshold,
+        )
+        .map_err(|e| wrap!(e))?;
+
+        let suite = match self.suite.as_deref().or(config.suite.as_deref()) {
+            Some(path) => crate::suite::Suite::load(path).map_err(|e| wrap!(e))?,
+            None => crate::suite::Suite::default(),
+        };
+
+        let tags = if self.tag.is_empty() {
+            config.tags.clone()
+        } else {
+            self.tag
+        };
+
+        suite
+            .check_tags(&tags)
+            .map_err(|e: String| wrap!(e.into()))?;
+
+        let mut formats = if self.format.is_empty() {
+            config
+                .formats
+                .clone()
+                .unwrap_or_else(|| vec![crate::types::OutputFormat::Json])
+        } else {
+            self.format
+        };
+
+        // Printing output is a shorthand for the table format
+        match self.print_output.or(config.print_output) {
+            Some(true) if !formats.contains(&crate::types::OutputFormat::Table) => {
+                formats.push(crate::types::OutputFormat::Table);
+            }
+            Some(false) => formats.retain(|format| *format != crate::types::OutputFormat::Table),
+            _ => {}
+        }
+
+        let database = if self.database.unwrap_or(config.database.enabled) {
+            Some(config.database.clone())
+        } else {
+            None
+        };
+
+        Ok(Options {
+            targets,
+            forc_path,
+            baseline_forc_path,
+            output_folder: self
+                .output_folder
+                .or_else(|| config.output_folder.clone())
+                .unwrap_or_else(|| PathBuf::from("./benchmarks")),
+            formats,
+            iterations: self.iterations.or(config.iterations).unwrap_or(1),
+            warmup: self.warmup.or(config.warmup).unwrap_or(0),
+            mode,
+            touch,
+            workloads,
+            sampling_interval,
+            counters: self.counters.or(config.counters).unwrap_or(false),
+            jobs,
+            environment,
+            timeout: timeout.map(std::time::Duration::from_secs),
+            timeouts: config
+                .timeouts
+                .iter()
+                .map(|(project, timeout)| {
+                    (project.clone(), std::time::Duration::from_secs(*timeout))
+                })
+                .collect(),
+            total_timeout: total_timeout.map(std::time::Duration::from_secs),
+            include: if self.include.is_empty() {
+                config.include.clone()
+            } else {
+                self.include
+            },
+            exclude: if self.exclude.is_empty() {
+                config.exclude.clone()
+            } else {
+                self.exclude
+            },
+            max_depth: self.max_depth.or(config.max_depth),
+            suite,
+            tags,
+            flamegraph: self.flamegraph.or(config.flamegraph).unwrap_or(false),
+            hyperfine: self.hyperfine.or(config.hyperfine).unwrap_or(false),
+            max_iterations: self.max_iterations.or(config.max_iterations).unwrap_or(2),
+            database,
+            baseline,
+            label: self.label,
+            pin_golden: self.pin_golden,
+            thresholds,
+        })
+    }
+}
+
+impl Options {
+    /// Get the deadline of the builds of a benchmark that starts now
+    ///
+    /// The timeout of a benchmark is the first entry of `timeouts` whose key is the name or a
+    /// trailing part of the path of the benchmark, falling back to `timeout`. The deadline
+    /// never extends past the end of the total timeout of the run.
+    ///
+    /// # Arguments
+    ///
+    /// * `epoch` - The start of the run.
+    ///
+    /// * `path` - The path of the benchmark's project.
+    ///
+    pub fn deadline(&self, epoch: &Instant, path: &Path) -> Option<Instant> {
+        let timeout = self
+            .timeouts
+            .iter()
+            .find(|(project, _)| path.ends_with(project))
+            .map(|(_, timeout)| *timeout)
+            .or(self.timeout);
+
+        let benchmark_deadline = timeout.map(|timeout| Instant::now() + timeout);
+        let run_deadline = self.total_timeout.map(|timeout| *epoch + timeout);
+
+        match (benchmark_deadline, run_deadline) {
+            (Some(benchmark_deadline), Some(run_deadline)) => {
+                Some(benchmark_deadline.min(run_deadline))
+            }
+            (deadline, None) | (None, deadline) => deadline,
+        }
+    }
+}
+
+#[derive(Args)]
+pub struct CompareOptions {
+    /// The path to the previous run file
+    pub previous: PathBuf,
+
+    /// The path to the current run file
+    pub current: PathBuf,
+
+    #[clap(short, long)]
+    /// The path to store the stats of the comparison at (Optional)
+    pub output: Option<PathBuf>,
+
+    #[clap(long, value_name = "METRIC=VALUE", value_parser = crate::thresholds::parse_metric_threshold)]
+    /// Regression threshold of a metric, either absolute (`time=500`) or a percentage (`time=5%`) (Optional)
+    pub threshold: Vec<(String, crate::thresholds::Threshold)>,
+
+    #[clap(long)]
+    /// Path to a JSON file with default and per project regression thresholds (Optional)
+    pub thresholds_file: Option<PathBuf>,
+}
+
+#[derive(Args)]
+pub struct ReportOptions {
+    /// The path to the stats file
+    pub stats: PathBuf,
+}
+
+#[derive(Args)]
+pub struct HistoryOptions {
+    #[clap(short, long)]
+    /// The benchmarks output folder (Optional) [default: ./benchmarks]
+    pub output_folder: Option<PathBuf>,
 
     #[clap(short, long)]
-    /// Database support (Optional)
+    /// List the runs stored in the database instead of the output folder (Optional)
     pub database: bool,
 }
+
+#[derive(Subcommand)]
+pub enum DbCommand {
+    /// Create the database schema if it does not exist
+    Migrate,
+    /// Insert stored run files into the database
+    Import {
+        /// The paths to the run files
+        #[clap(required = true)]
+        runs: Vec<PathBuf>,
+    },
+    /// Write a run stored in the database to a file
+    Export {
+        /// The id of the run
+        id: i32,
+
+        /// The path of the run file to write
+        output: PathBuf,
+    },
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_resolve_flags() -> crate::Result<()> {
+        let run_args = |args: &[&str]| -> crate::Result<RunArgs> {
+            let cli = Cli::try_parse_from(["dyno", "run"].iter().chain(args))
+                .map_err(|e| wrap!(Box::new(e)))?;
+
+            match cli.command {
+                Command::Run(run_args) => Ok(*run_args),
+                _ => unreachable!(),
+            }
+        };
+
+        let config = crate::config::Config {
+            targets: vec![PathBuf::from("/projects")],
+            forc_path: Some(PathBuf::from("forc")),
+            print_output: Some(true),
+            flamegraph: Some(true),
+            ..Default::default()
+        };
+
+        // The configuration applies without flags
+        let options = run_args(&[])?.resolve(&config)?;
+        assert_eq!(
+            options.formats,
+            vec![
+                crate::types::OutputFormat::Json,
+                crate::types::OutputFormat::Table
+            ]
+        );
+        assert!(options.flamegraph);
+        assert!(!options.hyperfine);
+
+        // The command line turns configured settings off and on
+        let options = run_args(&["--print-output=false", "--flamegraph=false", "--hyperfine"])?
+            .resolve(&config)?;
+        assert_eq!(options.formats, vec![crate::types::OutputFormat::Json]);
+        assert!(!options.flamegraph);
+        assert!(options.hyperfine);
+
+        let options = run_args(&["-p", "--format", "table"])?.resolve(&crate::config::Config {
+            print_output: Some(false),
+            formats: Some(vec![crate::types::OutputFormat::Json]),
+            ..config
+        })?;
+        assert_eq!(options.formats, vec![crate::types::OutputFormat::Table]);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_resolve_baseline_mode() -> crate::Result<()> {
+        let config = crate::config::Config {
+            targets: vec![PathBuf::from("/projects")],
+            forc_path: Some(PathBuf::from("forc")),
+            ..Default::default()
+        };
+
+        let resolve = |args: &[&str]| {
+            Cli::try_parse_from(
+                ["dyno", "run", "--baseline-forc-path", "main-forc"]
+                    .iter()
+                    .chain(args),
+            )
+            .map_err(|e| wrap!(Box::new(e)))
+            .map(|cli| match cli.command {
+                Command::Run(run_args) => run_args.resolve(&config),
+                _ => unreachable!(),
+            })
+        };
+
+        // Warm builds of one binary would reuse the outputs of the other
+        assert!(resolve(&[])?.is_err());
+        assert!(resolve(&["--mode", "incremental"])?.is_err());
+
+        let options = resolve(&["--mode", "cold"])??;
+        assert_eq!(options.baseline_forc_path, Some(PathBuf::from("main-forc")));
+
+        Ok(())
+    }
+}
@@ -0,0 +1,227 @@
+use crate::cli::{CompareOptions, DbCommand, HistoryOptions, ReportOptions};
+use crate::config::Config;
+use crate::types::Benchmarks;
+use crate::wrap;
+
+/// Read and deserialize a JSON file
+fn read_json<T: serde::de::DeserializeOwned>(path: &std::path::Path) -> crate::Result<T> {
+    let json = std::fs::read_to_string(path).map_err(|e| wrap!(e.into()))?;
+    Ok(serde_json::from_str(&json).map_err(|e| wrap!(e.into()))?)
+}
+
+/// Compare two stored runs without building anything.
+///
+/// # Arguments
+///
+/// * `options` - A reference to a `cli::CompareOptions`.
+///
+/// * `config` - The configuration, providing the configured thresholds.
+///
+/// # Returns
+///
+/// The metrics that regressed beyond their thresholds, and the benchmarks whose build failed
+/// in the current run.
+///
+/// # Errors
+///
+/// If the thresholds file cannot be loaded.
+///
+/// If either run file cannot be read or deserialized.
+///
+/// If the calculation of the performance regression or improvements fails.
+///
+/// If the stats cannot be stored.
+///
+pub fn compare(
+    options: &CompareOptions,
+    config: &Config,
+) -> crate::Result<crate::thresholds::Outcome> {
+    let regression_thresholds = crate::thresholds::Thresholds::resolve(
+        &config.thresholds,
+        options
+            .thresholds_file
+            .as_deref()
+            .or(config.thresholds_file.as_deref()),
+        &options.threshold,
+    )
+    .map_err(|e| wrap!(e))?;
+
+    let previous_benchmarks: Benchmarks = read_json(&options.previous).map_err(|e| wrap!(e))?;
+    let current_benchmarks: Benchmarks = read_json(&options.current).map_err(|e| wrap!(e))?;
+
+    let stats_result =
+        crate::stats::compare(&previous_benchmarks, &current_benchmarks).map_err(|e| wrap!(e))?;
+
+    if let Some(output) = &options.output {
+        crate::utils::store_item(&stats_result, &output.to_string_lossy()).map_err(|e| wrap!(e))?;
+    }
+
+    crate::utils::print_stats(&stats_result);
+    crate::utils::print_asm_information(&previous_benchmarks, &current_benchmarks)
+        .map_err(|e| wrap!(e))?;
+
+    let outcome = crate::thresholds::Outcome::check(&stats_result, &regression_thresholds);
+    crate::utils::print_outcome(&outcome);
+
+    Ok(outcome)
+}
+
+/// Render a stored stats file.
+///
+/// # Arguments
+///
+/// * `options` - A reference to a `cli::ReportOptions`.
+///
+/// # Errors
+///
+/// If the stats file cannot be read or deserialized, or was written by a version of `dyno`
+/// whose stats cannot be read anymore.
+///
+pub fn report(options: &ReportOptions) -> crate::Result<()> {
+    let json = std::fs::read_to_string(&options.stats).map_err(|e| wrap!(e.into()))?;
+    let stats_result = crate::stats::Collection::from_json(&json).map_err(|e| wrap!(e.into()))?;
+
+    crate::utils::print_stats(&stats_result);
+
+    Ok(())
+}
+
+/// List the runs stored in the output folder or the database.
+///
+/// # Arguments
+///
+/// * `options` - A reference to a `cli::HistoryOptions`.
+///
+/// * `config` - The configuration, providing the output folder and the database settings.
+///
+/// # Errors
+///
+/// If the runs folder cannot be read.
+///
+/// If the setup of the database or the query of the runs fails.
+///
+pub async fn history(options: &HistoryOptions, config: &Config) -> crate::Result<()> {
+    let history_rows = if options.database {
+        let client = crate::database::setup(&config.database)
+            .await
+            .map_err(|e| wrap!(e))?;
+
+        crate::database::list_runs(&client)
+            .await
+            .map_err(|e| wrap!(e))?
+            .into_iter()
+            .map(|run| crate::utils::HistoryRow {
+                run: format!("db:{}", run.id),
+                date: run.date,
+                forc_version: run.forc_version.unwrap_or_default(),
+                compiler_hash: run.compiler_hash.unwrap_or_default(),
+                label: run.label.unwrap_or_default(),
+            })
+            .collect()
+    } else {
+        let output_folder = options
+            .output_folder
+            .clone()
+            .or_else(|| config.output_folder.clone())
+            .unwrap_or_else(|| std::path::PathBuf::from("./benchmarks"));
+
+        let runs_folder = output_folder.join(crate::BENCHMARKS_RUN_FOLDER);
+
+        let mut runs = crate::utils::get_files_in_dir(&runs_folder, crate::EXPORT_FILE_TYPE_JSON)
+            .map_err(|e| wrap!(e))?;
+
+        // Sort the runs newest first
+        runs.sort_by_key(|path| {
+            std::cmp::Reverse(
+                std::fs::metadata(path)
+                    .and_then(|metadata| metadata.modified())
+                    .unwrap_or(std::time::SystemTime::UNIX_EPOCH),
+            )
+        });
+
+        // Skip files that are not runs
+        runs.iter()
+            .filter_map(|path| {
+                let header: crate::baseline::RunHeader = read_json(path).ok()?;
+                Some(crate::utils::HistoryRow {
+                    run: path.display().to_string(),
+                    date: header.benchmarks_datetime,
+                    forc_version: header.forc_version,
+                    compiler_hash: header.compiler_hash,
+                    label: header.label.unwrap_or_default(),
+                })
+            })
+            .collect()
+    };
+
+    crate::utils::print_history(history_rows);
+
+    Ok(())
+}
+
+/// Manage the benchmarks database.
+///
+/// # Arguments
+///
+/// * `command` - A reference to a `cli::DbCommand`.
+///
+/// * `config` - The configuration, providing the database settings.
+///
+/// # Errors
+///
+/// If the setup of the database fails.
+///
+/// If the creation of the schema fails.
+///
+/// If a run file cannot be read, deserialized or inserted.
+///
+/// If the exported run does not exist or cannot be stored.
+///
+pub async fn db(command: &DbCommand, config: &Config) -> crate::Result<()> {
+    let client = crate::database::setup(&config.database)
+        .await
+        .map_err(|e| wrap!(e))?;
+
+    match command {
+        DbCommand::Migrate => {
+            crate::database::create_schema(&client)
+                .await
+                .map_err(|e| wrap!(e))?;
+
+            println!("Created the database schema");
+        }
+
+        DbCommand::Import { runs } => {
+            crate::database::create_schema(&client)
+                .await
+                .map_err(|e| wrap!(e))?;
+
+            for path in runs {
+                let benchmarks: Benchmarks = read_json(path).map_err(|e| wrap!(e))?;
+
+                crate::database::insert_benchmarks(&client, &benchmarks)
+                    .await
+                    .map_err(|e| wrap!(e))?;
+
+                println!("Imported run : {}", path.display());
+            }
+        }
+
+        DbCommand::Export { id, output } => {
+            let Some(benchmarks) = crate::database::get_benchmarks_by_id(&client, *id)
+                .await
+                .map_err(|e| wrap!(e))?
+            else {
+                return Err(Box::new(wrap!(format!(
+                    "No run with id {id} in the database"
+                )
+                .into())));
+            };
+
+            crate::utils::store_item(&benchmarks, &output.to_string_lossy())
+                .map_err(|e| wrap!(e))?;
+        }
+    }
+
+    Ok(())
+}
@@ -0,0 +1,311 @@
+use serde::Deserialize;
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+use crate::thresholds::Thresholds;
+use crate::wrap;
+
+/// The name of the configuration file discovered in the working directory
+pub const CONFIG_FILE_NAME: &str = "dyno.toml";
+
+/// The workspace file searched for a `[dyno]` table when there is no configuration file
+pub const WORKSPACE_FILE_NAME: &str = "Forc.toml";
+
+/// The table holding the configuration inside a workspace file
+const WORKSPACE_TABLE: &str = "dyno";
+
+/// The project-level configuration, read from `dyno.toml` or a `[dyno]` table of a workspace file
+///
+/// Every setting mirrors the command line option of the same name, and the command line
+/// overrides the configuration. Relative paths are relative to the configuration file.
+#[derive(Debug, Default, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Config {
+    /// The path to the forc binary
+    pub forc_path: Option<PathBuf>,
+    /// The path to a baseline forc binary for A/B mode
+    pub baseline_forc_path: Option<PathBuf>,
+    /// The target folders of the projects to profile
+    #[serde(default)]
+    pub targets: Vec<PathBuf>,
+    /// Globs of the project paths to profile, relative to the target
+    #[serde(default)]
+    pub include: Vec<String>,
+    /// Globs of the project paths to skip, relative to the target
+    #[serde(default)]
+    pub exclude: Vec<String>,
+    /// The maximum depth of the projects below the target
+    pub max_depth: Option<usize>,
+    /// The path to a suite manifest that tags the projects
+    pub suite: Option<PathBuf>,
+    /// The tags of the projects to profile
+    #[serde(default)]
+    pub tags: Vec<String>,
+    /// The benchmarks output folder
+    pub output_folder: Option<PathBuf>,
+    /// Whether to print the comparison tables
+    pub print_output: Option<bool>,
+    /// The formats of the comparison, such as `["json", "table"]`
+    pub formats: Option<Vec<crate::types::OutputFormat>>,
+    /// The number of measured builds per benchmark
+    pub iterations: Option<u32>,
+    /// The number of unmeasured warmup builds per benchmark
+    pub warmup: Option<u32>,
+    /// How each build is prepared
+    pub mode: Option<crate::types::BuildMode>,
+    /// The source file touched before each incremental build, relative to the project
+    pub touch: Option<PathBuf>,
+    /// The forc commands run against each project, by name
+    #[serde(default)]
+    pub workloads: BTreeMap<String, crate::workload::WorkloadConfig>,
+    /// The interval between performance frames, in milliseconds
+    pub sampling_interval: Option<u64>,
+    /// Whether to collect performance counters
+    pub counters: Option<bool>,
+    /// The number of benchmarks built concurrently
+    pub jobs: Option<u32>,
+    /// The CPUs to pin forc to, such as `2,3` or `2-5`
+    pub cpus: Option<String>,
+    /// The scheduling priority of forc
+    pub nice: Option<i32>,
+    /// The I/O priority of forc, such as `best-effort:0`
+    pub ionice: Option<String>,
+    /// The CPU frequency governor to set for the run
+    pub governor: Option<String>,
+    /// Whether to warn when the CPUs do not use the performance governor
+    pub check_governor: Option<bool>,
+    /// Whether to disable address space layout randomization for forc
+    pub disable_aslr: Option<bool>,
+    /// The time limit of the builds of each benchmark, in seconds
+    pub timeout: Option<u64>,
+    /// The time limits of specific benchmarks, keyed by project path or name, in seconds
+    #[serde(default)]
+    pub timeouts: BTreeMap<String, u64>,
+    /// The time limit of the whole run, in seconds
+    pub total_timeout: Option<u64>,
+    /// Whether to generate flamegraphs
+    pub flamegraph: Option<bool>,
+    /// Whether to run the hyperfine analysis
+    pub hyperfine: Option<bool>,
+    /// The maximum iterations of the hyperfine analysis
+    pub max_iterations: Option<u32>,
+    /// The baseline to compare against
+    pub baseline: Option<String>,
+    /// The path to a JSON thresholds file
+    pub thresholds_file: Option<PathBuf>,
+    /// The regression thresholds, in the same layout as a thresholds file
+    #[serde(default)]
+    pub thresholds: Thresholds,
+    /// The database settings
+    #[serde(default)]
+    pub database: DatabaseConfig,
+}
+
+/// The database settings, each falling back to its environment variable when unset
+#[derive(Debug, Default, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct DatabaseConfig {
+    /// Whether to store the runs in the database
+    #[serde(default)]
+    pub enabled: bool,
+    /// The database host, or `DB_HOST`
+    pub host: Option<String>,
+    /// The database port, or `DB_PORT`
+    pub port: Option<u16>,
+    /// The database name, or `DB_NAME`
+    pub name: Option<String>,
+    /// The database user, or `DB_USER`
+    pub user: Option<String>,
+    /// The database password, or `DB_PASSWORD`
+    pub password: Option<String>,
+    /// The path to the base64-encoded certificate, or `CERT`
+    pub cert: Option<PathBuf>,
+}
+
+impl Config {
+    /// Load the configuration
+    ///
+    /// Without an explicit path, `dyno.toml` in the working directory is used, followed by the
+    /// `[dyno]` table of `Forc.toml` in the working directory. Without either, the configuration
+    /// is empty.
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - An explicit path to a configuration file
+    ///
+    /// # Errors
+    ///
+    /// If the configuration file cannot be read or parsed.
+    ///
+    /// If the configuration contains an unknown setting or metric.
+    ///
+    pub fn load(path: Option<&Path>) -> crate::Result<Self> {
+        if let Some(path) = path {
+            return Ok(Self::load_file(path, false).map_err(|e| wrap!(e))?);
+        }
+
+        let path = Path::new(CONFIG_FILE_NAME);
+        if path.is_file() {
+            return Ok(Self::load_file(path, false).map_err(|e| wrap!(e))?);
+        }
+
+        let path = Path::new(WORKSPACE_FILE_NAME);
+        if path.is_file() {
+            return Ok(Self::load_file(path, true).map_err(|e| wrap!(e))?);
+        }
+
+        Ok(Self::default())
+    }
+
+    /// Load the configuration from a file, either from its `[dyno]` table or the whole file
+    fn load_file(path: &Path, workspace: bool) -> crate::Result<Self> {
+        let text = std::fs::read_to_string(path).map_err(|e| wrap!(e.into()))?;
+        let mut table: toml::Table = text.parse().map_err(|e| wrap!(Box::new(e)))?;
+
+        let table = match table.remove(WORKSPACE_TABLE) {
+            Some(toml::Value::Table(table)) => table,
+            Some(_) => {
+                return Err(Box::new(wrap!(format!(
+                    "Expected [{WORKSPACE_TABLE}] to be a table in {}",
+                    path.display()
+                )
+                .into())))
+            }
+            // A workspace file without a `[dyno]` table holds no configuration
+            None if workspace => return Ok(Self::default()),
+            None => table,
+        };
+
+        let mut config: Self = toml::Value::Table(table)
+            .try_into()
+            .map_err(|e| wrap!(Box::new(e)))?;
+
+        config.thresholds.validate(path).map_err(|e| wrap!(e))?;
+
+        let base = path.parent().unwrap_or(Path::new(""));
+        config.resolve_paths(base);
+
+        Ok(config)
+    }
+
+    /// Make the relative paths of the configuration relative to the folder of the file
+    fn resolve_paths(&mut self, base: &Path) {
+        let resolve = |path: &mut PathBuf| {
+            if path.is_relative() {
+                *path = base.join(&*path);
+            }
+        };
+
+        self.forc_path.iter_mut().for_each(resolve);
+        self.baseline_forc_path.iter_mut().for_each(resolve);
+        self.targets.iter_mut().for_each(resolve);
+        self.output_folder.iter_mut().for_each(resolve);
+        self.suite.iter_mut().for_each(resolve);
+        self.thresholds_file.iter_mut().for_each(resolve);
+        self.database.cert.iter_mut().for_each(resolve);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_load_config() -> crate::Result<()> {
+        let folder = std::env::temp_dir().join(format!("dyno-config-{}", std::process::id()));
+        std::fs::create_dir_all(&folder).map_err(|e| wrap!(e.into()))?;
+
+        let path = folder.join(CONFIG_FILE_NAME);
+        std::fs::write(
+            &path,
+            r#"
+            forc_path = "sway/target/release/forc"
+            targets = ["/projects"]
+            exclude = ["**/should_fail*"]
+            max_depth = 3
+            suite = "suite.toml"
+            tags = ["small"]
+            iterations = 5
+            formats = ["json", "table"]
+            mode = "cold"
+            jobs = 4
+            cpus = "2-5"
+            nice = -5
+            ionice = "best-effort:0"
+            timeout = 600
+
+            [timeouts]
+            hashing = 60
+
+            [workloads.release]
+            args = ["build", "--release"]
+            env = { FORC_DEBUG = "1" }
+
+            [thresholds.default]
+            time = "5%"
+            bytecode_size = 0
+
+            [database]
+            enabled = true
+            host = "localhost"
+            port = 5432
+            "#,
+        )
+        .map_err(|e| wrap!(e.into()))?;
+
+        let config = Config::load(Some(&path))?;
+        assert_eq!(
+            config.forc_path,
+            Some(folder.join("sway/target/release/forc"))
+        );
+        assert_eq!(config.targets, vec![PathBuf::from("/projects")]);
+        assert_eq!(config.max_depth, Some(3));
+        assert_eq!(config.suite, Some(folder.join("suite.toml")));
+        assert_eq!(config.tags, vec!["small"]);
+        assert_eq!(config.iterations, Some(5));
+        assert_eq!(
+            config.formats,
+            Some(vec![
+                crate::types::OutputFormat::Json,
+                crate::types::OutputFormat::Table
+            ])
+        );
+        assert_eq!(config.mode, Some(crate::types::BuildMode::Cold));
+        assert_eq!(config.jobs, Some(4));
+        assert_eq!(config.cpus.as_deref(), Some("2-5"));
+        assert_eq!(config.nice, Some(-5));
+        assert_eq!(config.timeout, Some(600));
+        assert_eq!(config.timeouts["hashing"], 60);
+        assert_eq!(config.workloads["release"].args, vec!["build", "--release"]);
+        assert_eq!(config.workloads["release"].env["FORC_DEBUG"], "1");
+        assert_eq!(
+            config.thresholds.default["bytecode_size"],
+            crate::thresholds::Threshold::Absolute(0.0)
+        );
+        assert!(config.database.enabled);
+        assert_eq!(config.database.port, Some(5432));
+
+        // The configuration can also live in the `[dyno]` table of a workspace file
+        let path = folder.join(WORKSPACE_FILE_NAME);
+        std::fs::write(&path, "[workspace]\nmembers = []\n\n[dyno]\nwarmup = 2\n")
+            .map_err(|e| wrap!(e.into()))?;
+
+        let config = Config::load_file(&path, true)?;
+        assert_eq!(config.warmup, Some(2));
+
+        std::fs::write(&path, "[workspace]\nmembers = []\n").map_err(|e| wrap!(e.into()))?;
+        assert!(Config::load_file(&path, true)?.forc_path.is_none());
+
+        // Unknown settings and metrics are rejected
+        std::fs::write(&path, "[dyno]\niteration = 2\n").map_err(|e| wrap!(e.into()))?;
+        assert!(Config::load_file(&path, true).is_err());
+
+        std::fs::write(&path, "[dyno.thresholds.default]\nspeed = \"5%\"\n")
+            .map_err(|e| wrap!(e.into()))?;
+        assert!(Config::load_file(&path, true).is_err());
+
+        std::fs::remove_dir_all(&folder).map_err(|e| wrap!(e.into()))?;
+
+        Ok(())
+    }
+}
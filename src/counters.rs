@@ -0,0 +1,410 @@
+use std::fs::File;
+use std::io::{Read, Write};
+
+use crate::types::HardwareCounters;
+use crate::wrap;
+
+/// The `PERF_TYPE_HARDWARE` event type
+const PERF_TYPE_HARDWARE: u32 = 0;
+
+/// The `PERF_TYPE_SOFTWARE` event type
+const PERF_TYPE_SOFTWARE: u32 = 1;
+
+/// The `disabled` flag, keeping the counter stopped until it is enabled
+const FLAG_DISABLED: u64 = 1;
+
+/// The `inherit` flag, counting the children created after the counter was opened
+const FLAG_INHERIT: u64 = 1 << 1;
+
+/// The `exclude_kernel` flag, required when the kernel restricts unprivileged profiling
+const FLAG_EXCLUDE_KERNEL: u64 = 1 << 5;
+
+/// The `exclude_hv` flag
+const FLAG_EXCLUDE_HV: u64 = 1 << 6;
+
+/// The `enable_on_exec` flag, starting a disabled counter when its process executes
+const FLAG_ENABLE_ON_EXEC: u64 = 1 << 12;
+
+/// The `PERF_FORMAT_TOTAL_TIME_ENABLED | PERF_FORMAT_TOTAL_TIME_RUNNING` read format
+const READ_FORMAT: u64 = 1 | 2;
+
+/// The `PERF_FLAG_FD_CLOEXEC` flag of `perf_event_open`
+#[cfg(target_os = "linux")]
+const PERF_FLAG_FD_CLOEXEC: libc::c_ulong = 8;
+
+/// The first published layout of `struct perf_event_attr`, which every kernel accepts
+#[repr(C)]
+#[derive(Default)]
+struct PerfEventAttr {
+    kind: u32,
+    size: u32,
+    config: u64,
+    sample_period: u64,
+    sample_type: u64,
+    read_format: u64,
+    flags: u64,
+    wakeup_events: u32,
+    bp_type: u32,
+    config1: u64,
+}
+
+/// A counted event
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Event {
+    Instructions,
+    Cycles,
+    CacheMisses,
+    BranchMisses,
+    ContextSwitches,
+    PageFaults,
+}
+
+impl Event {
+    /// Every counted event
+    const ALL: [Event; 6] = [
+        Event::Instructions,
+        Event::Cycles,
+        Event::CacheMisses,
+        Event::BranchMisses,
+        Event::ContextSwitches,
+        Event::PageFaults,
+    ];
+
+    /// The type and config of the event
+    fn kind(self) -> (u32, u64) {
+        match self {
+            Event::Cycles => (PERF_TYPE_HARDWARE, 0),
+            Event::Instructions => (PERF_TYPE_HARDWARE, 1),
+            Event::CacheMisses => (PERF_TYPE_HARDWARE, 3),
+            Event::BranchMisses => (PERF_TYPE_HARDWARE, 5),
+            Event::PageFaults => (PERF_TYPE_SOFTWARE, 2),
+            Event::ContextSwitches => (PERF_TYPE_SOFTWARE, 3),
+        }
+    }
+
+    /// The field of the event in a set of counters
+    fn field(self, counters: &mut HardwareCounters) -> &mut Option<u64> {
+        match self {
+            Event::Instructions => &mut counters.instructions,
+            Event::Cycles => &mut counters.cycles,
+            Event::CacheMisses => &mut counters.cache_misses,
+            Event::BranchMisses => &mut counters.branch_misses,
+            Event::ContextSwitches => &mut counters.context_switches,
+            Event::PageFaults => &mut counters.page_faults,
+        }
+    }
+}
+
+/// The hardware and software performance counters of a process and the children it creates
+///
+/// Counters are only available on Linux, through `perf_event_open`.
+///
+/// Counters are opened for a command between its fork and its exec, and start counting when
+/// it executes, so everything the command runs is counted. The counts of child processes and
+/// threads are added to the counters when they exit, so a read while the process runs only
+/// includes its main thread and its exited children, and a read after it exited includes
+/// everything.
+pub struct Counters {
+    events: Vec<(Event, File)>,
+}
+
+/// The counters of a command that is about to be spawned
+///
+/// The spawned process sends its pid to a thread, which opens the counters of the process and
+/// then lets it execute the command.
+pub struct PendingCounters {
+    /// The write end of the pid pipe in this process, closed once the command was spawned so
+    /// that the thread stops waiting when the process was never created
+    pid_writer: std::os::fd::OwnedFd,
+    thread: std::thread::JoinHandle<Result<Counters, String>>,
+}
+
+impl Counters {
+    /// Prepare the counters of a command, which are opened when the command is spawned.
+    ///
+    /// The spawned process waits for its counters to be opened before it executes the command,
+    /// even when they fail to open.
+    ///
+    /// # Arguments
+    ///
+    /// * `command` - The command to count, before it is spawned.
+    ///
+    /// # Returns
+    ///
+    /// A `Result` containing the `PendingCounters` to open once the command was spawned.
+    ///
+    /// # Errors
+    ///
+    /// If the pipes to the spawned process could not be created.
+    ///
+    #[cfg(target_os = "linux")]
+    pub fn prepare(command: &mut std::process::Command) -> crate::Result<PendingCounters> {
+        use std::os::fd::AsRawFd;
+        use std::os::unix::process::CommandExt;
+
+        let (pid_reader, pid_writer) = pipe().map_err(|e| wrap!(e.into()))?;
+        let (release_reader, release_writer) = pipe().map_err(|e| wrap!(e.into()))?;
+
+        let pid_fd = pid_writer.as_raw_fd();
+        let release_fd = release_reader.as_raw_fd();
+        let release_writer_fd = release_writer.as_raw_fd();
+
+        let thread = std::thread::spawn(move || {
+            let mut pid = [0u8; std::mem::size_of::<libc::pid_t>()];
+            File::from(pid_reader)
+                .read_exact(&mut pid)
+                .map_err(|e| format!("Failed to receive the pid of the command: {e}"))?;
+
+            let counters = Self::open(libc::pid_t::from_ne_bytes(pid));
+
+            // Let the process execute the command, which enables the counters
+            let _ = File::from(release_writer).write_all(&[0]);
+            drop(release_reader);
+
+            counters
+        });
+
+        // SAFETY: only async-signal-safe system calls are made in the child
+        unsafe {
+            command.pre_exec(move || {
+                // The child's copy of the write end is closed, so that the read below ends
+                // when the thread is gone
+                libc::close(release_writer_fd);
+
+                let pid = libc::getpid().to_ne_bytes();
+                if libc::write(pid_fd, pid.as_ptr().cast(), pid.len()) < 0 {
+                    return Err(std::io::Error::last_os_error());
+                }
+
+                let mut byte = 0u8;
+                while libc::read(release_fd, std::ptr::from_mut(&mut byte).cast(), 1) < 0 {
+                    let error = std::io::Error::last_os_error();
+                    if error.kind() != std::io::ErrorKind::Interrupted {
+                        return Err(error);
+                    }
+                }
+
+                Ok(())
+            });
+        }
+
+        Ok(PendingCounters { pid_writer, thread })
+    }
+
+    /// Performance counters are only available on Linux.
+    ///
+    /// # Errors
+    ///
+    /// Always.
+    ///
+    #[cfg(not(target_os = "linux"))]
+    pub fn prepare(_command: &mut std::process::Command) -> crate::Result<PendingCounters> {
+        Err(Box::new(wrap!(
+            "Performance counters are only available on Linux".into()
+        )))
+    }
+
+    /// Open every available counter of the process with the given `pid`, disabled until the
+    /// process executes.
+    ///
+    /// Events that the system does not support, such as hardware events inside most virtual
+    /// machines, are skipped.
+    fn open(pid: libc::pid_t) -> Result<Self, String> {
+        let mut events = Vec::new();
+        let mut last_error = None;
+
+        for event in Event::ALL {
+            match open_event(event, pid) {
+                Ok(file) => events.push((event, file)),
+                Err(e) => last_error = Some(e),
+            }
+        }
+
+        if events.is_empty() {
+            let error = last_error.map_or_else(|| "unknown error".to_string(), |e| e.to_string());
+            return Err(format!("Failed to open any performance counter: {error}"));
+        }
+
+        Ok(Self { events })
+    }
+
+    /// Read the current value of every counter.
+    ///
+    /// Counters that the kernel multiplexed are scaled up to the whole time they were enabled,
+    /// and counters that never ran are left empty.
+    pub fn read(&self) -> HardwareCounters {
+        let mut counters = HardwareCounters::default();
+
+        for (event, file) in &self.events {
+            *event.field(&mut counters) = read_event(file);
+        }
+
+        counters
+    }
+}
+
+impl PendingCounters {
+    /// Open the counters of the spawned command.
+    ///
+    /// # Errors
+    ///
+    /// If the command was not spawned, or no counter could be opened.
+    ///
+    pub fn open(self) -> crate::Result<Counters> {
+        drop(self.pid_writer);
+
+        let counters = self
+            .thread
+            .join()
+            .map_err(|_| wrap!("The performance counters thread panicked".into()))?
+            .map_err(|e| wrap!(e.into()))?;
+
+        Ok(counters)
+    }
+}
+
+/// Create a pipe whose ends are closed when the process executes.
+#[cfg(target_os = "linux")]
+fn pipe() -> std::io::Result<(std::os::fd::OwnedFd, std::os::fd::OwnedFd)> {
+    let mut fds = [0; 2];
+
+    // SAFETY: `fds` has room for the two file descriptors of the pipe
+    if unsafe { libc::pipe2(fds.as_mut_ptr(), libc::O_CLOEXEC) } != 0 {
+        return Err(std::io::Error::last_os_error());
+    }
+
+    // SAFETY: both file descriptors were just opened and are owned by nothing else
+    Ok(unsafe {
+        (
+            std::os::fd::FromRawFd::from_raw_fd(fds[0]),
+            std::os::fd::FromRawFd::from_raw_fd(fds[1]),
+        )
+    })
+}
+
+/// Open a counter of a single event, excluding the kernel when the system requires it.
+fn open_event(event: Event, pid: libc::pid_t) -> std::io::Result<File> {
+    let (kind, config) = event.kind();
+
+    let mut attr = PerfEventAttr {
+        kind,
+        size: u32::try_from(std::mem::size_of::<PerfEventAttr>()).unwrap_or(64),
+        config,
+        read_format: READ_FORMAT,
+        flags: FLAG_DISABLED | FLAG_INHERIT | FLAG_EXCLUDE_HV | FLAG_ENABLE_ON_EXEC,
+        ..Default::default()
+    };
+
+    let result = perf_event_open(&attr, pid);
+
+    if let Err(e) = &result {
+        if matches!(e.raw_os_error(), Some(libc::EACCES | libc::EPERM)) {
+            attr.flags |= FLAG_EXCLUDE_KERNEL;
+            return perf_event_open(&attr, pid);
+        }
+    }
+
+    result
+}
+
+/// Call `perf_event_open` for the process with the given `pid` on any CPU.
+#[cfg(target_os = "linux")]
+fn perf_event_open(attr: &PerfEventAttr, pid: libc::pid_t) -> std::io::Result<File> {
+    // SAFETY: `attr` points to a valid `perf_event_attr` whose `size` matches its layout
+    let fd = unsafe {
+        libc::syscall(
+            libc::SYS_perf_event_open,
+            std::ptr::from_ref(attr),
+            pid,
+            -1,
+            -1,
+            PERF_FLAG_FD_CLOEXEC,
+        )
+    };
+
+    if fd < 0 {
+        return Err(std::io::Error::last_os_error());
+    }
+
+    let fd = i32::try_from(fd).map_err(|e| std::io::Error::other(e.to_string()))?;
+
+    // SAFETY: the file descriptor was just opened and is owned by nothing else
+    Ok(unsafe { std::os::fd::FromRawFd::from_raw_fd(fd) })
+}
+
+/// Performance counters are only available on Linux.
+#[cfg(not(target_os = "linux"))]
+fn perf_event_open(_attr: &PerfEventAttr, _pid: libc::pid_t) -> std::io::Result<File> {
+    Err(std::io::ErrorKind::Unsupported.into())
+}
+
+/// Read the value of a counter, scaled by the time it was enabled over the time it ran.
+fn read_event(mut file: &File) -> Option<u64> {
+    let mut buffer = [0u8; 24];
+    file.read_exact(&mut buffer).ok()?;
+
+    let field = |index: usize| {
+        let mut bytes = [0u8; 8];
+        bytes.copy_from_slice(&buffer[index * 8..(index + 1) * 8]);
+        u64::from_ne_bytes(bytes)
+    };
+
+    scale(field(0), field(1), field(2))
+}
+
+/// Scale the value of a multiplexed counter by the time it was enabled over the time it ran.
+fn scale(value: u64, enabled: u64, running: u64) -> Option<u64> {
+    if running == 0 {
+        return None;
+    }
+
+    if running >= enabled {
+        return Some(value);
+    }
+
+    u64::try_from(u128::from(value) * u128::from(enabled) / u128::from(running)).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_scale_counter() {
+        assert_eq!(scale(100, 10, 10), Some(100));
+        assert_eq!(scale(100, 10, 5), Some(200));
+        assert_eq!(scale(100, 10, 0), None);
+        assert_eq!(std::mem::size_of::<PerfEventAttr>(), 64);
+    }
+
+    #[test]
+    fn test_count_child_process() {
+        // The command exits right away, so only counters opened before it executes see it
+        let mut command = std::process::Command::new("true");
+        let pending = Counters::prepare(&mut command).expect("Failed to prepare the counters");
+        let mut child = command.spawn().expect("Failed to spawn the child process");
+        let counters = pending.open();
+        let _ = child.wait();
+
+        // Performance counters are commonly unavailable in containers and on CI runners, where
+        // the test process cannot count its own events either
+        let counters = match counters {
+            Ok(counters) => counters,
+            Err(e) => {
+                assert!(e
+                    .to_string()
+                    .contains("Failed to open any performance counter"));
+                assert!(Event::ALL
+                    .iter()
+                    .all(|event| open_event(*event, 0).is_err()));
+                return;
+            }
+        };
+
+        let counts = counters.read();
+        assert!(counts.page_faults.is_some_and(|faults| faults > 10));
+        assert!(counts
+            .instructions
+            .is_none_or(|instructions| instructions > 10_000));
+    }
+}
@@ -9,38 +9,60 @@ use crate::wrap;
 
 /// Setup the database connection and return the client
 ///
-/// # Returns
-///
-/// A `Result` containing a `tokio_postgres::Client`.
+/// # Arguments
 ///
-/// # Panics
+/// * `connection` - The configured database settings, each falling back to its environment variable.
 ///
-/// If the database URL is not set.
+/// # Returns
 ///
-/// If the database password is not set.
+/// A `Result` containing a `tokio_postgres::Client`.
 ///
 /// # Errors
 ///
+/// If a database setting is neither configured nor set in the environment.
+///
 /// If the connection to the database fails.
 ///
-pub async fn setup() -> crate::Result<tokio_postgres::Client> {
-    // Load the certificate from the environment variable
-    let cert_path = std::env::var("CERT").map_err(|e| wrap!(e.into()))?;
+pub async fn setup(
+    connection: &crate::config::DatabaseConfig,
+) -> crate::Result<tokio_postgres::Client> {
+    // Get a setting from the configuration or from its environment variable
+    let setting = |value: Option<String>, variable: &str| -> crate::Result<String> {
+        match value {
+            Some(value) => Ok(value),
+            None => Ok(std::env::var(variable).map_err(|e| {
+                wrap!(
+                    format!("Database setting missing, configure it or set {variable}: {e}").into()
+                )
+            })?),
+        }
+    };
+
+    // Load the certificate from the configuration or the environment variable
+    let cert_path = setting(
+        connection
+            .cert
+            .as_ref()
+            .map(|cert| cert.display().to_string()),
+        "CERT",
+    )
+    .map_err(|e| wrap!(e))?;
 
-    // Get the environment variable for the database URL
-    let db_host = std::env::var("DB_HOST").map_err(|e| wrap!(e.into()))?;
+    // Get the database URL
+    let db_host = setting(connection.host.clone(), "DB_HOST").map_err(|e| wrap!(e))?;
 
-    // Get the environment variable for the database password
-    let db_password = std::env::var("DB_PASSWORD").map_err(|e| wrap!(e.into()))?;
+    // Get the database password
+    let db_password = setting(connection.password.clone(), "DB_PASSWORD").map_err(|e| wrap!(e))?;
 
-    // Get the environment variable for the database port
-    let db_port = std::env::var("DB_PORT").map_err(|e| wrap!(e.into()))?;
+    // Get the database port
+    let db_port =
+        setting(connection.port.map(|port| port.to_string()), "DB_PORT").map_err(|e| wrap!(e))?;
 
-    // Get the environment variable for the database name
-    let db_name = std::env::var("DB_NAME").map_err(|e| wrap!(e.into()))?;
+    // Get the database name
+    let db_name = setting(connection.name.clone(), "DB_NAME").map_err(|e| wrap!(e))?;
 
-    // Get the environment variable for the database user
-    let db_user = std::env::var("DB_USER").map_err(|e| wrap!(e.into()))?;
+    // Get the database user
+    let db_user = setting(connection.user.clone(), "DB_USER").map_err(|e| wrap!(e))?;
 
     // Read the certificate
     let cert_data = std::fs::read_to_string(cert_path).map_err(|e| wrap!(e.into()))?;
@@ -241,6 +263,136 @@ pub async fn get_latest_benchmarks(
     Ok(serde_json::from_str(&benchmarks).map_err(|e| wrap!(e.into()))?)
 }
 
+/// Get the benchmarks of a run from the database
+///
+/// # Arguments
+///
+/// * `client` - A reference to a `tokio_postgres::Client`.
+///
+/// * `id` - The id of the run.
+///
+/// # Returns
+///
+/// A `Result` containing the `crate::types::Benchmarks` of the run, if it exists.
+///
+/// # Errors
+///
+/// If the query to the database fails.
+///
+/// If the deserialization of the benchmarks fails.
+///
+pub async fn get_benchmarks_by_id(
+    client: &tokio_postgres::Client,
+    id: i32,
+) -> crate::Result<Option<crate::types::Benchmarks>> {
+    let row = client
+        .query_opt("SELECT * FROM forc.runs WHERE id = $1;", &[&id])
+        .await
+        .map_err(|e| wrap!(e.into()))?;
+
+    let Some(row) = row else {
+        return Ok(None);
+    };
+
+    let benchmarks: String = row.get("benchmarks");
+
+    Ok(Some(
+        serde_json::from_str(&benchmarks).map_err(|e| wrap!(e.into()))?,
+    ))
+}
+
+/// Get the latest benchmarks from the database where a top level field of the run has a value
+///
+/// # Arguments
+///
+/// * `client` - A reference to a `tokio_postgres::Client`.
+///
+/// * `field` - The name of the field, such as `compiler_hash`.
+///
+/// * `value` - The value of the field.
+///
+/// # Returns
+///
+/// A `Result` containing the latest matching `crate::types::Benchmarks`, if any.
+///
+/// # Errors
+///
+/// If the query to the database fails.
+///
+/// If the deserialization of the benchmarks fails.
+///
+pub async fn find_latest_benchmarks(
+    client: &tokio_postgres::Client,
+    field: &str,
+    value: &str,
+) -> crate::Result<Option<crate::types::Benchmarks>> {
+    let row = client
+        .query_opt(
+            "SELECT * FROM forc.runs WHERE benchmarks::json ->> $1 = $2 ORDER BY date DESC LIMIT 1;",
+            &[&field, &value],
+        )
+        .await
+        .map_err(|e| wrap!(e.into()))?;
+
+    let Some(row) = row else {
+        return Ok(None);
+    };
+
+    let benchmarks: String = row.get("benchmarks");
+
+    Ok(Some(
+        serde_json::from_str(&benchmarks).map_err(|e| wrap!(e.into()))?,
+    ))
+}
+
+/// A run stored in the database, without the benchmark data
+pub struct StoredRun {
+    pub id: i32,
+    pub date: String,
+    pub forc_version: Option<String>,
+    pub compiler_hash: Option<String>,
+    pub label: Option<String>,
+}
+
+/// List the runs stored in the database, newest first
+///
+/// # Arguments
+///
+/// * `client` - A reference to a `tokio_postgres::Client`.
+///
+/// # Returns
+///
+/// A `Result` containing a `Vec` of `StoredRun`.
+///
+/// # Errors
+///
+/// If the query to the database fails.
+///
+pub async fn list_runs(client: &tokio_postgres::Client) -> crate::Result<Vec<StoredRun>> {
+    let rows = client
+        .query(
+            "SELECT id, date::TEXT AS date,
+                benchmarks::json ->> 'forc_version' AS forc_version,
+                benchmarks::json ->> 'compiler_hash' AS compiler_hash,
+                benchmarks::json ->> 'label' AS label
+            FROM forc.runs ORDER BY date DESC;",
+            &[],
+        )
+        .await
+        .map_err(|e| wrap!(e.into()))?;
+
+    Ok(rows
+        .iter()
+        .map(|row| StoredRun {
+            id: row.get("id"),
+            date: row.get("date"),
+            forc_version: row.get("forc_version"),
+            compiler_hash: row.get("compiler_hash"),
+            label: row.get("label"),
+        })
+        .collect())
+}
+
 /// Insert the stats into the database
 ///
 /// # Arguments
@@ -366,10 +518,23 @@ mod tests {
                 frames: std::sync::Arc::new(std::sync::Mutex::new(vec![])),
                 asm_information: None,
                 hyperfine: None,
+                iterations: vec![],
+                aggregate: None,
+                events: vec![],
+                warnings: vec![],
+                status: None,
+                cpus: vec![],
+                mode: None,
+                workload: crate::workload::Workload::default(),
+                tags: vec![],
             }],
             forc_version: "0.1.0".to_string(),
             compiler_hash: "123456".to_string(),
             benchmarks_datetime: "2021-01-01T00:00:00".to_string(),
+            target: std::path::PathBuf::new(),
+            label: None,
+            jobs: None,
+            environment: crate::environment::Environment::default(),
         };
 
         insert_benchmarks(&client, &benchmark1)
@@ -388,10 +553,23 @@ mod tests {
                 frames: std::sync::Arc::new(std::sync::Mutex::new(vec![])),
                 asm_information: None,
                 hyperfine: None,
+                iterations: vec![],
+                aggregate: None,
+                events: vec![],
+                warnings: vec![],
+                status: None,
+                cpus: vec![],
+                mode: None,
+                workload: crate::workload::Workload::default(),
+                tags: vec![],
             }],
             forc_version: "0.1.0".to_string(),
             compiler_hash: "123456".to_string(),
             benchmarks_datetime: "2021-01-01T00:00:00".to_string(),
+            target: std::path::PathBuf::new(),
+            label: None,
+            jobs: None,
+            environment: crate::environment::Environment::default(),
         };
 
         insert_benchmarks(&client, &benchmark2)
@@ -0,0 +1,463 @@
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::process::Command;
+
+use crate::wrap;
+
+/// The one minute load average per CPU above which the system is considered busy
+pub const HIGH_LOAD_PER_CPU: f64 = 0.5;
+
+/// The frequency governor that keeps the CPUs at their highest frequency
+pub const PERFORMANCE_GOVERNOR: &str = "performance";
+
+/// An I/O scheduling class, as used by `ionice`
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum IoClass {
+    /// Served before every other class
+    Realtime,
+    /// The default class
+    BestEffort,
+    /// Only served when no other process needs the disk
+    Idle,
+}
+
+/// The I/O scheduling priority of a process
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct IoPriority {
+    /// The scheduling class
+    pub class: IoClass,
+    /// The level within the class, from 0 (highest) to 7, which the idle class ignores
+    pub level: u8,
+}
+
+impl IoPriority {
+    /// The highest level within a class
+    pub const MAX_LEVEL: u8 = 7;
+
+    /// Returns the priority as passed to `ioprio_set`.
+    #[must_use]
+    pub fn value(self) -> i32 {
+        let class = match self.class {
+            IoClass::Realtime => 1,
+            IoClass::BestEffort => 2,
+            IoClass::Idle => 3,
+        };
+
+        (class << 13) | i32::from(self.level)
+    }
+}
+
+impl std::str::FromStr for IoPriority {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || {
+            format!("Invalid I/O priority \"{s}\", expected `realtime`, `best-effort` or `idle` with an optional level from 0 to 7, such as `best-effort:0`")
+        };
+
+        let (class, level) = match s.trim().split_once(':') {
+            Some((class, level)) => (class, Some(level)),
+            None => (s.trim(), None),
+        };
+
+        let class = match class {
+            "realtime" => IoClass::Realtime,
+            "best-effort" => IoClass::BestEffort,
+            "idle" => IoClass::Idle,
+            _ => return Err(invalid()),
+        };
+
+        let level = match level {
+            Some(level) => level.parse::<u8>().map_err(|_| invalid())?,
+            None => 0,
+        };
+
+        if level > Self::MAX_LEVEL || (class == IoClass::Idle && level != 0) {
+            return Err(invalid());
+        }
+
+        Ok(Self { class, level })
+    }
+}
+
+impl std::fmt::Display for IoPriority {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.class {
+            IoClass::Realtime => write!(f, "realtime:{}", self.level),
+            IoClass::BestEffort => write!(f, "best-effort:{}", self.level),
+            IoClass::Idle => write!(f, "idle"),
+        }
+    }
+}
+
+/// The opt-in controls that reduce the noise of the measured builds
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Controls {
+    /// The CPUs that forc is pinned to, split between the jobs of a parallel run
+    pub cpus: Vec<usize>,
+    /// The scheduling priority of forc, from -20 (highest) to 19 (lowest)
+    pub nice: Option<i32>,
+    /// The I/O scheduling priority of forc
+    pub io_priority: Option<IoPriority>,
+    /// The frequency governor to set for the duration of the run
+    pub governor: Option<String>,
+    /// Whether to warn when the CPUs do not use the performance governor
+    pub check_governor: bool,
+    /// Whether to disable address space layout randomization for forc
+    pub disable_aslr: bool,
+}
+
+/// The environment that the builds of a run were measured in
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Environment {
+    /// The CPUs that forc was pinned to, or empty if it was not pinned
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub cpus: Vec<usize>,
+    /// The scheduling priority of forc, if set
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub nice: Option<i32>,
+    /// The I/O scheduling priority of forc, if set
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub io_priority: Option<IoPriority>,
+    /// Whether address space layout randomization was disabled for forc
+    pub aslr_disabled: bool,
+    /// The frequency governor of each CPU that forc ran on, when the system reports it
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    pub governors: BTreeMap<usize, String>,
+    /// The problems found by the checks before the run
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub warnings: Vec<String>,
+}
+
+impl Controls {
+    /// Apply the controls to a command before it is spawned.
+    ///
+    /// The settings are applied in the child before it executes the command, and are inherited
+    /// by everything the command spawns.
+    ///
+    /// # Arguments
+    ///
+    /// * `command` - The command to apply the controls to.
+    ///
+    /// * `cpus` - The CPUs to pin the command to, or empty to leave it unpinned.
+    ///
+    pub fn apply(&self, command: &mut Command, cpus: &[usize]) {
+        use std::os::unix::process::CommandExt;
+
+        if !cpus.is_empty() {
+            crate::affinity::pin(command, cpus);
+        }
+
+        if self.nice.is_none() && self.io_priority.is_none() && !self.disable_aslr {
+            return;
+        }
+
+        let nice = self.nice;
+        let io_priority = self.io_priority.map(IoPriority::value);
+        let disable_aslr = self.disable_aslr;
+
+        // SAFETY: only async-signal-safe system calls are made in the child
+        unsafe {
+            command.pre_exec(move || {
+                if let Some(nice) = nice {
+                    if libc::setpriority(libc::PRIO_PROCESS, 0, nice) != 0 {
+                        return Err(std::io::Error::last_os_error());
+                    }
+                }
+
+                if let Some(io_priority) = io_priority {
+                    set_io_priority(io_priority)?;
+                }
+
+                if disable_aslr {
+                    disable_address_randomization()?;
+                }
+
+                Ok(())
+            });
+        }
+    }
+
+    /// Record the settings of the run, along with the frequency governors of the CPUs.
+    ///
+    /// # Arguments
+    ///
+    /// * `cpus` - The CPUs that forc is pinned to, or empty if it is not pinned.
+    ///
+    /// * `warnings` - The problems found by the checks before the run.
+    ///
+    #[must_use]
+    pub fn record(&self, cpus: Vec<usize>, warnings: Vec<String>) -> Environment {
+        let governed_cpus = if cpus.is_empty() {
+            crate::affinity::available_cpus().unwrap_or_default()
+        } else {
+            cpus.clone()
+        };
+
+        Environment {
+            cpus,
+            nice: self.nice,
+            io_priority: self.io_priority,
+            aslr_disabled: self.disable_aslr,
+            governors: governors(&governed_cpus),
+            warnings,
+        }
+    }
+}
+
+/// Set the I/O priority of the calling process.
+#[cfg(target_os = "linux")]
+fn set_io_priority(io_priority: i32) -> std::io::Result<()> {
+    /// The `IOPRIO_WHO_PROCESS` target of `ioprio_set`
+    const IOPRIO_WHO_PROCESS: libc::c_int = 1;
+
+    // SAFETY: `ioprio_set` only reads its integer arguments
+    if unsafe { libc::syscall(libc::SYS_ioprio_set, IOPRIO_WHO_PROCESS, 0, io_priority) } != 0 {
+        return Err(std::io::Error::last_os_error());
+    }
+
+    Ok(())
+}
+
+/// I/O priorities are only available on Linux.
+#[cfg(not(target_os = "linux"))]
+fn set_io_priority(_io_priority: i32) -> std::io::Result<()> {
+    Err(std::io::ErrorKind::Unsupported.into())
+}
+
+/// Disable address space layout randomization for the programs executed by the calling process.
+#[cfg(target_os = "linux")]
+fn disable_address_randomization() -> std::io::Result<()> {
+    /// The query of `personality`, which returns the persona without changing it
+    const QUERY_PERSONA: libc::c_ulong = 0xffff_ffff;
+
+    // SAFETY: `personality` only reads its integer argument
+    let persona = unsafe { libc::personality(QUERY_PERSONA) };
+
+    let persona = libc::c_ulong::try_from(persona | libc::ADDR_NO_RANDOMIZE)
+        .map_err(|_| std::io::Error::last_os_error())?;
+
+    // SAFETY: `personality` only reads its integer argument
+    if unsafe { libc::personality(persona) } == -1 {
+        return Err(std::io::Error::last_os_error());
+    }
+
+    Ok(())
+}
+
+/// Address space layout randomization can only be disabled on Linux.
+#[cfg(not(target_os = "linux"))]
+fn disable_address_randomization() -> std::io::Result<()> {
+    Err(std::io::ErrorKind::Unsupported.into())
+}
+
+/// Get the path of the frequency governor file of a CPU.
+fn governor_path(cpu: usize) -> std::path::PathBuf {
+    std::path::PathBuf::from(format!(
+        "/sys/devices/system/cpu/cpu{cpu}/cpufreq/scaling_governor"
+    ))
+}
+
+/// Get the frequency governor of each CPU, skipping the CPUs that do not report one.
+///
+/// # Arguments
+///
+/// * `cpus` - The CPUs to get the governor of.
+///
+#[must_use]
+pub fn governors(cpus: &[usize]) -> BTreeMap<usize, String> {
+    cpus.iter()
+        .filter_map(|cpu| {
+            let governor = std::fs::read_to_string(governor_path(*cpu)).ok()?;
+            Some((*cpu, governor.trim().to_string()))
+        })
+        .collect()
+}
+
+/// Restores the frequency governors of the CPUs when dropped
+pub struct GovernorGuard {
+    previous: BTreeMap<usize, String>,
+}
+
+impl Drop for GovernorGuard {
+    fn drop(&mut self) {
+        for (cpu, governor) in &self.previous {
+            if let Err(e) = std::fs::write(governor_path(*cpu), governor) {
+                eprintln!("Failed to restore the {governor} governor of CPU {cpu}: {e}");
+            }
+        }
+    }
+}
+
+/// Set the frequency governor of the CPUs until the returned guard is dropped.
+///
+/// # Arguments
+///
+/// * `cpus` - The CPUs to set the governor of.
+///
+/// * `governor` - The governor to set, such as `performance`.
+///
+/// # Errors
+///
+/// If no CPU reports a governor, or the governor of a CPU cannot be set, usually for lack of
+/// permissions.
+///
+pub fn set_governor(cpus: &[usize], governor: &str) -> crate::Result<GovernorGuard> {
+    let previous = governors(cpus);
+
+    if previous.is_empty() {
+        return Err(Box::new(wrap!(
+            "The CPUs do not report a frequency governor".into()
+        )));
+    }
+
+    // The guard restores the governors that were already changed if a later one fails
+    let mut guard = GovernorGuard {
+        previous: BTreeMap::new(),
+    };
+
+    for (cpu, current) in previous {
+        std::fs::write(governor_path(cpu), governor).map_err(|e| {
+            wrap!(format!("Failed to set the {governor} governor of CPU {cpu}: {e}").into())
+        })?;
+
+        guard.previous.insert(cpu, current);
+    }
+
+    Ok(guard)
+}
+
+/// Check that the system is quiet enough to benchmark.
+///
+/// # Arguments
+///
+/// * `system_specs` - The system specifications collected before the run.
+///
+/// # Returns
+///
+/// A warning if the one minute load average exceeds [`HIGH_LOAD_PER_CPU`] per CPU.
+///
+#[must_use]
+pub fn check_load(system_specs: &crate::types::SystemSpecs) -> Option<String> {
+    let cpus = system_specs.cpus.len().max(1) as f64;
+    let load = system_specs.load_average.one;
+
+    if load <= HIGH_LOAD_PER_CPU * cpus {
+        return None;
+    }
+
+    Some(format!(
+        "The one minute load average is {load:.2} on {cpus} CPUs, so other processes may add noise to the measurements"
+    ))
+}
+
+/// Check that the CPUs use the performance governor.
+///
+/// # Arguments
+///
+/// * `governors` - The governor of each CPU.
+///
+/// # Returns
+///
+/// A warning listing the CPUs that use another governor.
+///
+#[must_use]
+pub fn check_governors(governors: &BTreeMap<usize, String>) -> Option<String> {
+    let others = governors
+        .iter()
+        .filter(|(_, governor)| *governor != PERFORMANCE_GOVERNOR)
+        .map(|(cpu, governor)| format!("{cpu} ({governor})"))
+        .collect::<Vec<_>>();
+
+    if others.is_empty() {
+        return None;
+    }
+
+    Some(format!(
+        "CPUs {} do not use the {PERFORMANCE_GOVERNOR} frequency governor",
+        others.join(", ")
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_io_priority() {
+        let priority: IoPriority = "best-effort:3".parse().unwrap();
+        assert_eq!(priority.class, IoClass::BestEffort);
+        assert_eq!(priority.value(), (2 << 13) | 3);
+        assert_eq!(priority.to_string(), "best-effort:3");
+
+        assert_eq!("idle".parse::<IoPriority>().unwrap().value(), 3 << 13);
+        assert!("realtime:8".parse::<IoPriority>().is_err());
+        assert!("idle:2".parse::<IoPriority>().is_err());
+        assert!("fast".parse::<IoPriority>().is_err());
+    }
+
+    #[test]
+    fn test_check_load_and_governors() {
+        let mut system_specs = crate::types::SystemSpecs {
+            cpus: vec![crate::types::Cpu::default(); 4],
+            ..Default::default()
+        };
+
+        system_specs.load_average.one = 1.5;
+        assert!(check_load(&system_specs).is_none());
+
+        system_specs.load_average.one = 3.0;
+        assert!(check_load(&system_specs).is_some());
+
+        let governors =
+            BTreeMap::from([(0, "performance".to_string()), (1, "powersave".to_string())]);
+        assert_eq!(
+            check_governors(&governors).as_deref(),
+            Some("CPUs 1 (powersave) do not use the performance frequency governor")
+        );
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn test_apply_controls() -> crate::Result<()> {
+        let controls = Controls {
+            nice: Some(5),
+            io_priority: Some(IoPriority {
+                class: IoClass::Idle,
+                level: 0,
+            }),
+            disable_aslr: true,
+            ..Default::default()
+        };
+
+        let mut command = Command::new("cat");
+        command.arg("/proc/self/stat");
+        controls.apply(&mut command, &[]);
+
+        let output = command.output().map_err(|e| wrap!(e.into()))?;
+        let stat = String::from_utf8_lossy(&output.stdout);
+
+        // The niceness is the 17th field after the parenthesized command name
+        let nice = stat
+            .rsplit_once(')')
+            .and_then(|(_, fields)| fields.split_whitespace().nth(16))
+            .and_then(|nice| nice.parse::<i32>().ok());
+
+        assert_eq!(nice, Some(5));
+
+        let mut command = Command::new("cat");
+        command.arg("/proc/self/personality");
+        controls.apply(&mut command, &[]);
+
+        let output = command.output().map_err(|e| wrap!(e.into()))?;
+        let persona = u64::from_str_radix(String::from_utf8_lossy(&output.stdout).trim(), 16)
+            .map_err(|e| wrap!(e.into()))?;
+
+        // The `ADDR_NO_RANDOMIZE` flag
+        assert_ne!(persona & 0x0004_0000, 0);
+
+        Ok(())
+    }
+}
@@ -2,7 +2,7 @@ use crate::wrap;
 
 /// Execute the hyperfine command
 pub(crate) fn execute(
-    execution_path: &std::path::Path,
+    benchmark: &crate::types::Benchmark,
     options: &crate::cli::Options,
     date_time: &str,
     forc_version: &str,
@@ -28,22 +28,19 @@ pub(crate) fn execute(
         .display()
         .to_string();
 
-    // Construct the command string for hyperfine
-    let command_string = format!("{forc_path} build --log-level 5");
+    // Construct the command string for hyperfine from the workload of the benchmark
+    let command_string = benchmark
+        .workload
+        .env
+        .iter()
+        .map(|(key, value)| format!("{key}={value}"))
+        .chain(std::iter::once(forc_path))
+        .chain(benchmark.workload.args.iter().cloned())
+        .collect::<Vec<_>>()
+        .join(" ");
 
     // Construct the filename for the hyperfine json file
-    let filename = format!(
-        "{}_hyperfine.json",
-        execution_path
-            .components()
-            .last()
-            .ok_or(wrap!(
-                "Failed to get last component of the execution path for hyperfine".into()
-            ))?
-            .as_os_str()
-            .to_str()
-            .ok_or(wrap!("Failed to convert last component to str".into()))?
-    );
+    let filename = format!("{}_hyperfine.json", benchmark.file_stem());
 
     // Spawn the hyperfine command
     let mut hyperfine_command = std::process::Command::new("hyperfine")
@@ -56,7 +53,7 @@ pub(crate) fn execute(
         .arg(filename.clone())
         .stdin(std::process::Stdio::piped())
         .stdout(std::process::Stdio::piped())
-        .current_dir(execution_path)
+        .current_dir(&benchmark.path)
         .spawn()
         .map_err(|e| wrap!(e.into()))?;
 
@@ -85,7 +82,7 @@ pub(crate) fn execute(
 
     // Copy the hyperfine json file to the output folder
     let mut copy_file_command = std::process::Command::new("cp")
-        .arg(execution_path.join(filename.clone()))
+        .arg(benchmark.path.join(filename.clone()))
         .arg(&current_hyperfine_path)
         .spawn()
         .map_err(|e| wrap!(e.into()))?;
@@ -5,13 +5,27 @@
 #![allow(clippy::struct_excessive_bools)]
 #![allow(clippy::too_many_lines)]
 
+mod affinity;
+mod asm;
+mod baseline;
 mod cli;
+mod commands;
+mod config;
+mod counters;
 mod database;
+mod environment;
 mod error;
 mod hyperfine;
+#[cfg(target_os = "linux")]
+mod procfs;
+mod protocol;
+mod significance;
 mod stats;
+mod suite;
+mod thresholds;
 mod types;
 mod utils;
+mod workload;
 
 use clap::Parser;
 pub use error::Result;
@@ -19,14 +33,56 @@ pub use error::Result;
 const BENCHMARKS_RUN_FOLDER: &str = "runs";
 const BENCHMARKS_STATS_FOLDER: &str = "stats";
 const BENCHMARKS_FLAMEGRAPH_FOLDER: &str = "flamegraphs";
+const BENCHMARKS_GOLDEN_FOLDER: &str = "golden";
 
 const EXPORT_FILE_TYPE_JSON: &str = "json";
 
+/// The exit code used when a benchmark regressed beyond a threshold
+const REGRESSION_EXIT_CODE: i32 = 2;
+
+/// The exit code used when the build of a compared benchmark failed or timed out
+const FAILED_BUILD_EXIT_CODE: i32 = 3;
+
 #[tokio::main]
 pub async fn main() -> Result<()> {
     crate::utils::print_welcome();
-    let options = cli::Options::parse();
-    execute(&options).await.map_err(|e| wrap!(e))?;
+    let cli = cli::Cli::parse();
+    let config = config::Config::load(cli.config.as_deref()).map_err(|e| wrap!(e))?;
+
+    let outcome = match cli.command {
+        cli::Command::Run(args) => {
+            let options = args.resolve(&config).map_err(|e| wrap!(e))?;
+            execute(&options).await.map_err(|e| wrap!(e))?
+        }
+        cli::Command::Compare(options) => {
+            commands::compare(&options, &config).map_err(|e| wrap!(e))?
+        }
+        cli::Command::Report(options) => {
+            commands::report(&options).map_err(|e| wrap!(e))?;
+            thresholds::Outcome::default()
+        }
+        cli::Command::History(options) => {
+            commands::history(&options, &config)
+                .await
+                .map_err(|e| wrap!(e))?;
+            thresholds::Outcome::default()
+        }
+        cli::Command::Db(command) => {
+            commands::db(&command, &config)
+                .await
+                .map_err(|e| wrap!(e))?;
+            thresholds::Outcome::default()
+        }
+    };
+
+    // A broken build is worse than a regression, so it takes precedence
+    if !outcome.failed.is_empty() {
+        std::process::exit(FAILED_BUILD_EXIT_CODE);
+    }
+
+    if !outcome.violations.is_empty() {
+        std::process::exit(REGRESSION_EXIT_CODE);
+    }
 
     Ok(())
 }
@@ -61,10 +117,38 @@ pub async fn main() -> Result<()> {
 ///
 /// If the hyperfine analysis fails.
 ///
-pub async fn execute(options: &cli::Options) -> Result<()> {
+/// If the filtering of the benchmarks fails.
+///
+/// If the baseline cannot be resolved.
+///
+/// If the golden baseline cannot be pinned.
+///
+pub async fn execute(options: &cli::Options) -> Result<thresholds::Outcome> {
     // Setup the benchmarking environment
     utils::setup_system(options).map_err(|e| wrap!(e))?;
 
+    // Setup the database and get the client
+    let client = match &options.database {
+        Some(connection) => Some(database::setup(connection).await.map_err(|e| wrap!(e))?),
+        None => None,
+    };
+
+    // Resolve the explicit baseline before running, so a bad selection fails early
+    let explicit_baseline = if let Some(baseline) = &options.baseline {
+        let resolved = baseline::resolve(baseline, &options.output_folder, client.as_ref())
+            .await
+            .map_err(|e| wrap!(e))?;
+        println!(
+            "Comparing against baseline {baseline} ({} {})",
+            resolved.forc_version, resolved.compiler_hash
+        );
+        stats::check_jobs(resolved.jobs, (options.jobs > 1).then_some(options.jobs))
+            .map_err(|e| wrap!(e.into()))?;
+        Some(resolved)
+    } else {
+        None
+    };
+
     let forc_version = utils::get_forc_version(&options.forc_path).map_err(|e| wrap!(e))?;
 
     let compiler_hash = utils::compute_md5(&options.forc_path).map_err(|e| wrap!(e))?;
@@ -72,11 +156,81 @@ pub async fn execute(options: &cli::Options) -> Result<()> {
     // Get the system specifications
     let system_specs = utils::system_specs().map_err(|e| wrap!(e))?;
 
-    // Get the target path by resolving the canonical path
-    let target_path = std::fs::canonicalize(&options.target).map_err(|e| wrap!(e.into()))?;
+    // Warn about a busy system before benchmarking on it
+    let preflight_warnings = environment::check_load(&system_specs)
+        .into_iter()
+        .inspect(|warning| eprintln!("Warning: {warning}"))
+        .collect::<Vec<_>>();
 
     // Create a mutable array of new benchmarks to be performed
-    let mut current_benchmarks = utils::generate_benchmarks(target_path).map_err(|e| wrap!(e))?;
+    let mut current_benchmarks = Vec::new();
+
+    // Get the root folder of each target the benchmarks are discovered in
+    let mut target_roots = Vec::new();
+
+    for target in &options.targets {
+        // Get the target path by resolving the canonical path
+        let target_path = std::fs::canonicalize(target).map_err(|e| wrap!(e.into()))?;
+
+        let target_root = utils::target_root(&target_path).map_err(|e| wrap!(e))?;
+
+        let benchmarks =
+            utils::generate_benchmarks(&target_root, options.max_depth).map_err(|e| wrap!(e))?;
+        let benchmarks =
+            utils::filter_benchmarks(benchmarks, &target_root, &options.include, &options.exclude)
+                .map_err(|e| wrap!(e))?;
+        let benchmarks = options
+            .suite
+            .select(benchmarks, &target_root, &options.tags)
+            .map_err(|e| wrap!(e))?;
+
+        // Skip the projects already discovered through another target
+        for benchmark in benchmarks {
+            if !current_benchmarks
+                .iter()
+                .any(|existing: &types::Benchmark| existing.path == benchmark.path)
+            {
+                current_benchmarks.push(benchmark);
+            }
+        }
+
+        target_roots.push(target_root);
+    }
+
+    // Building a workspace builds its members, so they are not benchmarked on their own as well
+    let current_benchmarks = utils::skip_workspace_members(current_benchmarks);
+
+    if current_benchmarks.is_empty() {
+        return Err(Box::new(wrap!(
+            "No targets left after applying the include and exclude globs and tags.".into()
+        )));
+    }
+
+    // Every project is benchmarked once for each workload
+    if options
+        .workloads
+        .iter()
+        .any(|workload| !workload.is_default())
+    {
+        for workload in &options.workloads {
+            println!("Workload {workload}");
+        }
+    }
+
+    let mut current_benchmarks: Vec<types::Benchmark> = current_benchmarks
+        .iter()
+        .flat_map(|project| {
+            options.workloads.iter().map(|workload| {
+                let mut benchmark = types::Benchmark::new(&project.name, project.path.clone());
+                benchmark.workload = workload.clone();
+                benchmark.tags.clone_from(&project.tags);
+                benchmark
+            })
+        })
+        .collect();
+
+    // The benchmarks of every target are identified relative to the folder containing all targets
+    let target_root = utils::common_root(&target_roots);
 
     let benchmarks_datetime = utils::get_date_time();
 
@@ -89,71 +243,219 @@ pub async fn execute(options: &cli::Options) -> Result<()> {
         benchmarks_datetime
     );
 
+    // In A/B mode, the same benchmarks are built by the baseline binary
+    let mut interleaved_baseline = match &options.baseline_forc_path {
+        Some(baseline_forc_path) => {
+            let baseline_forc_version =
+                utils::get_forc_version(baseline_forc_path).map_err(|e| wrap!(e))?;
+
+            let baseline_compiler_hash =
+                utils::compute_md5(baseline_forc_path).map_err(|e| wrap!(e))?;
+
+            let baseline_run_path = format!(
+                "{}/{}/{}_{}_{}_baseline.json",
+                options.output_folder.display(),
+                BENCHMARKS_RUN_FOLDER,
+                baseline_forc_version,
+                baseline_compiler_hash,
+                benchmarks_datetime
+            );
+
+            // Fresh benchmarks, since clones would share the frames of the current benchmarks
+            let baseline_benchmarks: Vec<types::Benchmark> = current_benchmarks
+                .iter()
+                .map(|benchmark| {
+                    let mut baseline_benchmark =
+                        types::Benchmark::new(&benchmark.name, benchmark.path.clone());
+                    baseline_benchmark.workload = benchmark.workload.clone();
+                    baseline_benchmark.tags.clone_from(&benchmark.tags);
+                    baseline_benchmark
+                })
+                .collect();
+
+            Some((
+                baseline_forc_path,
+                baseline_forc_version,
+                baseline_compiler_hash,
+                baseline_run_path,
+                baseline_benchmarks,
+            ))
+        }
+        None => None,
+    };
+
+    // Pin each job to its own set of CPUs, so that concurrent builds do not compete for cores
+    let cpu_sets = job_cpu_sets(options.jobs, &options.environment.cpus).map_err(|e| wrap!(e))?;
+    let pinned_cpus = cpu_sets.concat();
+
+    // Set the frequency governor for the duration of the runs, on the CPUs that forc runs on
+    let governor_guard = match &options.environment.governor {
+        Some(governor) => {
+            let governed_cpus = if pinned_cpus.is_empty() {
+                affinity::available_cpus().map_err(|e| wrap!(e))?
+            } else {
+                pinned_cpus.clone()
+            };
+
+            println!("Setting the {governor} frequency governor");
+            Some(environment::set_governor(&governed_cpus, governor).map_err(|e| wrap!(e))?)
+        }
+        None => None,
+    };
+
+    let mut environment = options.environment.record(pinned_cpus, preflight_warnings);
+
+    if options.environment.check_governor {
+        if let Some(warning) = environment::check_governors(&environment.governors) {
+            eprintln!("Warning: {warning}");
+            environment.warnings.push(warning);
+        }
+    }
+
+    // Parallel runs are recorded as such, since they are never compared against serial runs
+    let jobs = (options.jobs > 1).then_some(options.jobs);
+
     // Get the program-specific epoch
     let epoch = std::time::Instant::now();
 
     // Run all of the benchmarks
-    for benchmark in &mut current_benchmarks {
-        println!("Currently profiling : {}", benchmark.path.display());
-        benchmark
-            .run(&epoch, options, &run_path)
-            .map_err(|e| wrap!(e))?;
+    if let Some((baseline_forc_path, _, _, baseline_run_path, baseline_benchmarks)) =
+        &mut interleaved_baseline
+    {
+        let baseline_forc_path: &std::path::Path = baseline_forc_path;
+        let baseline_run_path: &str = baseline_run_path;
+
+        run_jobs(
+            current_benchmarks.iter_mut().zip(baseline_benchmarks),
+            &cpu_sets,
+            |(benchmark, baseline_benchmark), cpus| {
+                println!(
+                    "Currently profiling against the baseline : {}",
+                    benchmark.display_path()
+                );
+                benchmark.cpus = cpus.to_vec();
+                baseline_benchmark.cpus = cpus.to_vec();
+                benchmark.run_interleaved(
+                    baseline_benchmark,
+                    &epoch,
+                    options,
+                    baseline_forc_path,
+                    &run_path,
+                    baseline_run_path,
+                )
+            },
+        )
+        .map_err(|e| wrap!(e))?;
+    } else {
+        run_jobs(
+            current_benchmarks.iter_mut(),
+            &cpu_sets,
+            |benchmark, cpus| {
+                println!("Currently profiling : {}", benchmark.display_path());
+                benchmark.cpus = cpus.to_vec();
+                benchmark.run(&epoch, options, &run_path)
+            },
+        )
+        .map_err(|e| wrap!(e))?;
     }
 
     // Get the end time of the entire benchmarking process
     let end_time = std::time::Instant::now();
 
+    // Restore the frequency governors as soon as the builds are done
+    drop(governor_guard);
+
+    utils::print_warnings(&current_benchmarks);
+    if let Some((_, _, _, _, baseline_benchmarks)) = &interleaved_baseline {
+        utils::print_warnings(baseline_benchmarks);
+    }
+
     // Create a new benchmarks struct
     let benchmarks = types::Benchmarks {
         total_time: end_time.duration_since(epoch),
-        system_specs,
+        system_specs: system_specs.clone(),
         benchmarks: current_benchmarks.clone(),
         forc_version: forc_version.clone(),
         compiler_hash: compiler_hash.clone(),
         benchmarks_datetime: benchmarks_datetime.clone(),
+        target: target_root.clone(),
+        label: options.label.clone(),
+        jobs,
+        environment: environment.clone(),
     };
 
-    let mut previous_benchmarks = String::new();
+    // In A/B mode, the baseline binary's run is the baseline of the comparison
+    let explicit_baseline = match interleaved_baseline {
+        Some((_, forc_version, compiler_hash, baseline_run_path, baseline_benchmarks)) => {
+            let baseline_benchmarks = types::Benchmarks {
+                total_time: end_time.duration_since(epoch),
+                system_specs,
+                benchmarks: baseline_benchmarks,
+                forc_version,
+                compiler_hash,
+                benchmarks_datetime: benchmarks_datetime.clone(),
+                target: target_root,
+                label: None,
+                jobs,
+                environment,
+            };
+
+            // Store the baseline benchmark results
+            utils::store_item(&baseline_benchmarks, &baseline_run_path).map_err(|e| wrap!(e))?;
+
+            Some(baseline_benchmarks)
+        }
+        None => explicit_baseline,
+    };
 
-    // Get the number of files in the output directory
-    let output_dir_file_count = utils::get_files_in_dir(
-        &options.output_folder.join(BENCHMARKS_RUN_FOLDER),
-        EXPORT_FILE_TYPE_JSON,
-    )
-    .map_err(|e| wrap!(e))?
-    .len();
+    // The comparison against the previous benchmarks, used to check the regression thresholds
+    let mut comparison = None;
 
-    // Check if we have previous benchmarks
-    if output_dir_file_count > 0 {
+    // Without an explicit baseline, compare against the latest run in the output folder
+    let latest_baseline = if explicit_baseline.is_none()
+        && !utils::get_files_in_dir(
+            &options.output_folder.join(BENCHMARKS_RUN_FOLDER),
+            EXPORT_FILE_TYPE_JSON,
+        )
+        .map_err(|e| wrap!(e))?
+        .is_empty()
+    {
         let file_path = utils::read_latest_file_in_directory(
             &options.output_folder.join(BENCHMARKS_RUN_FOLDER),
         )
         .map_err(|e| wrap!(e))?;
-        previous_benchmarks = std::fs::read_to_string(file_path).map_err(|e| wrap!(e.into()))?;
-    }
+        let previous_benchmarks =
+            std::fs::read_to_string(file_path).map_err(|e| wrap!(e.into()))?;
+
+        // Deserialize the previous benchmarks
+        let previous_benchmarks = serde_json::from_str::<types::Benchmarks>(&previous_benchmarks)
+            .map_err(|e| wrap!(e.into()))?;
+
+        // Parallel and serial runs are never compared, so the latest run of the other mode is skipped
+        match stats::check_jobs(previous_benchmarks.jobs, benchmarks.jobs) {
+            Ok(()) => Some(previous_benchmarks),
+            Err(e) => {
+                println!("Skipping the comparison against the latest run: {e}");
+                None
+            }
+        }
+    } else {
+        None
+    };
 
     // Store the benchmark results
     utils::store_item(&benchmarks, &run_path).map_err(|e| wrap!(e))?;
 
-    if output_dir_file_count > 0 {
-        println!("Calculating performance regression or improvements");
-        let mut stats_result = stats::Collection::default();
+    if let Some(name) = &options.pin_golden {
+        baseline::pin_golden(&benchmarks, &options.output_folder, name).map_err(|e| wrap!(e))?;
+    }
 
-        // Deserialize the previous benchmarks
-        let previous_benchmarks: types::Benchmarks =
-            serde_json::from_str(&previous_benchmarks).map_err(|e| wrap!(e.into()))?;
+    if let Some(previous_benchmarks) = explicit_baseline.as_ref().or(latest_baseline.as_ref()) {
+        println!("Calculating performance regression or improvements");
 
         // Calculate the performance regression or improvements
-        for (previous, current) in previous_benchmarks
-            .benchmarks
-            .iter()
-            .zip(current_benchmarks.iter())
-        {
-            let stats = stats::calculate(previous, current)?;
-            stats_result
-                .0
-                .push((previous.path.display().to_string(), stats));
-        }
+        let stats_result =
+            stats::compare(previous_benchmarks, &benchmarks).map_err(|e| wrap!(e))?;
 
         let stats_path = format!(
             "{}/{}/{}_{}_{}.json",
@@ -164,24 +466,21 @@ pub async fn execute(options: &cli::Options) -> Result<()> {
             benchmarks_datetime
         );
 
-        utils::store_item(&stats_result, &stats_path).map_err(|e| wrap!(e))?;
+        if options.formats.contains(&types::OutputFormat::Json) {
+            utils::store_item(&stats_result, &stats_path).map_err(|e| wrap!(e))?;
+        }
 
-        if options.print_output {
-            utils::print_stats(
-                &stats_result,
-                &previous_benchmarks.benchmarks,
-                &current_benchmarks,
-            )
-            .map_err(|e| wrap!(e))?;
+        if options.formats.contains(&types::OutputFormat::Table) {
+            utils::print_stats(&stats_result);
+            utils::print_asm_information(previous_benchmarks, &benchmarks).map_err(|e| wrap!(e))?;
         }
-    }
 
-    if options.database {
-        // Setup the database and get the client
-        let client = database::setup().await.map_err(|e| wrap!(e))?;
+        comparison = Some(stats_result);
+    }
 
+    if let Some(client) = &client {
         // Check if we already have benchmarks in the database
-        if database::get_table_count(&client)
+        if database::get_table_count(client)
             .await
             .map_err(|e| wrap!(e))?
             == 0
@@ -189,38 +488,44 @@ pub async fn execute(options: &cli::Options) -> Result<()> {
             println!("Creating the database schema");
 
             // Create the schema in the database
-            database::create_schema(&client)
+            database::create_schema(client)
                 .await
                 .map_err(|e| wrap!(e))?;
 
             // Insert the new benchmarks into the database
-            database::insert_benchmarks(&client, &benchmarks)
+            database::insert_benchmarks(client, &benchmarks)
                 .await
                 .map_err(|e| wrap!(e))?;
         } else {
-            let mut stats_collection = stats::Collection::default();
-            // Get the latest benchmarks from the database so we can compare the results
-            let previous_benchmarks = database::get_latest_benchmarks(&client)
-                .await
-                .map_err(|e| wrap!(e))?;
-
-            // Calculate the performance regression or improvements
-            for (previous, current) in previous_benchmarks
-                .benchmarks
-                .iter()
-                .zip(current_benchmarks.iter())
-            {
-                let stats = stats::calculate(previous, current).map_err(|e| wrap!(e))?;
-                stats_collection
-                    .0
-                    .push((previous.path.display().to_string(), stats));
+            // Compare against the explicit baseline, or the latest benchmarks from the database
+            let stats_collection = if let Some(previous_benchmarks) = &explicit_baseline {
+                Some(stats::compare(previous_benchmarks, &benchmarks).map_err(|e| wrap!(e))?)
+            } else {
+                let previous_benchmarks = database::get_latest_benchmarks(client)
+                    .await
+                    .map_err(|e| wrap!(e))?;
+
+                // The latest run of the other mode is skipped, like the latest run on disk
+                if let Err(e) = stats::check_jobs(previous_benchmarks.jobs, benchmarks.jobs) {
+                    println!("Skipping the comparison against the latest database run: {e}");
+                    None
+                } else {
+                    Some(stats::compare(&previous_benchmarks, &benchmarks).map_err(|e| wrap!(e))?)
+                }
+            };
+
+            if let Some(stats_collection) = stats_collection {
+                database::insert_stats(client, &stats_collection)
+                    .await
+                    .map_err(|e| wrap!(e))?;
+
+                if comparison.is_none() {
+                    comparison = Some(stats_collection);
+                }
             }
-            database::insert_stats(&client, &stats_collection)
-                .await
-                .map_err(|e| wrap!(e))?;
 
             // Insert the new benchmarks into the database
-            database::insert_benchmarks(&client, &benchmarks)
+            database::insert_benchmarks(client, &benchmarks)
                 .await
                 .map_err(|e| wrap!(e))?;
         }
@@ -229,9 +534,9 @@ pub async fn execute(options: &cli::Options) -> Result<()> {
     // If enabled run the hyperfine analysis
     if options.hyperfine {
         for b in &benchmarks.benchmarks {
-            println!("Running hyperfine analysis on {}", b.path.display());
+            println!("Running hyperfine analysis on {}", b.display_path());
             hyperfine::execute(
-                &b.path,
+                b,
                 options,
                 &benchmarks_datetime,
                 &forc_version,
@@ -241,6 +546,143 @@ pub async fn execute(options: &cli::Options) -> Result<()> {
         }
     }
 
+    // Check the regression thresholds and the failed builds against the comparison
+    let outcome = comparison
+        .as_ref()
+        .map_or_else(thresholds::Outcome::default, |comparison| {
+            thresholds::Outcome::check(comparison, &options.thresholds)
+        });
+
+    utils::print_outcome(&outcome);
+
+    Ok(outcome)
+}
+
+/// Get the CPUs that each job is pinned to.
+///
+/// Without explicit CPUs, a serial run is left unpinned and the jobs of a parallel run are
+/// spread over every available CPU. On systems without CPU affinity, the jobs run unpinned.
+///
+/// # Arguments
+///
+/// * `jobs` - The number of benchmarks built concurrently.
+///
+/// * `cpus` - The CPUs to pin forc to, or empty to choose them automatically.
+///
+/// # Returns
+///
+/// A set of CPUs for every job, empty when the job is not pinned.
+///
+/// # Errors
+///
+/// If an explicit CPU is not available to dyno.
+///
+/// If there are fewer CPUs than jobs.
+///
+fn job_cpu_sets(jobs: u32, cpus: &[usize]) -> Result<Vec<Vec<usize>>> {
+    let jobs = usize::try_from(jobs).map_err(|e| wrap!(e.into()))?;
+
+    if jobs == 1 && cpus.is_empty() {
+        return Ok(vec![vec![]]);
+    }
+
+    let available_cpus = match affinity::available_cpus() {
+        Ok(cpus) => cpus,
+        Err(e) => {
+            eprintln!("Running {jobs} jobs without pinning them to CPUs: {e}");
+            return Ok(vec![vec![]; jobs]);
+        }
+    };
+
+    if let Some(cpu) = cpus.iter().find(|cpu| !available_cpus.contains(cpu)) {
+        return Err(Box::new(
+            wrap!(format!("CPU {cpu} is not available").into()),
+        ));
+    }
+
+    let cpus = if cpus.is_empty() {
+        &available_cpus
+    } else {
+        cpus
+    };
+
+    let Some(cpu_sets) = affinity::split(cpus, jobs) else {
+        return Err(Box::new(wrap!(format!(
+            "Cannot run {jobs} jobs on {} CPUs",
+            cpus.len()
+        )
+        .into())));
+    };
+
+    for (job, cpus) in cpu_sets.iter().enumerate() {
+        println!("Job {job} is pinned to CPUs {cpus:?}");
+    }
+
+    Ok(cpu_sets)
+}
+
+/// Run a job for every item, on one thread for each set of CPUs.
+///
+/// Each thread takes the next item once its previous job is done, so that the jobs stay busy
+/// when the items take different times. Once a job fails, no new jobs are started.
+///
+/// # Arguments
+///
+/// * `items` - The items to run a job for.
+///
+/// * `cpu_sets` - The CPUs that the jobs of each thread are pinned to.
+///
+/// * `job` - The job to run for an item, given the CPUs of its thread.
+///
+/// # Errors
+///
+/// If any job fails.
+///
+fn run_jobs<T, I, F>(items: I, cpu_sets: &[Vec<usize>], job: F) -> Result<()>
+where
+    T: Send,
+    I: Iterator<Item = T> + Send,
+    F: Fn(T, &[usize]) -> Result<()> + Sync,
+{
+    let items = std::sync::Mutex::new(items);
+    let failed = std::sync::atomic::AtomicBool::new(false);
+
+    // The errors are not `Send`, so they leave the threads as messages
+    let errors = std::thread::scope(|scope| {
+        let workers = cpu_sets
+            .iter()
+            .map(|cpus| {
+                scope.spawn(|| -> std::result::Result<(), String> {
+                    while !failed.load(std::sync::atomic::Ordering::Acquire) {
+                        let Some(item) = items.lock().expect("Failed to get lock for items").next()
+                        else {
+                            break;
+                        };
+
+                        if let Err(e) = job(item, cpus) {
+                            failed.store(true, std::sync::atomic::Ordering::Release);
+                            return Err(e.to_string());
+                        }
+                    }
+
+                    Ok(())
+                })
+            })
+            .collect::<Vec<_>>();
+
+        workers
+            .into_iter()
+            .filter_map(|worker| match worker.join() {
+                Ok(result) => result.err(),
+                Err(_) => Some("A job panicked".to_string()),
+            })
+            .collect::<Vec<_>>()
+    });
+
+    if !errors.is_empty() {
+        return Err(Box::new(wrap!(errors.join("\n").into())));
+    }
+
     Ok(())
 }
 
@@ -249,6 +691,34 @@ mod tests {
     use super::*;
     use std::path::PathBuf;
 
+    #[test]
+    fn test_run_jobs() {
+        let cpu_sets = vec![vec![0], vec![1], vec![2]];
+        let mut items = (0..10).map(|_| None).collect::<Vec<Option<usize>>>();
+
+        // Every item is taken by exactly one job, which records the CPUs of its thread
+        run_jobs(items.iter_mut(), &cpu_sets, |item, cpus| {
+            *item = Some(cpus[0]);
+            Ok(())
+        })
+        .unwrap();
+
+        assert!(items.iter().all(|item| item.is_some_and(|cpu| cpu < 3)));
+
+        // A failed job stops the remaining jobs from starting
+        let started = std::sync::atomic::AtomicUsize::new(0);
+        let result = run_jobs(0..100, &cpu_sets[..1], |item, _| {
+            started.fetch_add(1, std::sync::atomic::Ordering::Relaxed);
+            if item == 3 {
+                return Err(Box::new(wrap!("job failed".into())));
+            }
+            Ok(())
+        });
+
+        assert!(result.is_err());
+        assert_eq!(started.load(std::sync::atomic::Ordering::Relaxed), 4);
+    }
+
     #[tokio::test]
     async fn test_execute() -> crate::error::Result<()> {
         // Removing all the benchmarks
@@ -262,35 +732,79 @@ mod tests {
         std::thread::sleep(std::time::Duration::from_secs(5));
 
         let options = cli::Options {
+            targets: vec![PathBuf::from("/Users/georgiosdelkos/Documents/GitHub/ourovoros.io/Fuel/sway/test/src/sdk-harness/test_projects/hashing")],
             forc_path: PathBuf::from("../sway/target/release/forc"),
-            target: PathBuf::from("/Users/georgiosdelkos/Documents/GitHub/ourovoros.io/Fuel/sway/test/src/sdk-harness/test_projects/hashing"),
+            baseline_forc_path: None,
             output_folder: PathBuf::from("benchmarks"),
+            formats: vec![types::OutputFormat::Json, types::OutputFormat::Table],
+            iterations: 1,
+            warmup: 0,
+            mode: types::BuildMode::Warm,
+            touch: None,
+            workloads: vec![workload::Workload::default()],
+            sampling_interval: std::time::Duration::from_millis(100),
+            counters: false,
+            jobs: 1,
+            environment: environment::Controls::default(),
+            timeout: None,
+            timeouts: std::collections::BTreeMap::new(),
+            total_timeout: None,
+            include: vec![],
+            exclude: vec![],
+            max_depth: None,
+            suite: suite::Suite::default(),
+            tags: vec![],
             flamegraph: true,
-            print_output: true,
-            database: false,
             hyperfine: false,
             max_iterations: 2,
+            database: None,
+            baseline: None,
+            label: None,
+            pin_golden: None,
+            thresholds: thresholds::Thresholds::default(),
         };
 
         let result = execute(&options).await.map_err(|e| wrap!(e))?;
-        assert_eq!(result, ());
+        assert!(result.is_empty());
 
         // Warm up the system
         std::thread::sleep(std::time::Duration::from_secs(5));
 
         let options = cli::Options {
+            targets: vec![PathBuf::from("/Users/georgiosdelkos/Documents/GitHub/ourovoros.io/Fuel/sway/test/src/sdk-harness/test_projects/hashing")],
             forc_path: PathBuf::from("../sway_slow/target/release/forc"),
-            target: PathBuf::from("/Users/georgiosdelkos/Documents/GitHub/ourovoros.io/Fuel/sway/test/src/sdk-harness/test_projects/hashing"),
+            baseline_forc_path: None,
             output_folder: PathBuf::from("benchmarks"),
+            formats: vec![types::OutputFormat::Json, types::OutputFormat::Table],
+            iterations: 1,
+            warmup: 0,
+            mode: types::BuildMode::Warm,
+            touch: None,
+            workloads: vec![workload::Workload::default()],
+            sampling_interval: std::time::Duration::from_millis(100),
+            counters: false,
+            jobs: 1,
+            environment: environment::Controls::default(),
+            timeout: None,
+            timeouts: std::collections::BTreeMap::new(),
+            total_timeout: None,
+            include: vec![],
+            exclude: vec![],
+            max_depth: None,
+            suite: suite::Suite::default(),
+            tags: vec![],
             flamegraph: true,
-            print_output: true,
-            database: false,
             hyperfine: false,
             max_iterations: 2,
+            database: None,
+            baseline: None,
+            label: None,
+            pin_golden: None,
+            thresholds: thresholds::Thresholds::default(),
         };
 
         let result = execute(&options).await.map_err(|e| wrap!(e))?;
-        assert_eq!(result, ());
+        assert!(result.is_empty());
 
         println!("Executing improvement testing");
 
@@ -298,18 +812,40 @@ mod tests {
         std::thread::sleep(std::time::Duration::from_secs(5));
 
         let options = cli::Options {
+            targets: vec![PathBuf::from("/Users/georgiosdelkos/Documents/GitHub/ourovoros.io/Fuel/sway/test/src/sdk-harness/test_projects/hashing")],
             forc_path: PathBuf::from("../sway/target/release/forc"),
-            target: PathBuf::from("/Users/georgiosdelkos/Documents/GitHub/ourovoros.io/Fuel/sway/test/src/sdk-harness/test_projects/hashing"),
+            baseline_forc_path: None,
             output_folder: PathBuf::from("benchmarks"),
+            formats: vec![types::OutputFormat::Json, types::OutputFormat::Table],
+            iterations: 1,
+            warmup: 0,
+            mode: types::BuildMode::Warm,
+            touch: None,
+            workloads: vec![workload::Workload::default()],
+            sampling_interval: std::time::Duration::from_millis(100),
+            counters: false,
+            jobs: 1,
+            environment: environment::Controls::default(),
+            timeout: None,
+            timeouts: std::collections::BTreeMap::new(),
+            total_timeout: None,
+            include: vec![],
+            exclude: vec![],
+            max_depth: None,
+            suite: suite::Suite::default(),
+            tags: vec![],
             flamegraph: true,
-            print_output: true,
-            database: false,
             hyperfine: false,
             max_iterations: 2,
+            database: None,
+            baseline: None,
+            label: None,
+            pin_golden: None,
+            thresholds: thresholds::Thresholds::default(),
         };
 
         let result = execute(&options).await.map_err(|e| wrap!(e))?;
-        assert_eq!(result, ());
+        assert!(result.is_empty());
 
         Ok(())
     }
@@ -0,0 +1,625 @@
+use std::collections::{HashMap, HashSet};
+use std::time::{Duration, Instant};
+
+use crate::types::{ProcessFrame, ProcessTreeUsage, ProcessUsage};
+
+/// The cumulative usage counters of a process at a point in time
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ProcessCounters {
+    /// The time the process has spent on a CPU
+    pub cpu_time: Duration,
+    /// The resident memory of the process, in bytes
+    pub memory: u64,
+    /// The virtual memory of the process, in bytes
+    pub virtual_memory: u64,
+    /// The total number of bytes the process has read from storage
+    pub read_bytes: u64,
+    /// The total number of bytes the process has written to storage
+    pub written_bytes: u64,
+}
+
+/// Samples the usage of a process from the `/proc/<pid>` files, cheap enough to run every millisecond
+///
+/// CPU usage and disk bytes are the differences between consecutive counter reads.
+pub struct Sampler {
+    pid: u32,
+    num_cpus: usize,
+    page_size: u64,
+    clock_ticks: u64,
+    previous: Option<(Instant, ProcessCounters)>,
+}
+
+impl Sampler {
+    /// Creates a sampler of the process with the given `pid`.
+    pub fn new(pid: u32, num_cpus: usize) -> Self {
+        // SAFETY: `sysconf` has no preconditions and only reads system configuration
+        let (page_size, clock_ticks) = unsafe {
+            (
+                libc::sysconf(libc::_SC_PAGESIZE),
+                libc::sysconf(libc::_SC_CLK_TCK),
+            )
+        };
+
+        Self {
+            pid,
+            num_cpus: num_cpus.max(1),
+            page_size: u64::try_from(page_size).unwrap_or(4096),
+            clock_ticks: u64::try_from(clock_ticks).unwrap_or(100),
+            previous: None,
+        }
+    }
+
+    /// Samples the usage of the process since the previous sample.
+    ///
+    /// # Returns
+    ///
+    /// `None` if the process has exited.
+    pub fn sample(&mut self) -> Option<ProcessUsage> {
+        let now = Instant::now();
+        let counters = read_counters(self.pid, self.page_size, self.clock_ticks)?;
+
+        let (cpu_usage, disk_read_bytes, disk_written_bytes) = match self.previous {
+            Some((previous_time, previous)) => {
+                let elapsed = now.duration_since(previous_time).as_secs_f64();
+                let cpu_time = counters.cpu_time.saturating_sub(previous.cpu_time);
+
+                let cpu_usage = if elapsed > 0.0 {
+                    cpu_time.as_secs_f64() / elapsed * 100.0 / self.num_cpus as f64
+                } else {
+                    0.0
+                };
+
+                (
+                    cpu_usage,
+                    counters.read_bytes.saturating_sub(previous.read_bytes),
+                    counters
+                        .written_bytes
+                        .saturating_sub(previous.written_bytes),
+                )
+            }
+            None => (0.0, counters.read_bytes, counters.written_bytes),
+        };
+
+        self.previous = Some((now, counters));
+
+        #[allow(clippy::cast_possible_truncation)]
+        Some(ProcessUsage {
+            cpu_usage: cpu_usage as f32,
+            memory_usage: counters.memory,
+            virtual_memory_usage: counters.virtual_memory,
+            disk_total_written_bytes: counters.written_bytes,
+            disk_written_bytes,
+            disk_total_read_bytes: counters.read_bytes,
+            disk_read_bytes,
+        })
+    }
+
+    /// Returns the counters of the latest sample.
+    fn last_counters(&self) -> Option<ProcessCounters> {
+        self.previous.map(|(_, counters)| counters)
+    }
+}
+
+/// Samples the usage of a process and of every process it spawns, directly or indirectly
+///
+/// The descendants are discovered again on every sample, so short-lived helper processes are
+/// picked up as soon as they appear. The storage totals keep the bytes of the descendants that
+/// have already exited.
+pub struct TreeSampler {
+    root: u32,
+    num_cpus: usize,
+    samplers: HashMap<u32, (String, Sampler)>,
+    exited_read_bytes: u64,
+    exited_written_bytes: u64,
+}
+
+impl TreeSampler {
+    /// Creates a sampler of the process tree rooted at `root`.
+    ///
+    /// # Arguments
+    ///
+    /// * `root` - The pid of the root process.
+    ///
+    /// * `num_cpus` - The number of CPUs of the system.
+    ///
+    pub fn new(root: u32, num_cpus: usize) -> Self {
+        Self {
+            root,
+            num_cpus,
+            samplers: HashMap::new(),
+            exited_read_bytes: 0,
+            exited_written_bytes: 0,
+        }
+    }
+
+    /// Samples the usage of the process tree since the previous sample.
+    ///
+    /// # Returns
+    ///
+    /// `None` if the root process has exited.
+    pub fn sample(&mut self) -> Option<ProcessTreeUsage> {
+        if read_state(self.root).is_none_or(|state| state == 'Z' || state == 'X') {
+            return None;
+        }
+
+        let mut pids = descendants(self.root);
+        pids.insert(0, self.root);
+
+        let mut total = ProcessUsage::default();
+        let mut processes = Vec::with_capacity(pids.len());
+        let mut alive = HashSet::with_capacity(pids.len());
+
+        for pid in pids {
+            let (name, sampler) = self
+                .samplers
+                .entry(pid)
+                .or_insert_with(|| (read_name(pid), Sampler::new(pid, self.num_cpus)));
+
+            let Some(usage) = sampler.sample() else {
+                continue;
+            };
+
+            alive.insert(pid);
+
+            total.cpu_usage += usage.cpu_usage;
+            total.memory_usage += usage.memory_usage;
+            total.virtual_memory_usage += usage.virtual_memory_usage;
+            total.disk_total_written_bytes += usage.disk_total_written_bytes;
+            total.disk_written_bytes += usage.disk_written_bytes;
+            total.disk_total_read_bytes += usage.disk_total_read_bytes;
+            total.disk_read_bytes += usage.disk_read_bytes;
+
+            processes.push(ProcessFrame {
+                pid,
+                name: name.clone(),
+                cpu_usage: usage.cpu_usage,
+                memory_usage: usage.memory_usage,
+                virtual_memory_usage: usage.virtual_memory_usage,
+                disk_written_bytes: usage.disk_written_bytes,
+                disk_read_bytes: usage.disk_read_bytes,
+            });
+        }
+
+        // Keep the storage totals of the processes that exited since the previous sample
+        let exited: Vec<u32> = self
+            .samplers
+            .keys()
+            .filter(|pid| !alive.contains(pid))
+            .copied()
+            .collect();
+
+        for pid in exited {
+            if let Some(counters) = self
+                .samplers
+                .remove(&pid)
+                .and_then(|(_, sampler)| sampler.last_counters())
+            {
+                self.exited_read_bytes += counters.read_bytes;
+                self.exited_written_bytes += counters.written_bytes;
+            }
+        }
+
+        total.disk_total_read_bytes += self.exited_read_bytes;
+        total.disk_total_written_bytes += self.exited_written_bytes;
+
+        Some(ProcessTreeUsage { total, processes })
+    }
+}
+
+/// Finds the descendants of a process, parents before their children.
+///
+/// The `children` files of the threads of each process are used when the kernel provides them,
+/// otherwise the parents of every process are read from `/proc`.
+pub fn descendants(root: u32) -> Vec<u32> {
+    let mut result = Vec::new();
+
+    if let Some(children) = read_children(root) {
+        let mut queue = std::collections::VecDeque::from(children);
+
+        while let Some(pid) = queue.pop_front() {
+            queue.extend(read_children(pid).unwrap_or_default());
+            result.push(pid);
+        }
+    } else {
+        let mut children: HashMap<u32, Vec<u32>> = HashMap::new();
+
+        for (pid, parent) in read_parents() {
+            children.entry(parent).or_default().push(pid);
+        }
+
+        let mut queue = std::collections::VecDeque::from([root]);
+
+        while let Some(pid) = queue.pop_front() {
+            if let Some(children) = children.remove(&pid) {
+                result.extend(&children);
+                queue.extend(children);
+            }
+        }
+    }
+
+    result
+}
+
+/// Kills a process and every process it spawned.
+///
+/// The tree is stopped before it is killed, so that no process can spawn a new child that
+/// escapes the kill, and killed children are not reparented before they are found.
+pub fn kill_tree(root: u32) {
+    let signal = |pid: u32, signal: libc::c_int| {
+        // SAFETY: `kill` has no memory safety preconditions
+        unsafe {
+            libc::kill(pid.cast_signed(), signal);
+        }
+    };
+
+    signal(root, libc::SIGSTOP);
+
+    // Stop the descendants until no new process shows up
+    let mut stopped = vec![root];
+
+    loop {
+        let pids = descendants(root)
+            .into_iter()
+            .filter(|pid| !stopped.contains(pid))
+            .collect::<Vec<_>>();
+
+        if pids.is_empty() {
+            break;
+        }
+
+        for pid in pids {
+            signal(pid, libc::SIGSTOP);
+            stopped.push(pid);
+        }
+    }
+
+    for pid in stopped {
+        signal(pid, libc::SIGKILL);
+    }
+}
+
+/// Reads the children of every thread of a process.
+///
+/// # Returns
+///
+/// `None` if the kernel does not provide the `children` files or the process has exited.
+fn read_children(pid: u32) -> Option<Vec<u32>> {
+    let mut result = Vec::new();
+
+    for task in std::fs::read_dir(format!("/proc/{pid}/task"))
+        .ok()?
+        .flatten()
+    {
+        let children = std::fs::read_to_string(task.path().join("children")).ok()?;
+        result.extend(
+            children
+                .split_whitespace()
+                .filter_map(|pid| pid.parse::<u32>().ok()),
+        );
+    }
+
+    Some(result)
+}
+
+/// Reads the parent of every process of the system.
+fn read_parents() -> Vec<(u32, u32)> {
+    let Ok(entries) = std::fs::read_dir("/proc") else {
+        return vec![];
+    };
+
+    entries
+        .flatten()
+        .filter_map(|entry| entry.file_name().to_str()?.parse::<u32>().ok())
+        .filter_map(|pid| {
+            let stat = std::fs::read_to_string(format!("/proc/{pid}/stat")).ok()?;
+            Some((pid, parse_parent(&stat)?))
+        })
+        .collect()
+}
+
+/// Reads the state of a process.
+fn read_state(pid: u32) -> Option<char> {
+    let stat = std::fs::read_to_string(format!("/proc/{pid}/stat")).ok()?;
+    stat.get(stat.rfind(')')? + 1..)?
+        .trim_start()
+        .chars()
+        .next()
+}
+
+/// Reads the command name of a process.
+fn read_name(pid: u32) -> String {
+    std::fs::read_to_string(format!("/proc/{pid}/comm"))
+        .map(|name| name.trim_end().to_string())
+        .unwrap_or_default()
+}
+
+/// Reads the cumulative usage counters of a process.
+///
+/// # Returns
+///
+/// `None` if the process has exited or is a zombie.
+pub fn read_counters(pid: u32, page_size: u64, clock_ticks: u64) -> Option<ProcessCounters> {
+    let stat = std::fs::read_to_string(format!("/proc/{pid}/stat")).ok()?;
+    let (state, tick_cpu_time) = parse_stat(&stat, clock_ticks)?;
+
+    // The process has exited and is only waiting to be reaped
+    if state == 'Z' || state == 'X' {
+        return None;
+    }
+
+    // The clock ticks are too coarse for millisecond frames, so they are only a fallback
+    let cpu_time = read_cpu_time(pid).unwrap_or(tick_cpu_time);
+
+    let statm = std::fs::read_to_string(format!("/proc/{pid}/statm")).ok()?;
+    let (virtual_memory, memory) = parse_statm(&statm, page_size)?;
+
+    // The I/O counters are unreadable without ptrace access to the process
+    let (read_bytes, written_bytes) = std::fs::read_to_string(format!("/proc/{pid}/io"))
+        .ok()
+        .map(|io| parse_io(&io))
+        .unwrap_or_default();
+
+    Some(ProcessCounters {
+        cpu_time,
+        memory,
+        virtual_memory,
+        read_bytes,
+        written_bytes,
+    })
+}
+
+/// Reads the CPU time of every thread of a process, including the threads that have exited, at
+/// nanosecond resolution.
+///
+/// The CPU clock of the process is used rather than `/proc/<pid>/schedstat`, which is as precise
+/// but only covers the main thread of the process.
+///
+/// # Returns
+///
+/// `None` if the CPU clock of the process cannot be read.
+fn read_cpu_time(pid: u32) -> Option<Duration> {
+    let pid = libc::pid_t::try_from(pid).ok()?;
+    let mut clock: libc::clockid_t = 0;
+
+    // SAFETY: `clock` is a valid pointer to a clock id
+    if unsafe { libc::clock_getcpuclockid(pid, std::ptr::from_mut(&mut clock)) } != 0 {
+        return None;
+    }
+
+    let mut time = libc::timespec {
+        tv_sec: 0,
+        tv_nsec: 0,
+    };
+
+    // SAFETY: `time` is a valid pointer to a timespec
+    if unsafe { libc::clock_gettime(clock, std::ptr::from_mut(&mut time)) } != 0 {
+        return None;
+    }
+
+    Some(Duration::new(
+        u64::try_from(time.tv_sec).ok()?,
+        u32::try_from(time.tv_nsec).ok()?,
+    ))
+}
+
+/// Parses the state and the user and system CPU time of `/proc/<pid>/stat`.
+///
+/// The CPU time covers every thread of the process, at the resolution of the clock ticks.
+fn parse_stat(stat: &str, clock_ticks: u64) -> Option<(char, Duration)> {
+    // The command name is in parentheses and may itself contain spaces and parentheses
+    let fields: Vec<&str> = stat
+        .get(stat.rfind(')')? + 1..)?
+        .split_whitespace()
+        .collect();
+
+    let state = fields.first()?.chars().next()?;
+
+    // `utime` and `stime` are the 14th and 15th fields, counted from the pid
+    let utime: u64 = fields.get(11)?.parse().ok()?;
+    let stime: u64 = fields.get(12)?.parse().ok()?;
+
+    let ticks = utime + stime;
+    let cpu_time = Duration::from_secs(ticks / clock_ticks)
+        + Duration::from_nanos((ticks % clock_ticks) * 1_000_000_000 / clock_ticks);
+
+    Some((state, cpu_time))
+}
+
+/// Parses the parent pid of `/proc/<pid>/stat`.
+fn parse_parent(stat: &str) -> Option<u32> {
+    stat.get(stat.rfind(')')? + 1..)?
+        .split_whitespace()
+        .nth(1)?
+        .parse()
+        .ok()
+}
+
+/// Parses the virtual and resident memory of `/proc/<pid>/statm`, in bytes.
+fn parse_statm(statm: &str, page_size: u64) -> Option<(u64, u64)> {
+    let mut fields = statm.split_whitespace();
+    let size: u64 = fields.next()?.parse().ok()?;
+    let resident: u64 = fields.next()?.parse().ok()?;
+    Some((size * page_size, resident * page_size))
+}
+
+/// Parses the bytes read from and written to storage of `/proc/<pid>/io`.
+fn parse_io(io: &str) -> (u64, u64) {
+    let mut read_bytes = 0;
+    let mut written_bytes = 0;
+
+    for line in io.lines() {
+        let Some((key, value)) = line.split_once(':') else {
+            continue;
+        };
+
+        let Ok(value) = value.trim().parse() else {
+            continue;
+        };
+
+        match key {
+            "read_bytes" => read_bytes = value,
+            "write_bytes" => written_bytes = value,
+            _ => {}
+        }
+    }
+
+    (read_bytes, written_bytes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_proc_files() {
+        let stat =
+            "1234 (forc (build)) R 1 1234 1234 0 -1 4194304 2630 0 0 0 250 75 0 0 20 0 9 0 1 5 6";
+        assert_eq!(
+            parse_stat(stat, 100),
+            Some(('R', Duration::from_millis(3250)))
+        );
+        assert_eq!(parse_parent(stat), Some(1));
+
+        assert_eq!(
+            parse_statm("2048 512 100 10 0 300 0\n", 4096),
+            Some((2048 * 4096, 512 * 4096))
+        );
+
+        let io = "rchar: 100\nwchar: 50\nsyscr: 1\nsyscw: 1\nread_bytes: 4096\nwrite_bytes: 8192\ncancelled_write_bytes: 0\n";
+        assert_eq!(parse_io(io), (4096, 8192));
+    }
+
+    #[test]
+    fn test_sample_own_process() {
+        let mut sampler = Sampler::new(std::process::id(), 1);
+
+        let first = sampler.sample().expect("Failed to sample own process");
+        assert!(first.memory_usage > 0);
+        assert!(first.virtual_memory_usage >= first.memory_usage);
+
+        // Spin for a while so the second sample sees CPU time
+        let start = Instant::now();
+        while start.elapsed() < Duration::from_millis(100) {
+            std::hint::spin_loop();
+        }
+
+        let second = sampler.sample().expect("Failed to sample own process");
+        assert!(second.cpu_usage > 0.0);
+    }
+
+    #[test]
+    fn test_sample_busy_process_every_millisecond() {
+        let mut child = std::process::Command::new("sh")
+            .arg("-c")
+            .arg("while :; do :; done")
+            .spawn()
+            .expect("Failed to spawn the busy process");
+
+        let mut sampler = Sampler::new(child.id(), 1);
+        let mut usages = Vec::new();
+
+        for _ in 0..200 {
+            std::thread::sleep(Duration::from_millis(1));
+            usages.extend(sampler.sample().map(|usage| usage.cpu_usage));
+        }
+
+        let _ = child.kill();
+        let _ = child.wait();
+
+        // With clock tick resolution most frames would be 0%, and the rest spikes of about 1000%
+        let idle = usages.iter().filter(|usage| **usage == 0.0).count();
+        let peak = usages.iter().copied().fold(0.0, f32::max);
+
+        assert!(usages.len() > 100);
+        assert!(
+            idle < usages.len() / 4,
+            "{idle} of {} frames at 0%",
+            usages.len()
+        );
+        assert!(peak <= 150.0, "Peak of {peak}%");
+    }
+
+    #[test]
+    fn test_sample_process_tree() {
+        let mut child = std::process::Command::new("sh")
+            .arg("-c")
+            .arg("sleep 5 & wait")
+            .spawn()
+            .expect("Failed to spawn the process tree");
+
+        let root = child.id();
+
+        // Wait for the shell to spawn its child
+        let start = Instant::now();
+        while descendants(root).is_empty() && start.elapsed() < Duration::from_secs(5) {
+            std::thread::sleep(Duration::from_millis(10));
+        }
+
+        let mut sampler = TreeSampler::new(root, 1);
+        let usage = sampler.sample().expect("Failed to sample the process tree");
+
+        assert_eq!(
+            usage.processes.first().map(|process| process.pid),
+            Some(root)
+        );
+        assert!(usage
+            .processes
+            .iter()
+            .any(|process| process.name == "sleep"));
+        assert_eq!(
+            usage.total.memory_usage,
+            usage
+                .processes
+                .iter()
+                .map(|process| process.memory_usage)
+                .sum::<u64>()
+        );
+
+        for pid in descendants(root) {
+            // SAFETY: `kill` has no memory safety preconditions
+            unsafe {
+                libc::kill(pid.cast_signed(), libc::SIGKILL);
+            }
+        }
+        let _ = child.kill();
+        let _ = child.wait();
+
+        assert!(sampler.sample().is_none());
+    }
+
+    #[test]
+    fn test_kill_tree() {
+        let mut child = std::process::Command::new("sh")
+            .arg("-c")
+            .arg("sleep 30 & sleep 30 & wait")
+            .spawn()
+            .expect("Failed to spawn the process tree");
+
+        let root = child.id();
+
+        // Wait for the shell to spawn its children
+        let start = Instant::now();
+        while descendants(root).len() < 2 && start.elapsed() < Duration::from_secs(5) {
+            std::thread::sleep(Duration::from_millis(10));
+        }
+
+        let pids = descendants(root);
+        assert_eq!(pids.len(), 2);
+
+        kill_tree(root);
+        let _ = child.wait();
+
+        // The killed children are reaped by their new parent, or left as zombies without one
+        let start = Instant::now();
+        while pids
+            .iter()
+            .any(|pid| read_state(*pid).is_some_and(|state| state != 'Z'))
+        {
+            assert!(
+                start.elapsed() < Duration::from_secs(5),
+                "The children outlived the kill"
+            );
+            std::thread::sleep(Duration::from_millis(10));
+        }
+    }
+}
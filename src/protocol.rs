@@ -0,0 +1,298 @@
+use crossbeam_channel::{unbounded, Receiver, Sender};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::io::{BufRead, BufReader};
+use std::os::unix::net::{UnixListener, UnixStream};
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use crate::wrap;
+
+/// The version of the phase protocol understood by dyno
+pub const PROTOCOL_VERSION: u32 = 1;
+
+/// The environment variable holding the path of the socket that the build reports phases to
+pub const SOCKET_ENV_VAR: &str = "DYNO_SOCKET";
+
+/// How long to wait for the messages still in flight once the build has exited
+const DRAIN_TIMEOUT: Duration = Duration::from_millis(50);
+
+/// A message of the phase protocol, sent by the build as a single JSON line
+///
+/// Every connection starts with a `hello` message carrying the version of the protocol.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum Message {
+    /// The start of a connection
+    Hello {
+        /// The version of the protocol spoken by the build
+        version: u32,
+    },
+    /// The start of a phase, nested in the `parent` phase if given
+    PhaseStart {
+        /// The id of the phase, unique within the build
+        id: u64,
+        /// The id of the enclosing phase
+        #[serde(default)]
+        parent: Option<u64>,
+        /// The name of the phase
+        name: String,
+        /// Key/value metadata of the phase, such as item or function counts
+        #[serde(default)]
+        metadata: BTreeMap<String, serde_json::Value>,
+    },
+    /// The end of a phase
+    PhaseEnd {
+        /// The id of the phase
+        id: u64,
+        /// Key/value metadata added to the phase, for values only known at its end
+        #[serde(default)]
+        metadata: BTreeMap<String, serde_json::Value>,
+    },
+    /// An amount added to a named counter of a phase
+    Counter {
+        /// The id of the phase
+        phase: u64,
+        /// The name of the counter
+        name: String,
+        /// The amount added to the counter
+        value: f64,
+    },
+    /// An instant event, optionally within a phase
+    Event {
+        /// The name of the event
+        name: String,
+        /// The id of the phase the event happened in
+        #[serde(default)]
+        phase: Option<u64>,
+        /// Key/value metadata of the event
+        #[serde(default)]
+        metadata: BTreeMap<String, serde_json::Value>,
+    },
+    /// The bytecode information of the build
+    Info {
+        /// The bytecode information
+        data: serde_json::Value,
+    },
+}
+
+/// What the listener received from the build
+#[derive(Debug, Clone, PartialEq)]
+pub enum Received {
+    /// A message, along with the time it was received at
+    Message(Duration, Message),
+    /// A line that could not be handled, to be recorded as a warning of the benchmark
+    Warning(String),
+}
+
+/// Listens on a Unix socket for the phase messages of a single build
+///
+/// Each connection is read on its own thread, so a build may report from several processes.
+/// Lines that are not valid messages are received as warnings, and never fail the build.
+pub struct Listener {
+    path: PathBuf,
+    stop: Arc<AtomicBool>,
+    receiver: Receiver<Received>,
+}
+
+impl Listener {
+    /// Bind a new socket for a build.
+    ///
+    /// # Arguments
+    ///
+    /// * `epoch` - The epoch that the times of the messages are relative to.
+    ///
+    /// # Errors
+    ///
+    /// If the socket cannot be bound.
+    ///
+    pub fn bind(epoch: &Instant) -> crate::Result<Self> {
+        static NEXT_ID: AtomicUsize = AtomicUsize::new(0);
+
+        let path = std::env::temp_dir().join(format!(
+            "dyno-{}-{}.sock",
+            std::process::id(),
+            NEXT_ID.fetch_add(1, Ordering::Relaxed)
+        ));
+
+        let _ = std::fs::remove_file(&path);
+        let listener = UnixListener::bind(&path).map_err(|e| wrap!(e.into()))?;
+
+        let stop = Arc::new(AtomicBool::new(false));
+        let (sender, receiver) = unbounded();
+
+        let epoch = *epoch;
+        let accept_stop = stop.clone();
+
+        std::thread::spawn(move || {
+            for stream in listener.incoming() {
+                // The listener is woken up by a final connection once the build has exited
+                if accept_stop.load(Ordering::Acquire) {
+                    break;
+                }
+
+                let Ok(stream) = stream else {
+                    continue;
+                };
+
+                let sender = sender.clone();
+                std::thread::spawn(move || read_connection(stream, &epoch, &sender));
+            }
+        });
+
+        Ok(Self {
+            path,
+            stop,
+            receiver,
+        })
+    }
+
+    /// Returns the path of the socket, to be passed to the build in [`SOCKET_ENV_VAR`].
+    pub fn path(&self) -> &std::path::Path {
+        &self.path
+    }
+
+    /// Returns the receiver of the messages of the build.
+    pub fn receiver(&self) -> &Receiver<Received> {
+        &self.receiver
+    }
+
+    /// Stop accepting connections and collect the messages still in flight.
+    pub fn finish(self) -> Vec<Received> {
+        self.stop.store(true, Ordering::Release);
+
+        // Wake up the accepting thread so that it sees the stop flag and drops its sender
+        let _ = UnixStream::connect(&self.path);
+        let _ = std::fs::remove_file(&self.path);
+
+        let mut remaining = Vec::new();
+
+        while let Ok(message) = self.receiver.recv_timeout(DRAIN_TIMEOUT) {
+            remaining.push(message);
+        }
+
+        remaining
+    }
+}
+
+/// Read the messages of a single connection until it is closed.
+fn read_connection(stream: UnixStream, epoch: &Instant, sender: &Sender<Received>) {
+    let mut greeted = false;
+
+    for line in BufReader::new(stream).lines() {
+        let Ok(line) = line else {
+            break;
+        };
+
+        let line = line.trim();
+        if line.is_empty() {
+            continue;
+        }
+
+        let time = epoch.elapsed();
+
+        let received = match serde_json::from_str::<Message>(line) {
+            Ok(Message::Hello { version }) if version > PROTOCOL_VERSION => {
+                let _ = sender.send(Received::Warning(format!(
+                    "Ignoring phase messages of protocol version {version}, the latest supported version is {PROTOCOL_VERSION}"
+                )));
+                return;
+            }
+            Ok(Message::Hello { .. }) => {
+                greeted = true;
+                continue;
+            }
+            Ok(_) if !greeted => Received::Warning(format!(
+                "Ignoring phase message sent before the hello message: {line}"
+            )),
+            Ok(message) => Received::Message(time, message),
+            Err(e) => Received::Warning(format!("Ignoring invalid phase message \"{line}\": {e}")),
+        };
+
+        if sender.send(received).is_err() {
+            break;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+
+    #[test]
+    fn test_parse_messages() {
+        let message: Message = serde_json::from_str(
+            r#"{"type":"phase_start","id":2,"parent":1,"name":"type check","metadata":{"functions":12}}"#,
+        )
+        .unwrap();
+
+        assert_eq!(
+            message,
+            Message::PhaseStart {
+                id: 2,
+                parent: Some(1),
+                name: "type check".to_string(),
+                metadata: BTreeMap::from([("functions".to_string(), serde_json::json!(12))]),
+            }
+        );
+
+        let message: Message = serde_json::from_str(r#"{"type":"phase_end","id":2}"#).unwrap();
+        assert_eq!(
+            message,
+            Message::PhaseEnd {
+                id: 2,
+                metadata: BTreeMap::new()
+            }
+        );
+
+        assert!(serde_json::from_str::<Message>(r#"{"type":"phase_stop","id":2}"#).is_err());
+    }
+
+    #[test]
+    fn test_listen_for_messages() -> crate::Result<()> {
+        let epoch = Instant::now();
+        let listener = Listener::bind(&epoch)?;
+
+        let mut stream = UnixStream::connect(listener.path()).map_err(|e| wrap!(e.into()))?;
+        writeln!(
+            stream,
+            "{}",
+            [
+                r#"{"type":"phase_start","id":1,"name":"early"}"#,
+                r#"{"type":"hello","version":1}"#,
+                r#"{"type":"phase_start","id":1,"name":"compile"}"#,
+                "not json",
+                r#"{"type":"counter","phase":1,"name":"items","value":3}"#,
+                r#"{"type":"phase_end","id":1}"#,
+            ]
+            .join("\n")
+        )
+        .map_err(|e| wrap!(e.into()))?;
+        drop(stream);
+
+        let first = listener
+            .receiver()
+            .recv_timeout(Duration::from_secs(5))
+            .map_err(|e| wrap!(e.into()))?;
+        assert!(matches!(first, Received::Warning(_)));
+
+        let second = listener
+            .receiver()
+            .recv_timeout(Duration::from_secs(5))
+            .map_err(|e| wrap!(e.into()))?;
+        assert!(
+            matches!(second, Received::Message(_, Message::PhaseStart { ref name, .. }) if name == "compile")
+        );
+
+        let path = listener.path().to_path_buf();
+        let remaining = listener.finish();
+        assert_eq!(remaining.len(), 3);
+        assert!(matches!(remaining[0], Received::Warning(_)));
+        assert!(!path.exists());
+
+        Ok(())
+    }
+}
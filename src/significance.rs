@@ -0,0 +1,348 @@
+use serde::{Deserialize, Serialize};
+
+/// The significance level used to reject the hypothesis that two samples share the same mean
+const ALPHA: f64 = 0.05;
+
+/// The effect size below which a statistically significant change is considered negligible
+const NEGLIGIBLE_EFFECT_SIZE: f64 = 0.2;
+
+/// The number of bootstrap resamples used to estimate the confidence interval
+const BOOTSTRAP_RESAMPLES: usize = 2000;
+
+/// The seed of the bootstrap resampling, fixed so that repeated comparisons are reproducible
+const BOOTSTRAP_SEED: u64 = 0x5EED_D1E0;
+
+/// The verdict of comparing the samples of a metric between two runs
+///
+/// Lower values are considered better for every metric.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Verdict {
+    Improved,
+    Regressed,
+    NoChange,
+    Inconclusive,
+}
+
+impl std::fmt::Display for Verdict {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Verdict::Improved => write!(f, "Improved"),
+            Verdict::Regressed => write!(f, "Regressed"),
+            Verdict::NoChange => write!(f, "No Change"),
+            Verdict::Inconclusive => write!(f, "Inconclusive"),
+        }
+    }
+}
+
+/// [`Significance`] struct that contains the statistical comparison of the samples of a metric
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Significance {
+    /// The two-sided p-value of Welch's t-test
+    pub p_value: f64,
+    /// The 95% bootstrap confidence interval of the difference of the means (current - previous)
+    pub confidence_interval: (f64, f64),
+    /// Hedges' g effect size, if the samples are not constant
+    pub effect_size: Option<f64>,
+    /// The verdict of the comparison
+    pub verdict: Verdict,
+}
+
+/// Compare the samples of a metric from two runs
+///
+/// # Arguments
+///
+/// * `previous` - The samples of the previous run
+///
+/// * `current` - The samples of the current run
+///
+/// # Returns
+///
+/// `None` if either run has fewer than two samples
+///
+pub fn test(previous: &[f64], current: &[f64]) -> Option<Significance> {
+    if previous.len() < 2 || current.len() < 2 {
+        return None;
+    }
+
+    let (previous_mean, previous_variance) = mean_variance(previous);
+    let (current_mean, current_variance) = mean_variance(current);
+    let difference = current_mean - previous_mean;
+
+    let p_value = welch_t_test(previous, current);
+    let confidence_interval = bootstrap_confidence_interval(previous, current);
+    let effect_size = hedges_g(previous, current);
+
+    // Constant samples leave nothing to test, the difference itself is the answer
+    #[allow(clippy::float_cmp)]
+    let verdict = if previous_variance == 0.0 && current_variance == 0.0 {
+        direction(difference)
+    } else {
+        let interval_excludes_zero = confidence_interval.0 > 0.0 || confidence_interval.1 < 0.0;
+        let negligible = effect_size.is_some_and(|g| g.abs() < NEGLIGIBLE_EFFECT_SIZE);
+
+        match (p_value < ALPHA, interval_excludes_zero) {
+            (true, true) if negligible => Verdict::NoChange,
+            (true, true) => direction(difference),
+            (false, false) => Verdict::NoChange,
+            _ => Verdict::Inconclusive,
+        }
+    };
+
+    Some(Significance {
+        p_value,
+        confidence_interval,
+        effect_size,
+        verdict,
+    })
+}
+
+/// Get the verdict of a difference of means, where lower values are better
+fn direction(difference: f64) -> Verdict {
+    if difference < 0.0 {
+        Verdict::Improved
+    } else if difference > 0.0 {
+        Verdict::Regressed
+    } else {
+        Verdict::NoChange
+    }
+}
+
+/// Calculate the mean and the sample variance of the values
+fn mean_variance(values: &[f64]) -> (f64, f64) {
+    let n = values.len() as f64;
+    let mean = values.iter().sum::<f64>() / n;
+
+    if values.len() < 2 {
+        return (mean, 0.0);
+    }
+
+    let variance = values.iter().map(|v| (v - mean).powi(2)).sum::<f64>() / (n - 1.0);
+    (mean, variance)
+}
+
+/// Calculate the two-sided p-value of Welch's t-test
+fn welch_t_test(previous: &[f64], current: &[f64]) -> f64 {
+    let (previous_mean, previous_variance) = mean_variance(previous);
+    let (current_mean, current_variance) = mean_variance(current);
+
+    let previous_error = previous_variance / previous.len() as f64;
+    let current_error = current_variance / current.len() as f64;
+    let standard_error = (previous_error + current_error).sqrt();
+
+    #[allow(clippy::float_cmp)]
+    if standard_error == 0.0 {
+        return if previous_mean == current_mean {
+            1.0
+        } else {
+            0.0
+        };
+    }
+
+    let t = (current_mean - previous_mean) / standard_error;
+
+    // Welch–Satterthwaite degrees of freedom
+    let degrees_of_freedom = (previous_error + current_error).powi(2)
+        / (previous_error.powi(2) / (previous.len() - 1) as f64
+            + current_error.powi(2) / (current.len() - 1) as f64);
+
+    // The two-sided tail of Student's t distribution
+    regularized_incomplete_beta(
+        degrees_of_freedom / (degrees_of_freedom + t * t),
+        degrees_of_freedom / 2.0,
+        0.5,
+    )
+    .clamp(0.0, 1.0)
+}
+
+/// Calculate Hedges' g, the bias-corrected standardized difference of the means
+fn hedges_g(previous: &[f64], current: &[f64]) -> Option<f64> {
+    let (previous_mean, previous_variance) = mean_variance(previous);
+    let (current_mean, current_variance) = mean_variance(current);
+
+    let n1 = previous.len() as f64;
+    let n2 = current.len() as f64;
+
+    let pooled_deviation =
+        (((n1 - 1.0) * previous_variance + (n2 - 1.0) * current_variance) / (n1 + n2 - 2.0)).sqrt();
+
+    if pooled_deviation == 0.0 {
+        return None;
+    }
+
+    let correction = 1.0 - 3.0 / (4.0 * (n1 + n2) - 9.0);
+    Some((current_mean - previous_mean) / pooled_deviation * correction)
+}
+
+/// Estimate the 95% confidence interval of the difference of the means with the percentile bootstrap
+fn bootstrap_confidence_interval(previous: &[f64], current: &[f64]) -> (f64, f64) {
+    let mut rng = SplitMix64(BOOTSTRAP_SEED);
+
+    let mut resample_mean = |values: &[f64]| {
+        (0..values.len())
+            .map(|_| values[rng.next_index(values.len())])
+            .sum::<f64>()
+            / values.len() as f64
+    };
+
+    let mut differences: Vec<f64> = (0..BOOTSTRAP_RESAMPLES)
+        .map(|_| resample_mean(current) - resample_mean(previous))
+        .collect();
+
+    differences.sort_by(f64::total_cmp);
+
+    let percentile = |per_mille: usize| differences[(differences.len() - 1) * per_mille / 1000];
+
+    (percentile(25), percentile(975))
+}
+
+/// A small deterministic pseudo random number generator used for bootstrap resampling
+struct SplitMix64(u64);
+
+impl SplitMix64 {
+    fn next_u64(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^ (z >> 31)
+    }
+
+    #[allow(clippy::cast_possible_truncation)]
+    fn next_index(&mut self, len: usize) -> usize {
+        (self.next_u64() % len as u64) as usize
+    }
+}
+
+/// Calculate the natural logarithm of the gamma function with the Lanczos approximation
+fn ln_gamma(x: f64) -> f64 {
+    const COEFFICIENTS: [f64; 6] = [
+        76.180_091_729_471_46,
+        -86.505_320_329_416_77,
+        24.014_098_240_830_91,
+        -1.231_739_572_450_155,
+        0.001_208_650_973_866_179,
+        -0.000_005_395_239_384_953,
+    ];
+
+    let mut y = x;
+    let tmp = x + 5.5;
+    let tmp = tmp - (x + 0.5) * tmp.ln();
+
+    let mut series = 1.000_000_000_190_015;
+    for coefficient in COEFFICIENTS {
+        y += 1.0;
+        series += coefficient / y;
+    }
+
+    -tmp + (2.506_628_274_631_000_5 * series / x).ln()
+}
+
+/// Calculate the regularized incomplete beta function `I_x(a, b)`
+#[allow(clippy::many_single_char_names)]
+fn regularized_incomplete_beta(x: f64, a: f64, b: f64) -> f64 {
+    if x <= 0.0 {
+        return 0.0;
+    }
+    if x >= 1.0 {
+        return 1.0;
+    }
+
+    let front =
+        (ln_gamma(a + b) - ln_gamma(a) - ln_gamma(b) + a * x.ln() + b * (1.0 - x).ln()).exp();
+
+    // The continued fraction converges quickly on this side of the symmetry point
+    if x < (a + 1.0) / (a + b + 2.0) {
+        front * beta_continued_fraction(x, a, b) / a
+    } else {
+        1.0 - front * beta_continued_fraction(1.0 - x, b, a) / b
+    }
+}
+
+/// Evaluate the continued fraction of the incomplete beta function with the modified Lentz method
+#[allow(clippy::many_single_char_names)]
+fn beta_continued_fraction(x: f64, a: f64, b: f64) -> f64 {
+    const MAX_ITERATIONS: usize = 300;
+    const EPSILON: f64 = 3.0e-14;
+    const TINY: f64 = 1.0e-300;
+
+    let mut c = 1.0;
+    let mut d = 1.0 - (a + b) * x / (a + 1.0);
+    if d.abs() < TINY {
+        d = TINY;
+    }
+    d = 1.0 / d;
+    let mut result = d;
+
+    for m in 1..=MAX_ITERATIONS {
+        let m = m as f64;
+        let m2 = 2.0 * m;
+
+        // The even step of the recurrence
+        let numerator = m * (b - m) * x / ((a + m2 - 1.0) * (a + m2));
+        d = 1.0 + numerator * d;
+        if d.abs() < TINY {
+            d = TINY;
+        }
+        c = 1.0 + numerator / c;
+        if c.abs() < TINY {
+            c = TINY;
+        }
+        d = 1.0 / d;
+        result *= d * c;
+
+        // The odd step of the recurrence
+        let numerator = -(a + m) * (a + b + m) * x / ((a + m2) * (a + m2 + 1.0));
+        d = 1.0 + numerator * d;
+        if d.abs() < TINY {
+            d = TINY;
+        }
+        c = 1.0 + numerator / c;
+        if c.abs() < TINY {
+            c = TINY;
+        }
+        d = 1.0 / d;
+        let delta = d * c;
+        result *= delta;
+
+        if (delta - 1.0).abs() < EPSILON {
+            break;
+        }
+    }
+
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_welch_t_test() {
+        // t = 2.074 with 10.21 degrees of freedom
+        let previous = [19.8, 20.4, 19.6, 17.8, 18.5, 18.9, 18.3, 18.9, 19.5, 22.0];
+        let current = [28.2, 26.6, 20.1, 23.3, 25.2, 22.1, 17.7, 27.6, 20.6, 13.7];
+        let p_value = welch_t_test(&previous, &current);
+        assert!((p_value - 0.064_28).abs() < 1e-4, "p = {p_value}");
+    }
+
+    #[test]
+    fn test_verdicts() {
+        let previous = [100.0, 101.0, 99.0, 100.5, 99.5];
+
+        let regressed = test(&previous, &[120.0, 121.0, 119.0, 120.5, 119.5]).unwrap();
+        assert_eq!(regressed.verdict, Verdict::Regressed);
+        assert!(regressed.confidence_interval.0 > 0.0);
+
+        let improved = test(&previous, &[80.0, 81.0, 79.0, 80.5, 79.5]).unwrap();
+        assert_eq!(improved.verdict, Verdict::Improved);
+
+        let unchanged = test(&previous, &[100.2, 99.8, 100.4, 99.6, 100.0]).unwrap();
+        assert_eq!(unchanged.verdict, Verdict::NoChange);
+
+        let constant = test(&[10.0, 10.0], &[12.0, 12.0]).unwrap();
+        assert_eq!(constant.verdict, Verdict::Regressed);
+        assert_eq!(constant.effect_size, None);
+
+        assert!(test(&[1.0], &[2.0, 3.0]).is_none());
+    }
+}
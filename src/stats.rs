@@ -1,28 +1,661 @@
 use serde::{Deserialize, Serialize};
 
-use crate::types::{Benchmark, BenchmarkFrame};
+use crate::asm::{diff_sizes, AsmInformation, SizeChange};
+use crate::significance::{Significance, Verdict};
+use crate::types::{
+    Benchmark, BenchmarkAggregate, BenchmarkFrame, BenchmarkPhase, Benchmarks, BuildMode,
+    HardwareCounters, Summary,
+};
 
 use crate::wrap;
 
-use std::sync::MutexGuard;
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
 
+/// [`Collection`] struct that contains the comparison of two benchmark runs
+///
+/// Lists and metrics missing from a stored stats file, because it was written before they were
+/// compared, are read as empty.
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Collection {
+    /// The stats of the benchmarks that are present in both runs
+    pub matched: Vec<(String, Stats)>,
+    /// The benchmarks that are only present in the current run
+    pub added: Vec<String>,
+    /// The benchmarks that are only present in the previous run
+    pub removed: Vec<String>,
+    /// The benchmarks that were moved to a different path between the runs
+    pub renamed: Vec<Renamed>,
+    /// The benchmarks left out of the comparison because a build of either run failed
+    pub failed: Vec<Failed>,
+    /// The benchmarks left out of the comparison because the runs built them in different modes
+    pub mismatched: Vec<Mismatched>,
+}
+
+impl Collection {
+    /// Read a stored stats file
+    ///
+    /// # Errors
+    ///
+    /// If the file is not valid JSON, or was written by a version of `dyno` whose stats only
+    /// held a list of benchmarks and their metric tuples.
+    ///
+    pub fn from_json(json: &str) -> Result<Self, String> {
+        let value: serde_json::Value = serde_json::from_str(json).map_err(|e| e.to_string())?;
+
+        if value.is_array() {
+            return Err("The stats file was written by an older version of dyno, whose stats cannot be read anymore. Compare its run files again with `dyno compare` to render them".to_string());
+        }
+
+        serde_json::from_value(value).map_err(|e| e.to_string())
+    }
+}
+
+/// [`Renamed`] struct that contains the stats of a benchmark that was moved between runs
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct Renamed {
+    pub previous: String,
+    pub current: String,
+    pub stats: Stats,
+}
+
+/// [`Failed`] struct that describes a benchmark whose build failed in either run
 #[derive(Debug, Default, Serialize, Deserialize)]
-pub struct Collection(pub Vec<(String, Stats)>);
+pub struct Failed {
+    pub previous: String,
+    pub current: String,
+    /// How the previous build exited, if it failed
+    pub previous_failure: Option<String>,
+    /// How the current build exited, if it failed
+    pub current_failure: Option<String>,
+}
+
+/// [`Mismatched`] struct that describes a benchmark built in a different mode in each run
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Mismatched {
+    pub previous: String,
+    pub current: String,
+    /// The build mode of the previous run, or `None` if it was recorded before build modes
+    pub previous_mode: Option<BuildMode>,
+    /// The build mode of the current run, or `None` if it was recorded before build modes
+    pub current_mode: Option<BuildMode>,
+}
+
+/// The identity of a benchmark, used to match benchmarks between two runs
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub struct BenchmarkId {
+    /// The name of the benchmark
+    pub name: String,
+    /// The path of the benchmark relative to the target root folder
+    pub relative_path: PathBuf,
+    /// The name of the workload of the benchmark
+    pub workload: String,
+}
+
+impl BenchmarkId {
+    /// Create the identity of `benchmark` relative to the `root` target folder
+    pub fn new(benchmark: &Benchmark, root: &Path) -> Self {
+        let relative_path = benchmark
+            .path
+            .strip_prefix(root)
+            .unwrap_or(&benchmark.path)
+            .to_path_buf();
+
+        Self {
+            name: benchmark.name.clone(),
+            relative_path,
+            workload: benchmark.workload.name.clone(),
+        }
+    }
+}
+
+impl std::fmt::Display for BenchmarkId {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if self.relative_path.as_os_str().is_empty() {
+            write!(f, "{}", self.name)?;
+        } else {
+            write!(f, "{}", self.relative_path.display())?;
+        }
+
+        // The default workload is left out, so that the benchmarks keep their identity
+        if self.workload != crate::workload::DEFAULT_WORKLOAD {
+            write!(f, " [{}]", self.workload)?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Split the displayed identity of a benchmark into its project and its workload.
+///
+/// # Returns
+///
+/// The project, and the workload if it is not the default one.
+///
+#[must_use]
+pub fn split_workload(id: &str) -> (&str, Option<&str>) {
+    match id.strip_suffix(']').and_then(|id| id.rsplit_once(" [")) {
+        Some((project, workload)) => (project, Some(workload)),
+        None => (id, None),
+    }
+}
+
+/// A pair of benchmarks from two runs that refer to the same project
+pub struct Pair<'a> {
+    pub previous_id: BenchmarkId,
+    pub current_id: BenchmarkId,
+    pub previous: &'a Benchmark,
+    pub current: &'a Benchmark,
+}
+
+impl Pair<'_> {
+    /// Whether the benchmark was moved to a different path between the runs
+    pub fn is_renamed(&self) -> bool {
+        self.previous_id != self.current_id
+    }
+}
+
+/// The result of matching the benchmarks of two runs by identity
+#[derive(Default)]
+pub struct Matches<'a> {
+    /// The benchmarks present in both runs, in the order of the current run
+    pub pairs: Vec<Pair<'a>>,
+    /// The benchmarks that are only present in the current run
+    pub added: Vec<BenchmarkId>,
+    /// The benchmarks that are only present in the previous run
+    pub removed: Vec<BenchmarkId>,
+}
+
+/// Match the benchmarks of two runs by their name, path relative to the target root and workload
+///
+/// Benchmarks that cannot be matched by identity are paired as renamed when exactly one
+/// unmatched benchmark with the same name and workload exists in each run.
+///
+/// # Arguments
+///
+/// * `previous` - The previous benchmarks
+///
+/// * `current` - The current benchmarks
+///
+pub fn match_benchmarks<'a>(previous: &'a Benchmarks, current: &'a Benchmarks) -> Matches<'a> {
+    // Runs recorded before the target root was stored fall back to the current target root
+    let previous_root = if previous.target.as_os_str().is_empty() {
+        &current.target
+    } else {
+        &previous.target
+    };
+
+    let mut unmatched_previous: BTreeMap<BenchmarkId, &Benchmark> = previous
+        .benchmarks
+        .iter()
+        .map(|b| (BenchmarkId::new(b, previous_root), b))
+        .collect();
+
+    let mut matches = Matches::default();
+    let mut unmatched_current = Vec::new();
+
+    for benchmark in &current.benchmarks {
+        let id = BenchmarkId::new(benchmark, &current.target);
+
+        match unmatched_previous.remove(&id) {
+            Some(previous) => matches.pairs.push(Pair {
+                previous_id: id.clone(),
+                current_id: id,
+                previous,
+                current: benchmark,
+            }),
+            None => unmatched_current.push((id, benchmark)),
+        }
+    }
+
+    for (id, benchmark) in unmatched_current {
+        let previous_ids: Vec<BenchmarkId> = unmatched_previous
+            .keys()
+            .filter(|previous_id| {
+                previous_id.name == id.name && previous_id.workload == id.workload
+            })
+            .cloned()
+            .collect();
+
+        let current_count = current
+            .benchmarks
+            .iter()
+            .filter(|b| b.name == id.name && b.workload.name == id.workload)
+            .count();
+
+        // Only pair a moved benchmark when its name is unambiguous in both runs
+        match previous_ids.as_slice() {
+            [previous_id] if current_count == 1 => {
+                let previous = unmatched_previous
+                    .remove(previous_id)
+                    .expect("Failed to remove the previous benchmark");
+
+                matches.pairs.push(Pair {
+                    previous_id: previous_id.clone(),
+                    current_id: id,
+                    previous,
+                    current: benchmark,
+                });
+            }
+            _ => matches.added.push(id),
+        }
+    }
+
+    matches.removed = unmatched_previous.into_keys().collect();
+
+    matches
+}
+
+/// Check that two runs can be compared, which they can only be when both ran serially or
+/// with the same number of parallel jobs
+///
+/// Concurrent builds share caches, memory bandwidth and thermal headroom, so their timings are
+/// not comparable with builds that had the machine to themselves.
+///
+/// # Arguments
+///
+/// * `previous` - The number of jobs of the previous run, or `None` if it was serial
+///
+/// * `current` - The number of jobs of the current run, or `None` if it was serial
+///
+/// # Errors
+///
+/// If the runs were not run the same way
+///
+pub fn check_jobs(previous: Option<u32>, current: Option<u32>) -> Result<(), String> {
+    let describe = |jobs: Option<u32>| match jobs {
+        Some(jobs) => format!("a run of {jobs} parallel jobs"),
+        None => "a serial run".to_string(),
+    };
+
+    if previous != current {
+        return Err(format!(
+            "Refusing to compare {} against {}",
+            describe(current),
+            describe(previous)
+        ));
+    }
+
+    Ok(())
+}
+
+/// Compare two benchmark runs, matching the benchmarks by identity
+///
+/// # Arguments
+///
+/// * `previous` - The previous benchmarks
+///
+/// * `current` - The current benchmarks
+///
+/// # Errors
+///
+/// If one run was serial and the other parallel, or they ran a different number of jobs
+///
+/// If the calculation of the stats of a matched benchmark fails
+///
+pub fn compare(previous: &Benchmarks, current: &Benchmarks) -> crate::error::Result<Collection> {
+    check_jobs(previous.jobs, current.jobs).map_err(|e| wrap!(e.into()))?;
+
+    let matches = match_benchmarks(previous, current);
+
+    let mut collection = Collection {
+        added: matches.added.iter().map(ToString::to_string).collect(),
+        removed: matches.removed.iter().map(ToString::to_string).collect(),
+        ..Default::default()
+    };
+
+    for pair in &matches.pairs {
+        // Failed builds are left out of the regression math, since their metrics are meaningless
+        if pair.previous.failed() || pair.current.failed() {
+            let failure = |benchmark: &Benchmark| {
+                benchmark
+                    .status
+                    .as_ref()
+                    .filter(|status| !status.success)
+                    .map(crate::types::BuildStatus::describe)
+            };
+
+            collection.failed.push(Failed {
+                previous: pair.previous_id.to_string(),
+                current: pair.current_id.to_string(),
+                previous_failure: failure(pair.previous),
+                current_failure: failure(pair.current),
+            });
+            continue;
+        }
+
+        // Cold, warm and incremental builds measure different work, and benchmarks recorded
+        // before build modes were built without priming, so they only match each other
+        if pair.previous.mode != pair.current.mode {
+            collection.mismatched.push(Mismatched {
+                previous: pair.previous_id.to_string(),
+                current: pair.current_id.to_string(),
+                previous_mode: pair.previous.mode,
+                current_mode: pair.current.mode,
+            });
+            continue;
+        }
+
+        let stats = calculate(pair.previous, pair.current).map_err(|e| wrap!(e))?;
+
+        for warning in &stats.warnings {
+            eprintln!("Warning in benchmark \"{}\": {warning}", pair.current_id);
+        }
+
+        if pair.is_renamed() {
+            collection.renamed.push(Renamed {
+                previous: pair.previous_id.to_string(),
+                current: pair.current_id.to_string(),
+                stats,
+            });
+        } else {
+            collection
+                .matched
+                .push((pair.current_id.to_string(), stats));
+        }
+    }
+
+    Ok(collection)
+}
 
 /// [`Stats`] struct that contains the regression information for each metric
-/// The tuple contains the change and the percentage change for each metric
+///
+/// Frame metrics are reduced per iteration, see [`FrameMetrics`].
 #[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(default)]
 pub struct Stats {
-    pub cpu_usage: (f64, f64),
-    pub memory_usage: (f64, f64),
-    pub virtual_memory_usage: (f64, f64),
-    pub disk_total_written_bytes: (f64, f64),
-    pub disk_written_bytes: (f64, f64),
-    pub disk_total_read_bytes: (f64, f64),
-    pub disk_read_bytes: (f64, f64),
-    pub bytecode_size: (f64, f64),
-    pub data_section_size: (f64, f64),
-    pub time: (f64, f64),
+    pub cpu_usage_peak: Change,
+    pub cpu_usage_mean: Change,
+    pub cpu_usage_p95: Change,
+    pub cpu_usage_integral: Change,
+    pub memory_usage_peak: Change,
+    pub memory_usage_mean: Change,
+    pub memory_usage_p95: Change,
+    pub memory_usage_integral: Change,
+    pub virtual_memory_usage_peak: Change,
+    pub disk_total_written_bytes: Change,
+    pub disk_written_bytes: Change,
+    pub disk_total_read_bytes: Change,
+    pub disk_read_bytes: Change,
+    pub bytecode_size: Change,
+    pub data_section_size: Change,
+    pub data_section_used: Change,
+    pub data_section_values: Change,
+    pub entries: Change,
+    pub time: Change,
+    /// The regression information of the performance counters, when both benchmarks collected them
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub counters: Option<CounterStats>,
+    /// The regression information of each phase present in both benchmarks
+    pub phases: Vec<(String, PhaseStats)>,
+    /// The entries, functions and data section values whose size changed, largest growth first
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub sizes: Vec<SizeChange>,
+    /// The metrics that could not be compared, such as the bytecode of a benchmark whose
+    /// bytecode information is missing from one of the runs
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub warnings: Vec<String>,
+}
+
+impl Stats {
+    /// The names of the metrics of the stats
+    pub const METRICS: [&'static str; 25] = [
+        "cpu_usage_peak",
+        "cpu_usage_mean",
+        "cpu_usage_p95",
+        "cpu_usage_integral",
+        "memory_usage_peak",
+        "memory_usage_mean",
+        "memory_usage_p95",
+        "memory_usage_integral",
+        "virtual_memory_usage_peak",
+        "disk_total_written_bytes",
+        "disk_written_bytes",
+        "disk_total_read_bytes",
+        "disk_read_bytes",
+        "bytecode_size",
+        "data_section_size",
+        "data_section_used",
+        "data_section_values",
+        "entries",
+        "time",
+        "instructions",
+        "cycles",
+        "cache_misses",
+        "branch_misses",
+        "context_switches",
+        "page_faults",
+    ];
+
+    /// Get the change of each metric along with the name of the metric
+    ///
+    /// The counters that were not collected by both benchmarks are left out.
+    pub fn metrics(&self) -> Vec<(&'static str, &Change)> {
+        let mut metrics = vec![
+            ("cpu_usage_peak", &self.cpu_usage_peak),
+            ("cpu_usage_mean", &self.cpu_usage_mean),
+            ("cpu_usage_p95", &self.cpu_usage_p95),
+            ("cpu_usage_integral", &self.cpu_usage_integral),
+            ("memory_usage_peak", &self.memory_usage_peak),
+            ("memory_usage_mean", &self.memory_usage_mean),
+            ("memory_usage_p95", &self.memory_usage_p95),
+            ("memory_usage_integral", &self.memory_usage_integral),
+            ("virtual_memory_usage_peak", &self.virtual_memory_usage_peak),
+            ("disk_total_written_bytes", &self.disk_total_written_bytes),
+            ("disk_written_bytes", &self.disk_written_bytes),
+            ("disk_total_read_bytes", &self.disk_total_read_bytes),
+            ("disk_read_bytes", &self.disk_read_bytes),
+            ("bytecode_size", &self.bytecode_size),
+            ("data_section_size", &self.data_section_size),
+            ("data_section_used", &self.data_section_used),
+            ("data_section_values", &self.data_section_values),
+            ("entries", &self.entries),
+            ("time", &self.time),
+        ];
+
+        if let Some(counters) = &self.counters {
+            metrics.extend(counters.metrics());
+        }
+
+        metrics
+    }
+}
+
+/// [`CounterStats`] struct that contains the regression information for each performance counter
+///
+/// A counter is only compared when both benchmarks collected it.
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct CounterStats {
+    pub instructions: Option<Change>,
+    pub cycles: Option<Change>,
+    pub cache_misses: Option<Change>,
+    pub branch_misses: Option<Change>,
+    pub context_switches: Option<Change>,
+    pub page_faults: Option<Change>,
+}
+
+impl CounterStats {
+    /// Get the change of each compared counter along with the name of the counter
+    pub fn metrics(&self) -> Vec<(&'static str, &Change)> {
+        [
+            ("instructions", &self.instructions),
+            ("cycles", &self.cycles),
+            ("cache_misses", &self.cache_misses),
+            ("branch_misses", &self.branch_misses),
+            ("context_switches", &self.context_switches),
+            ("page_faults", &self.page_faults),
+        ]
+        .into_iter()
+        .filter_map(|(name, change)| Some((name, change.as_ref()?)))
+        .collect()
+    }
+}
+
+/// Compare the performance counters of the samples of the previous and current runs
+///
+/// Returns `None` if either run has no counters.
+fn compare_counters(
+    previous: &[HardwareCounters],
+    current: &[HardwareCounters],
+) -> Option<CounterStats> {
+    if previous.is_empty() || current.is_empty() {
+        return None;
+    }
+
+    let check_counter = |counter_fn: fn(&HardwareCounters) -> Option<u64>| {
+        let samples = |counters: &[HardwareCounters]| {
+            counters
+                .iter()
+                .filter_map(|c| counter_fn(c).map(|value| value as f64))
+                .collect::<Vec<_>>()
+        };
+
+        let (previous, current) = (samples(previous), samples(current));
+        if previous.is_empty() || current.is_empty() {
+            return None;
+        }
+
+        Some(check(&previous, &current))
+    };
+
+    Some(CounterStats {
+        instructions: check_counter(|c| c.instructions),
+        cycles: check_counter(|c| c.cycles),
+        cache_misses: check_counter(|c| c.cache_misses),
+        branch_misses: check_counter(|c| c.branch_misses),
+        context_switches: check_counter(|c| c.context_switches),
+        page_faults: check_counter(|c| c.page_faults),
+    })
+}
+
+/// [`FrameMetrics`] struct that contains the reduction of the frames of a single iteration
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct FrameMetrics {
+    /// The peak CPU usage
+    pub cpu_usage_peak: f64,
+    /// The time-weighted mean CPU usage
+    pub cpu_usage_mean: f64,
+    /// The 95th percentile of the CPU usage samples
+    pub cpu_usage_p95: f64,
+    /// The CPU usage integrated over time (in percent-seconds)
+    pub cpu_usage_integral: f64,
+    /// The peak memory usage (in bytes)
+    pub memory_usage_peak: f64,
+    /// The time-weighted mean memory usage (in bytes)
+    pub memory_usage_mean: f64,
+    /// The 95th percentile of the memory usage samples (in bytes)
+    pub memory_usage_p95: f64,
+    /// The memory usage integrated over time (in byte-seconds)
+    pub memory_usage_integral: f64,
+    /// The peak virtual memory usage (in bytes)
+    pub virtual_memory_usage_peak: f64,
+    /// The final value of the cumulative written bytes counter, i.e. the bytes written since the process started
+    pub disk_total_written_bytes: f64,
+    /// The sum of the bytes written between each refresh
+    pub disk_written_bytes: f64,
+    /// The final value of the cumulative read bytes counter, i.e. the bytes read since the process started
+    pub disk_total_read_bytes: f64,
+    /// The sum of the bytes read between each refresh
+    pub disk_read_bytes: f64,
+}
+
+/// Reduce the frames of a single iteration to physically meaningful metrics
+pub(crate) fn reduce_frames(frames: &[BenchmarkFrame]) -> FrameMetrics {
+    let cpu_usage: Vec<f64> = frames.iter().map(|f| f64::from(f.cpu_usage)).collect();
+    let memory_usage: Vec<f64> = frames.iter().map(|f| f.memory_usage as f64).collect();
+
+    let cpu_usage_integral = time_integral(frames, &cpu_usage);
+    let memory_usage_integral = time_integral(frames, &memory_usage);
+
+    FrameMetrics {
+        cpu_usage_peak: peak(&cpu_usage),
+        cpu_usage_mean: time_weighted_mean(frames, &cpu_usage, cpu_usage_integral),
+        cpu_usage_p95: percentile(&cpu_usage, 95),
+        cpu_usage_integral,
+        memory_usage_peak: peak(&memory_usage),
+        memory_usage_mean: time_weighted_mean(frames, &memory_usage, memory_usage_integral),
+        memory_usage_p95: percentile(&memory_usage, 95),
+        memory_usage_integral,
+        virtual_memory_usage_peak: frames
+            .iter()
+            .map(|f| f.virtual_memory_usage as f64)
+            .fold(0.0, f64::max),
+        disk_total_written_bytes: frames
+            .last()
+            .map_or(0.0, |f| f.disk_total_written_bytes as f64),
+        disk_written_bytes: aggregate_values(frames, |f| f.disk_written_bytes as f64),
+        disk_total_read_bytes: frames
+            .last()
+            .map_or(0.0, |f| f.disk_total_read_bytes as f64),
+        disk_read_bytes: aggregate_values(frames, |f| f.disk_read_bytes as f64),
+    }
+}
+
+/// Get the peak of the values
+fn peak(values: &[f64]) -> f64 {
+    values.iter().copied().fold(0.0, f64::max)
+}
+
+/// Get the nearest-rank percentile of the values
+fn percentile(values: &[f64], percent: usize) -> f64 {
+    if values.is_empty() {
+        return 0.0;
+    }
+
+    let mut sorted = values.to_vec();
+    sorted.sort_by(f64::total_cmp);
+
+    let rank = (percent * sorted.len()).div_ceil(100).max(1);
+    sorted[rank - 1]
+}
+
+/// Integrate the values of the frames over time with the trapezoidal rule
+fn time_integral(frames: &[BenchmarkFrame], values: &[f64]) -> f64 {
+    frames
+        .windows(2)
+        .zip(values.windows(2))
+        .map(|(frames, values)| {
+            let elapsed = frames[1]
+                .timestamp
+                .saturating_sub(frames[0].timestamp)
+                .as_secs_f64();
+            f64::midpoint(values[0], values[1]) * elapsed
+        })
+        .sum()
+}
+
+/// Get the mean of the values weighted by the time between the frames
+fn time_weighted_mean(frames: &[BenchmarkFrame], values: &[f64], integral: f64) -> f64 {
+    let elapsed = match (frames.first(), frames.last()) {
+        (Some(first), Some(last)) => last.timestamp.saturating_sub(first.timestamp).as_secs_f64(),
+        _ => 0.0,
+    };
+
+    // A single frame has no duration to weight by
+    if elapsed > 0.0 {
+        integral / elapsed
+    } else {
+        mean(values)
+    }
+}
+
+/// [`Change`] struct that contains the regression information for a single metric
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+pub struct Change {
+    /// The change between the previous and the current mean value
+    pub change: f64,
+    /// The percentage change between the previous and the current mean value
+    pub percentage_change: f64,
+    /// The statistical significance of the change, when both runs have repeated samples
+    pub significance: Option<Significance>,
+}
+
+impl Change {
+    /// The verdict of the change, if its significance could be tested
+    pub fn verdict(&self) -> Option<Verdict> {
+        self.significance.as_ref().map(|s| s.verdict)
+    }
 }
 
 /// Aggregate the values of a metric from all the frames
@@ -30,6 +663,78 @@ fn aggregate_values(frames: &[BenchmarkFrame], metric_fn: fn(&BenchmarkFrame) ->
     frames.iter().map(metric_fn).sum()
 }
 
+/// Calculate the mean of the values
+fn mean(values: &[f64]) -> f64 {
+    if values.is_empty() {
+        return 0.0;
+    }
+    values.iter().sum::<f64>() / values.len() as f64
+}
+
+/// Summarise the values of a metric with their min, max, mean, median and sample standard deviation
+pub(crate) fn summarize(values: &[f64]) -> Summary {
+    if values.is_empty() {
+        return Summary::default();
+    }
+
+    let mut sorted = values.to_vec();
+    sorted.sort_by(f64::total_cmp);
+
+    let middle = sorted.len() / 2;
+    let median = if sorted.len().is_multiple_of(2) {
+        f64::midpoint(sorted[middle - 1], sorted[middle])
+    } else {
+        sorted[middle]
+    };
+
+    let mean = mean(values);
+
+    let stddev = if values.len() > 1 {
+        (values.iter().map(|v| (v - mean).powi(2)).sum::<f64>() / (values.len() - 1) as f64).sqrt()
+    } else {
+        0.0
+    };
+
+    Summary {
+        min: sorted[0],
+        max: sorted[sorted.len() - 1],
+        mean,
+        median,
+        stddev,
+    }
+}
+
+/// Aggregate the measured iterations of a benchmark
+pub(crate) fn aggregate(benchmark: &Benchmark) -> BenchmarkAggregate {
+    let iteration_metrics: Vec<FrameMetrics> = benchmark
+        .iteration_frames()
+        .iter()
+        .map(|frames| reduce_frames(frames))
+        .collect();
+
+    let summarize_metrics = |metric_fn: fn(&FrameMetrics) -> f64| {
+        summarize(&iteration_metrics.iter().map(metric_fn).collect::<Vec<_>>())
+    };
+
+    let wall_times: Vec<f64> = benchmark
+        .iteration_windows()
+        .iter()
+        .map(|iteration| iteration.duration().as_secs_f64() * 1000.0)
+        .collect();
+
+    BenchmarkAggregate {
+        iterations: wall_times.len(),
+        wall_time: summarize(&wall_times),
+        cpu_usage: summarize_metrics(|m| m.cpu_usage_mean),
+        memory_usage: summarize_metrics(|m| m.memory_usage_peak),
+        virtual_memory_usage: summarize_metrics(|m| m.virtual_memory_usage_peak),
+        disk_total_written_bytes: summarize_metrics(|m| m.disk_total_written_bytes),
+        disk_written_bytes: summarize_metrics(|m| m.disk_written_bytes),
+        disk_total_read_bytes: summarize_metrics(|m| m.disk_total_read_bytes),
+        disk_read_bytes: summarize_metrics(|m| m.disk_read_bytes),
+    }
+}
+
 /// Calculate the change and the percentage change between two values
 fn calculate_change(previous: f64, current: f64) -> (f64, f64) {
     #[allow(clippy::float_cmp)]
@@ -55,10 +760,14 @@ fn calculate_change(previous: f64, current: f64) -> (f64, f64) {
     }
 }
 
-/// Check if the change in a metric is greater than the threshold
-fn check(previous: f64, current: f64) -> (f64, f64) {
-    let (change, percentage_change) = calculate_change(previous, current);
-    (change, percentage_change)
+/// Check the change in a metric between the samples of the previous and current runs
+fn check(previous: &[f64], current: &[f64]) -> Change {
+    let (change, percentage_change) = calculate_change(mean(previous), mean(current));
+    Change {
+        change,
+        percentage_change,
+        significance: crate::significance::test(previous, current),
+    }
 }
 
 /// Calculate the regression between two benchmarks
@@ -87,145 +796,286 @@ fn check(previous: f64, current: f64) -> (f64, f64) {
 ///
 /// If the time is missing
 ///
-/// # Panics
-///
-/// If the metric name is unknown
 #[allow(clippy::too_many_lines)]
 pub(crate) fn calculate(
     previous_benchmark: &Benchmark,
     current_benchmark: &Benchmark,
 ) -> crate::error::Result<Stats> {
-    let previous_frames: MutexGuard<_> = previous_benchmark
-        .frames
-        .lock()
-        .expect("Failed to get the previous benchmark frames lock");
-
-    let current_frames: MutexGuard<_> = current_benchmark
-        .frames
-        .lock()
-        .expect("Failed to get the current benchmark frames lock");
-
-    #[allow(clippy::type_complexity)]
-    let metrics: Vec<(&str, fn(&BenchmarkFrame) -> f64)> = vec![
-        ("cpu_usage", |f| f64::from(f.cpu_usage)),
-        ("memory_usage", |f| f.memory_usage as f64),
-        ("virtual_memory_usage", |f| f.virtual_memory_usage as f64),
-        ("disk_total_written_bytes", |f| {
-            f.disk_total_written_bytes as f64
-        }),
-        ("disk_written_bytes", |f| f.disk_written_bytes as f64),
-        ("disk_total_read_bytes", |f| f.disk_total_read_bytes as f64),
-        ("disk_read_bytes", |f| f.disk_read_bytes as f64),
-    ];
+    // Each iteration's reduced frames are one sample, so runs with a different iteration count compare
+    let iteration_metrics = |benchmark: &Benchmark| {
+        benchmark
+            .iteration_frames()
+            .iter()
+            .map(|frames| reduce_frames(frames))
+            .collect::<Vec<_>>()
+    };
 
-    let mut regression = Stats::default();
-
-    for (metric_name, metric_fn) in metrics {
-        let previous_aggregated_value = aggregate_values(&previous_frames, metric_fn);
-        let current_aggregated_value = aggregate_values(&current_frames, metric_fn);
-
-        let metric = match metric_name {
-            "cpu_usage" => &mut regression.cpu_usage,
-            "memory_usage" => &mut regression.memory_usage,
-            "virtual_memory_usage" => &mut regression.virtual_memory_usage,
-            "disk_total_written_bytes" => &mut regression.disk_total_written_bytes,
-            "disk_written_bytes" => &mut regression.disk_written_bytes,
-            "disk_total_read_bytes" => &mut regression.disk_total_read_bytes,
-            "disk_read_bytes" => &mut regression.disk_read_bytes,
-            _ => panic!("Unknown metric"),
-        };
+    let previous_metrics = iteration_metrics(previous_benchmark);
+    let current_metrics = iteration_metrics(current_benchmark);
+
+    let check_metric = |metric_fn: fn(&FrameMetrics) -> f64| {
+        check(
+            &previous_metrics.iter().map(metric_fn).collect::<Vec<_>>(),
+            &current_metrics.iter().map(metric_fn).collect::<Vec<_>>(),
+        )
+    };
+
+    let mut regression = Stats {
+        cpu_usage_peak: check_metric(|m| m.cpu_usage_peak),
+        cpu_usage_mean: check_metric(|m| m.cpu_usage_mean),
+        cpu_usage_p95: check_metric(|m| m.cpu_usage_p95),
+        cpu_usage_integral: check_metric(|m| m.cpu_usage_integral),
+        memory_usage_peak: check_metric(|m| m.memory_usage_peak),
+        memory_usage_mean: check_metric(|m| m.memory_usage_mean),
+        memory_usage_p95: check_metric(|m| m.memory_usage_p95),
+        memory_usage_integral: check_metric(|m| m.memory_usage_integral),
+        virtual_memory_usage_peak: check_metric(|m| m.virtual_memory_usage_peak),
+        disk_total_written_bytes: check_metric(|m| m.disk_total_written_bytes),
+        disk_written_bytes: check_metric(|m| m.disk_written_bytes),
+        disk_total_read_bytes: check_metric(|m| m.disk_total_read_bytes),
+        disk_read_bytes: check_metric(|m| m.disk_read_bytes),
+        ..Default::default()
+    };
+
+    // Workloads other than builds, such as `forc fmt`, report no bytecode to compare
+    match (
+        previous_benchmark.asm_information.as_ref(),
+        current_benchmark.asm_information.as_ref(),
+    ) {
+        (Some(previous_asm), Some(current_asm)) => {
+            let check_asm = |metric_fn: fn(&AsmInformation) -> f64| {
+                check(&[metric_fn(previous_asm)], &[metric_fn(current_asm)])
+            };
+
+            regression.bytecode_size = check_asm(|asm| asm.bytecode_size as f64);
+            regression.data_section_size = check_asm(|asm| asm.data_section.size as f64);
+            regression.data_section_used = check_asm(|asm| asm.data_section.used as f64);
+            regression.data_section_values = check_asm(|asm| asm.data_section_values() as f64);
+            regression.entries = check_asm(|asm| asm.entries.len() as f64);
+            regression.sizes = diff_sizes(previous_asm, current_asm);
+        }
+
+        (None, None) => {}
+
+        // A malformed bytecode marker leaves the information of a single run out, so only the
+        // bytecode metrics are left unset
+        (None, Some(_)) => regression.warnings.push(
+            "The previous run has no bytecode information, so the bytecode is not compared"
+                .to_string(),
+        ),
+
+        (Some(_), None) => regression.warnings.push(
+            "The current run has no bytecode information, so the bytecode is not compared"
+                .to_string(),
+        ),
+    }
+
+    let wall_time = |benchmark: &Benchmark| {
+        benchmark
+            .iteration_windows()
+            .iter()
+            .map(|iteration| iteration.duration().as_millis() as f64)
+            .collect::<Vec<_>>()
+    };
+
+    let previous_time = wall_time(previous_benchmark);
+    if previous_time.is_empty() {
+        return Err(Box::new(wrap!(
+            "Failed to get previous start and end time of benchmarks".into()
+        )));
+    }
+
+    let current_time = wall_time(current_benchmark);
+    if current_time.is_empty() {
+        return Err(Box::new(wrap!(
+            "Failed to get current start and end time of benchmarks".into()
+        )));
+    }
+
+    regression.time = check(&previous_time, &current_time);
+
+    let iteration_counters = |benchmark: &Benchmark| {
+        benchmark
+            .iterations
+            .iter()
+            .filter_map(|iteration| iteration.counters)
+            .collect::<Vec<_>>()
+    };
 
-        *metric = check(previous_aggregated_value, current_aggregated_value);
-    }
-
-    let previous_bytecode_size = previous_benchmark
-        .asm_information
-        .as_ref()
-        .ok_or(wrap!(
-            "Failed to get previous asm information for bytecode size".into()
-        ))?
-        .get("bytecode_size")
-        .ok_or(wrap!("Failed to get the previous bytecode size".into()))?
-        .as_u64()
-        .ok_or(wrap!("Failed to parse previous bytecode size as u64".into()))?
-        as f64;
-
-    let current_bytecode_size = current_benchmark
-        .asm_information
-        .as_ref()
-        .ok_or(wrap!(
-            "Failed to get current asm information for bytecode size".into()
-        ))?
-        .get("bytecode_size")
-        .ok_or(wrap!("Failed to get the current bytecode size".into()))?
-        .as_u64()
-        .ok_or(wrap!("Failed to parse current bytecode size as u64".into()))?
-        as f64;
-
-    regression.bytecode_size = check(previous_bytecode_size, current_bytecode_size);
-
-    let previous_datasection_size = previous_benchmark
-        .asm_information
-        .as_ref()
-        .ok_or(wrap!(
-            "Failed to get previous asm information for data section".into()
-        ))?
-        .get("data_section")
-        .ok_or(wrap!("Failed to get previous data section".into()))?
-        .get("size")
-        .ok_or(wrap!("Failed to get previous size of data section".into()))?
-        .as_u64()
-        .ok_or(wrap!(
-            "Failed to parse previous size for data section as u64".into()
-        ))? as f64;
-
-    let current_datasection_size = current_benchmark
-        .asm_information
-        .as_ref()
-        .ok_or(wrap!(
-            "Failed to get current asm information for data section".into()
-        ))?
-        .get("data_section")
-        .ok_or(wrap!("Failed to get current data section".into()))?
-        .get("size")
-        .ok_or(wrap!("Failed to get current size of data section".into()))?
-        .as_u64()
-        .ok_or(wrap!(
-            "Failed to parse current size for data section as u64".into()
-        ))? as f64;
-
-    regression.data_section_size = check(previous_datasection_size, current_datasection_size);
-
-    let previous_time = previous_benchmark
-        .end_time
-        .as_ref()
-        .ok_or(wrap!("Failed to get previous end time of benchmarks".into()))?
-        .as_millis()
-        - previous_benchmark
-            .start_time
-            .as_ref()
-            .ok_or(wrap!(
-                "Failed to get previous start time of benchmarks".into()
-            ))?
-            .as_millis();
-    let current_time = current_benchmark
-        .end_time
-        .as_ref()
-        .ok_or(wrap!("Failed to get current end time of benchmarks".into()))?
-        .as_millis()
-        - current_benchmark
-            .start_time
-            .as_ref()
-            .ok_or(wrap!(
-                "Failed to get current start time of benchmarks".into()
-            ))?
-            .as_millis();
-
-    regression.time = check(previous_time as f64, current_time as f64);
+    regression.counters = compare_counters(
+        &iteration_counters(previous_benchmark),
+        &iteration_counters(current_benchmark),
+    );
+
+    regression.phases = compare_phases(previous_benchmark, current_benchmark);
 
     Ok(regression)
 }
 
+/// [`PhaseSummary`] struct that summarises the frames captured during a single phase of a benchmark
+#[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PhaseSummary {
+    /// The duration of the phase (in milliseconds)
+    pub duration: f64,
+    /// The peak memory usage (in bytes) during the phase
+    pub peak_memory_usage: f64,
+    /// The mean memory usage (in bytes) during the phase
+    pub mean_memory_usage: f64,
+    /// The mean CPU usage during the phase
+    pub mean_cpu_usage: f64,
+    /// The number of bytes read from disk during the phase
+    pub disk_read_bytes: f64,
+    /// The number of bytes written to disk during the phase
+    pub disk_written_bytes: f64,
+    /// The performance counters of the phase, if they were collected
+    pub counters: Option<HardwareCounters>,
+}
+
+/// [`PhaseStats`] struct that contains the regression information for each metric of a phase
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct PhaseStats {
+    pub duration: Change,
+    pub peak_memory_usage: Change,
+    pub mean_memory_usage: Change,
+    pub mean_cpu_usage: Change,
+    pub disk_read_bytes: Change,
+    pub disk_written_bytes: Change,
+    /// The regression information of the performance counters, when both phases collected them
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub counters: Option<CounterStats>,
+}
+
+/// Summarise the frames captured within the time window of a phase
+///
+/// Returns `None` if the phase never started or stopped.
+pub(crate) fn summarize_phase(
+    phase: &BenchmarkPhase,
+    frames: &[BenchmarkFrame],
+) -> Option<PhaseSummary> {
+    let (start_time, end_time) = (phase.start_time?, phase.end_time?);
+
+    let phase_frames: Vec<&BenchmarkFrame> = frames
+        .iter()
+        .filter(|frame| frame.timestamp >= start_time && frame.timestamp <= end_time)
+        .collect();
+
+    let memory_usage: Vec<f64> = phase_frames
+        .iter()
+        .map(|frame| frame.memory_usage as f64)
+        .collect();
+
+    let cpu_usage: Vec<f64> = phase_frames
+        .iter()
+        .map(|frame| f64::from(frame.cpu_usage))
+        .collect();
+
+    Some(PhaseSummary {
+        duration: end_time.saturating_sub(start_time).as_secs_f64() * 1000.0,
+        peak_memory_usage: memory_usage.iter().copied().fold(0.0, f64::max),
+        mean_memory_usage: mean(&memory_usage),
+        mean_cpu_usage: mean(&cpu_usage),
+        disk_read_bytes: phase_frames
+            .iter()
+            .map(|frame| frame.disk_read_bytes as f64)
+            .sum(),
+        disk_written_bytes: phase_frames
+            .iter()
+            .map(|frame| frame.disk_written_bytes as f64)
+            .sum(),
+        counters: phase.counters,
+    })
+}
+
+/// Summarise the phases of each measured iteration of a benchmark
+///
+/// Phases are keyed by their path of names, in the order they first started. A nested phase is keyed
+/// by the names of its ancestors and its own, i.e. `compile/type check`. A phase that runs more
+/// than once within an iteration is keyed by its path and occurrence, i.e. `name #2`.
+pub(crate) fn phase_summaries(benchmark: &Benchmark) -> Vec<(String, Vec<PhaseSummary>)> {
+    let mut summaries: Vec<(String, Vec<PhaseSummary>)> = vec![];
+
+    for (iteration, frames) in benchmark
+        .iteration_windows()
+        .iter()
+        .zip(benchmark.iteration_frames())
+    {
+        let phases = BenchmarkPhase::walk(&benchmark.phases);
+        let mut occurrences: BTreeMap<&str, usize> = BTreeMap::new();
+
+        for (path, phase) in &phases {
+            if !phase
+                .start_time
+                .is_some_and(|start_time| iteration.contains(start_time))
+            {
+                continue;
+            }
+
+            let occurrence = occurrences.entry(path).or_default();
+            *occurrence += 1;
+
+            let Some(summary) = summarize_phase(phase, &frames) else {
+                continue;
+            };
+
+            let key = if *occurrence == 1 {
+                path.clone()
+            } else {
+                format!("{path} #{occurrence}")
+            };
+
+            match summaries.iter_mut().find(|(name, _)| *name == key) {
+                Some((_, samples)) => samples.push(summary),
+                None => summaries.push((key, vec![summary])),
+            }
+        }
+    }
+
+    summaries
+}
+
+/// Compare the phases of two benchmarks that are present in both benchmarks
+fn compare_phases(
+    previous_benchmark: &Benchmark,
+    current_benchmark: &Benchmark,
+) -> Vec<(String, PhaseStats)> {
+    let previous_phases = phase_summaries(previous_benchmark);
+
+    phase_summaries(current_benchmark)
+        .into_iter()
+        .filter_map(|(name, current)| {
+            let (_, previous) = previous_phases.iter().find(|(n, _)| *n == name)?;
+
+            let samples = |summaries: &[PhaseSummary], metric_fn: fn(&PhaseSummary) -> f64| {
+                summaries.iter().map(metric_fn).collect::<Vec<_>>()
+            };
+            let check_metric = |metric_fn: fn(&PhaseSummary) -> f64| {
+                check(&samples(previous, metric_fn), &samples(&current, metric_fn))
+            };
+
+            let stats = PhaseStats {
+                duration: check_metric(|p| p.duration),
+                peak_memory_usage: check_metric(|p| p.peak_memory_usage),
+                mean_memory_usage: check_metric(|p| p.mean_memory_usage),
+                mean_cpu_usage: check_metric(|p| p.mean_cpu_usage),
+                disk_read_bytes: check_metric(|p| p.disk_read_bytes),
+                disk_written_bytes: check_metric(|p| p.disk_written_bytes),
+                counters: compare_counters(
+                    &previous
+                        .iter()
+                        .filter_map(|p| p.counters)
+                        .collect::<Vec<_>>(),
+                    &current
+                        .iter()
+                        .filter_map(|p| p.counters)
+                        .collect::<Vec<_>>(),
+                ),
+            };
+
+            Some((name, stats))
+        })
+        .collect()
+}
+
 #[cfg(test)]
 mod tests {
     #[test]
@@ -241,4 +1091,463 @@ mod tests {
         println!("{:#?}", regression);
         Ok(())
     }
+
+    fn make_benchmarks(target: &str, paths: &[&str]) -> crate::types::Benchmarks {
+        crate::types::Benchmarks {
+            total_time: std::time::Duration::from_secs(1),
+            system_specs: crate::types::SystemSpecs::default(),
+            benchmarks: paths
+                .iter()
+                .map(|path| {
+                    let path = std::path::Path::new(target).join(path);
+                    let name = path.file_name().unwrap().to_str().unwrap().to_string();
+                    crate::types::Benchmark::new(&name, path)
+                })
+                .collect(),
+            forc_version: "0.1.0".to_string(),
+            compiler_hash: "123456".to_string(),
+            benchmarks_datetime: "2021-01-01T00:00:00".to_string(),
+            target: target.into(),
+            label: None,
+            jobs: None,
+            environment: crate::environment::Environment::default(),
+        }
+    }
+
+    #[test]
+    fn test_match_benchmarks() {
+        let previous = make_benchmarks(
+            "/old/test_projects",
+            &[
+                "hashing",
+                "storage/vec",
+                "removed",
+                "a/moved",
+                "x/dup",
+                "y/dup",
+            ],
+        );
+        let current = make_benchmarks(
+            "/new/test_projects",
+            &[
+                "added",
+                "b/moved",
+                "storage/vec",
+                "hashing",
+                "x/dup",
+                "z/dup",
+            ],
+        );
+
+        let matches = crate::stats::match_benchmarks(&previous, &current);
+
+        let pairs: Vec<(String, String)> = matches
+            .pairs
+            .iter()
+            .map(|pair| (pair.previous_id.to_string(), pair.current_id.to_string()))
+            .collect();
+
+        assert_eq!(
+            pairs,
+            vec![
+                ("storage/vec".to_string(), "storage/vec".to_string()),
+                ("hashing".to_string(), "hashing".to_string()),
+                ("x/dup".to_string(), "x/dup".to_string()),
+                ("a/moved".to_string(), "b/moved".to_string()),
+            ]
+        );
+
+        let added: Vec<String> = matches.added.iter().map(ToString::to_string).collect();
+        assert_eq!(added, vec!["added".to_string(), "z/dup".to_string()]);
+
+        let removed: Vec<String> = matches.removed.iter().map(ToString::to_string).collect();
+        assert_eq!(removed, vec!["y/dup".to_string(), "removed".to_string()]);
+    }
+
+    #[test]
+    fn test_compare_failed_builds() -> crate::error::Result<()> {
+        let previous = make_benchmarks("/test_projects", &["hashing"]);
+        let mut current = make_benchmarks("/test_projects", &["hashing"]);
+
+        current.benchmarks[0].status = Some(crate::types::BuildStatus {
+            success: false,
+            exit_code: Some(1),
+            ..Default::default()
+        });
+
+        // The failed build has no asm information, which would otherwise fail the comparison
+        let collection = crate::stats::compare(&previous, &current)?;
+
+        assert!(collection.matched.is_empty());
+        assert_eq!(collection.failed.len(), 1);
+        assert_eq!(collection.failed[0].previous_failure, None);
+        assert_eq!(
+            collection.failed[0].current_failure.as_deref(),
+            Some("exit code 1")
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_compare_build_modes() -> crate::error::Result<()> {
+        use crate::types::BuildMode;
+
+        let run = |modes: [Option<BuildMode>; 2]| {
+            let mut benchmarks = make_benchmarks("/test_projects", &["hashing", "storage"]);
+
+            for (benchmark, mode) in benchmarks.benchmarks.iter_mut().zip(modes) {
+                benchmark.mode = mode;
+                benchmark.start_time = Some(std::time::Duration::ZERO);
+                benchmark.end_time = Some(std::time::Duration::from_secs(1));
+                benchmark.asm_information = Some(crate::asm::AsmInformation::default());
+            }
+
+            benchmarks
+        };
+
+        let current = run([Some(BuildMode::Cold), Some(BuildMode::Cold)]);
+
+        let collection = crate::stats::compare(&run([Some(BuildMode::Cold); 2]), &current)?;
+        assert_eq!(collection.matched.len(), 2);
+        assert!(collection.mismatched.is_empty());
+
+        // Benchmarks recorded before build modes were unprimed, and only match each other
+        let collection = crate::stats::compare(&run([None, None]), &current)?;
+        assert!(collection.matched.is_empty());
+        assert_eq!(collection.mismatched.len(), 2);
+        assert_eq!(collection.mismatched[0].previous_mode, None);
+
+        let collection = crate::stats::compare(&run([None, None]), &run([None, None]))?;
+        assert_eq!(collection.matched.len(), 2);
+
+        let previous = run([Some(BuildMode::Cold), Some(BuildMode::Incremental)]);
+
+        let collection = crate::stats::compare(&previous, &current)?;
+        assert_eq!(collection.matched.len(), 1);
+        assert_eq!(collection.mismatched.len(), 1);
+        assert_eq!(collection.mismatched[0].current, "storage");
+        assert_eq!(
+            collection.mismatched[0].previous_mode,
+            Some(BuildMode::Incremental)
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_compare_workloads() -> crate::error::Result<()> {
+        let run = |workloads: &[&str]| {
+            let mut benchmarks = make_benchmarks("/test_projects", &[]);
+
+            for name in workloads {
+                let mut benchmark =
+                    crate::types::Benchmark::new(&"hashing", "/test_projects/hashing");
+                benchmark.start_time = Some(std::time::Duration::ZERO);
+                benchmark.end_time = Some(std::time::Duration::from_secs(1));
+
+                // Only builds report bytecode
+                if *name == crate::workload::DEFAULT_WORKLOAD {
+                    benchmark.asm_information = Some(crate::asm::AsmInformation::default());
+                } else {
+                    benchmark.workload.name = (*name).to_string();
+                }
+
+                benchmarks.benchmarks.push(benchmark);
+            }
+
+            benchmarks
+        };
+
+        let previous = run(&["build", "fmt"]);
+        let current = run(&["build", "fmt", "release"]);
+
+        let collection = crate::stats::compare(&previous, &current)?;
+        assert_eq!(
+            collection
+                .matched
+                .iter()
+                .map(|(id, _)| id.as_str())
+                .collect::<Vec<_>>(),
+            vec!["hashing", "hashing [fmt]"]
+        );
+        assert_eq!(collection.added, vec!["hashing [release]"]);
+        assert!(collection.renamed.is_empty());
+
+        assert_eq!(
+            crate::stats::split_workload("hashing [release]"),
+            ("hashing", Some("release"))
+        );
+        assert_eq!(crate::stats::split_workload("hashing"), ("hashing", None));
+
+        Ok(())
+    }
+
+    #[test]
+    #[allow(clippy::float_cmp)]
+    fn test_compare_one_sided_asm_information() -> crate::error::Result<()> {
+        let run = |asm_information: Option<crate::asm::AsmInformation>| {
+            let mut benchmarks = make_benchmarks("/test_projects", &["hashing"]);
+            let benchmark = &mut benchmarks.benchmarks[0];
+            benchmark.start_time = Some(std::time::Duration::ZERO);
+            benchmark.end_time = Some(std::time::Duration::from_secs(1));
+            benchmark.asm_information = asm_information;
+            benchmarks
+        };
+
+        let asm_information = crate::asm::AsmInformation {
+            bytecode_size: 1024,
+            entries: vec![crate::asm::EntryInformation::default()],
+            ..Default::default()
+        };
+
+        // The previous run dropped its malformed marker, so only the bytecode is left unset
+        let collection = crate::stats::compare(&run(None), &run(Some(asm_information)))?;
+        assert_eq!(collection.matched.len(), 1);
+
+        let stats = &collection.matched[0].1;
+        assert_eq!(stats.bytecode_size.change, 0.0);
+        assert!(stats.bytecode_size.significance.is_none());
+        assert_eq!(stats.entries.change, 0.0);
+        assert!(stats.sizes.is_empty());
+        assert_eq!(stats.warnings.len(), 1);
+        assert!(stats.warnings[0].contains("previous run"));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_read_stored_stats() {
+        // Stats files of older versions were a list of benchmarks and their metric tuples
+        let legacy = r#"[["hashing", { "cpu_usage": [1.0, 2.0], "time": [3.0, 4.0] }]]"#;
+        let error = crate::stats::Collection::from_json(legacy).unwrap_err();
+        assert!(error.contains("dyno compare"));
+
+        // Lists and metrics that were added later are read as empty
+        let collection = crate::stats::Collection::from_json(
+            r#"{ "matched": [["hashing", { "time": { "change": 3.0, "percentage_change": 5.0 } }]] }"#,
+        )
+        .unwrap();
+        assert_eq!(collection.matched.len(), 1);
+        assert!(collection.renamed.is_empty() && collection.mismatched.is_empty());
+        assert!(collection.matched[0].1.sizes.is_empty());
+
+        let stored = serde_json::to_string(&collection).unwrap();
+        assert!(crate::stats::Collection::from_json(&stored).is_ok());
+        assert!(crate::stats::Collection::from_json("{").is_err());
+    }
+
+    #[test]
+    fn test_compare_parallel_runs() {
+        let serial = make_benchmarks("/test_projects", &["hashing"]);
+        let mut parallel = make_benchmarks("/test_projects", &["hashing"]);
+        parallel.jobs = Some(4);
+
+        assert!(crate::stats::compare(&serial, &parallel).is_err());
+        assert_eq!(
+            crate::stats::check_jobs(serial.jobs, parallel.jobs),
+            Err("Refusing to compare a run of 4 parallel jobs against a serial run".to_string())
+        );
+        assert!(crate::stats::check_jobs(Some(2), Some(4)).is_err());
+        assert!(crate::stats::check_jobs(Some(4), Some(4)).is_ok());
+        assert!(crate::stats::check_jobs(None, None).is_ok());
+    }
+
+    #[test]
+    #[allow(clippy::float_cmp)]
+    fn test_summarize() {
+        let summary = crate::stats::summarize(&[4.0, 1.0, 3.0, 2.0]);
+        assert_eq!(summary.min, 1.0);
+        assert_eq!(summary.max, 4.0);
+        assert_eq!(summary.mean, 2.5);
+        assert_eq!(summary.median, 2.5);
+        assert!((summary.stddev - 1.290_994_448_735_805_6).abs() < 1e-12);
+
+        let summary = crate::stats::summarize(&[7.0]);
+        assert_eq!(summary.median, 7.0);
+        assert_eq!(summary.stddev, 0.0);
+    }
+
+    #[test]
+    #[allow(clippy::float_cmp)]
+    fn test_aggregate_iterations() {
+        use crate::types::{BenchmarkFrame, BenchmarkIteration};
+        use std::time::Duration;
+
+        let frame = |millis: u64, memory_usage: u64| BenchmarkFrame {
+            timestamp: Duration::from_millis(millis),
+            relative_timestamp: Duration::from_millis(millis),
+            lateness: Duration::ZERO,
+            cpu_usage: 0.0,
+            memory_usage,
+            virtual_memory_usage: 0,
+            disk_total_written_bytes: 0,
+            disk_written_bytes: 0,
+            disk_total_read_bytes: 0,
+            disk_read_bytes: 0,
+            processes: vec![],
+        };
+
+        let mut benchmark = crate::types::Benchmark::new(&"hashing", "hashing");
+        benchmark.iterations = vec![
+            BenchmarkIteration {
+                start_time: Duration::from_millis(0),
+                end_time: Duration::from_millis(100),
+                counters: None,
+            },
+            BenchmarkIteration {
+                start_time: Duration::from_millis(200),
+                end_time: Duration::from_millis(500),
+                counters: None,
+            },
+        ];
+        *benchmark.frames.lock().unwrap() = vec![
+            frame(0, 10),
+            frame(50, 30),
+            frame(200, 100),
+            frame(300, 100),
+        ];
+
+        let aggregate = crate::stats::aggregate(&benchmark);
+        assert_eq!(aggregate.iterations, 2);
+        assert_eq!(aggregate.wall_time.mean, 200.0);
+        assert_eq!(aggregate.memory_usage.min, 30.0);
+        assert_eq!(aggregate.memory_usage.max, 100.0);
+    }
+
+    #[test]
+    #[allow(clippy::float_cmp)]
+    fn test_phase_summaries() {
+        use crate::types::{BenchmarkFrame, BenchmarkIteration, BenchmarkPhase};
+        use std::time::Duration;
+
+        let frame = |millis: u64, memory_usage: u64, disk_read_bytes: u64| BenchmarkFrame {
+            timestamp: Duration::from_millis(millis),
+            relative_timestamp: Duration::from_millis(millis),
+            lateness: Duration::ZERO,
+            cpu_usage: 50.0,
+            memory_usage,
+            virtual_memory_usage: 0,
+            disk_total_written_bytes: 0,
+            disk_written_bytes: 0,
+            disk_total_read_bytes: 0,
+            disk_read_bytes,
+            processes: vec![],
+        };
+        let phase = |name: &str, start: u64, end: u64| BenchmarkPhase {
+            name: name.to_string(),
+            start_time: Some(Duration::from_millis(start)),
+            end_time: Some(Duration::from_millis(end)),
+            ..Default::default()
+        };
+
+        let mut benchmark = crate::types::Benchmark::new(&"hashing", "hashing");
+        benchmark.iterations = vec![
+            BenchmarkIteration {
+                start_time: Duration::from_millis(0),
+                end_time: Duration::from_millis(400),
+                counters: None,
+            },
+            BenchmarkIteration {
+                start_time: Duration::from_millis(500),
+                end_time: Duration::from_millis(900),
+                counters: None,
+            },
+        ];
+        benchmark.phases = vec![
+            phase("parse", 0, 100),
+            phase("type check", 100, 300),
+            phase("type check", 300, 400),
+            phase("parse", 500, 550),
+        ];
+        *benchmark.frames.lock().unwrap() = vec![
+            frame(50, 10, 1),
+            frame(150, 30, 2),
+            frame(250, 50, 4),
+            frame(525, 20, 8),
+        ];
+
+        let summaries = crate::stats::phase_summaries(&benchmark);
+        let names: Vec<&str> = summaries.iter().map(|(name, _)| name.as_str()).collect();
+        assert_eq!(names, vec!["parse", "type check", "type check #2"]);
+
+        let (_, parse) = &summaries[0];
+        assert_eq!(parse.len(), 2);
+        assert_eq!(parse[0].duration, 100.0);
+        assert_eq!(parse[1].peak_memory_usage, 20.0);
+
+        let (_, type_check) = &summaries[1];
+        assert_eq!(type_check[0].peak_memory_usage, 50.0);
+        assert_eq!(type_check[0].mean_memory_usage, 40.0);
+        assert_eq!(type_check[0].disk_read_bytes, 6.0);
+    }
+
+    #[test]
+    #[allow(clippy::float_cmp)]
+    fn test_reduce_frames() {
+        use crate::types::BenchmarkFrame;
+        use std::time::Duration;
+
+        let frame =
+            |millis: u64, cpu_usage: f32, memory_usage: u64, disk_total: u64| BenchmarkFrame {
+                timestamp: Duration::from_millis(millis),
+                relative_timestamp: Duration::from_millis(millis),
+                lateness: Duration::ZERO,
+                cpu_usage,
+                memory_usage,
+                virtual_memory_usage: memory_usage * 2,
+                disk_total_written_bytes: disk_total,
+                disk_written_bytes: 0,
+                disk_total_read_bytes: disk_total,
+                disk_read_bytes: 0,
+                processes: vec![],
+            };
+
+        // Unevenly spaced samples must not skew the mean towards the densely sampled values
+        let frames = vec![
+            frame(0, 100.0, 100, 10),
+            frame(100, 100.0, 300, 20),
+            frame(200, 0.0, 100, 40),
+            frame(1200, 0.0, 100, 80),
+        ];
+
+        let metrics = crate::stats::reduce_frames(&frames);
+        assert_eq!(metrics.cpu_usage_peak, 100.0);
+        assert!((metrics.cpu_usage_integral - 15.0).abs() < 1e-9);
+        assert!((metrics.cpu_usage_mean - 12.5).abs() < 1e-9);
+        assert_eq!(metrics.memory_usage_peak, 300.0);
+        assert_eq!(metrics.memory_usage_p95, 300.0);
+        assert!((metrics.memory_usage_integral - 140.0).abs() < 1e-9);
+        assert_eq!(metrics.virtual_memory_usage_peak, 600.0);
+        assert_eq!(metrics.disk_total_written_bytes, 80.0);
+        assert_eq!(metrics.disk_total_read_bytes, 80.0);
+    }
+
+    #[test]
+    fn test_compare_counters() {
+        use crate::types::HardwareCounters;
+
+        let counters = |instructions: u64, cycles: Option<u64>| HardwareCounters {
+            instructions: Some(instructions),
+            cycles,
+            ..Default::default()
+        };
+
+        let stats = crate::stats::compare_counters(
+            &[counters(1000, Some(10)), counters(1000, Some(10))],
+            &[counters(1100, None), counters(1100, None)],
+        )
+        .expect("Both runs collected counters");
+
+        let instructions = stats.instructions.as_ref().unwrap();
+        assert!((instructions.change - 100.0).abs() < 1e-9);
+        assert!((instructions.percentage_change - 10.0).abs() < 1e-9);
+
+        // Counters missing from either run are not compared
+        assert!(stats.cycles.is_none());
+        assert!(stats.cache_misses.is_none());
+        assert_eq!(stats.metrics().len(), 1);
+
+        assert!(crate::stats::compare_counters(&[], &[counters(1, None)]).is_none());
+    }
 }
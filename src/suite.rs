@@ -0,0 +1,167 @@
+use serde::Deserialize;
+use std::collections::{BTreeMap, BTreeSet};
+
+use crate::types::Benchmark;
+use crate::wrap;
+
+/// A suite manifest, which tags the projects of a target so that a run can select them by tag
+#[derive(Debug, Default, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Suite {
+    /// The tags of the projects, keyed by a glob of the project path relative to the target
+    #[serde(default)]
+    pub projects: BTreeMap<String, Vec<String>>,
+}
+
+impl Suite {
+    /// Load a suite manifest from a TOML file
+    ///
+    /// # Errors
+    ///
+    /// If the file cannot be read or parsed, or contains an unknown setting.
+    ///
+    pub fn load(path: &std::path::Path) -> crate::Result<Self> {
+        let text = std::fs::read_to_string(path).map_err(|e| wrap!(e.into()))?;
+        let suite: Self = toml::from_str(&text).map_err(|e| wrap!(Box::new(e)))?;
+
+        Ok(suite)
+    }
+
+    /// Returns every tag of the manifest.
+    #[must_use]
+    pub fn tags(&self) -> BTreeSet<&str> {
+        self.projects
+            .values()
+            .flatten()
+            .map(String::as_str)
+            .collect()
+    }
+
+    /// Check that every selected tag is defined in the manifest
+    ///
+    /// # Errors
+    ///
+    /// If a tag is selected without a manifest, or is not defined in the manifest.
+    ///
+    pub fn check_tags(&self, selected: &[String]) -> Result<(), String> {
+        if selected.is_empty() {
+            return Ok(());
+        }
+
+        if self.projects.is_empty() {
+            return Err("Selecting projects by tag requires a suite manifest".to_string());
+        }
+
+        let tags = self.tags();
+
+        if let Some(tag) = selected.iter().find(|tag| !tags.contains(tag.as_str())) {
+            return Err(format!(
+                "Unknown tag \"{tag}\", the suite manifest defines {}",
+                tags.into_iter().collect::<Vec<_>>().join(", ")
+            ));
+        }
+
+        Ok(())
+    }
+
+    /// Tag the benchmarks with the tags of every manifest entry matching their path, and keep
+    /// the benchmarks with any of the selected tags.
+    ///
+    /// # Arguments
+    ///
+    /// * `benchmarks` - The benchmarks discovered in the target root.
+    ///
+    /// * `target_root` - The root folder the benchmarks were discovered in.
+    ///
+    /// * `selected` - The tags of the benchmarks to keep, or all benchmarks when empty.
+    ///
+    /// # Returns
+    ///
+    /// A `Result` containing the `Vec` of kept and tagged `Benchmark` structs.
+    ///
+    /// # Errors
+    ///
+    /// If a glob of the manifest is invalid.
+    ///
+    pub fn select(
+        &self,
+        benchmarks: Vec<Benchmark>,
+        target_root: &std::path::Path,
+        selected: &[String],
+    ) -> crate::Result<Vec<Benchmark>> {
+        let globs = self.projects.keys().cloned().collect::<Vec<_>>();
+        let glob_set = crate::utils::glob_set(&globs).map_err(|e| wrap!(e))?;
+        let entries = self.projects.values().collect::<Vec<_>>();
+
+        Ok(benchmarks
+            .into_iter()
+            .filter_map(|mut benchmark| {
+                let relative_path = crate::utils::relative_project_path(&benchmark, target_root);
+
+                let tags = glob_set
+                    .matches(relative_path)
+                    .into_iter()
+                    .flat_map(|index| entries[index].iter().cloned())
+                    .collect::<BTreeSet<_>>();
+
+                benchmark.tags = tags.into_iter().collect();
+
+                (selected.is_empty() || selected.iter().any(|tag| benchmark.tags.contains(tag)))
+                    .then_some(benchmark)
+            })
+            .collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_select_tags() -> crate::Result<()> {
+        let suite: Suite = toml::from_str(
+            r#"
+            [projects]
+            "sway-lib-std" = ["stdlib", "small"]
+            "examples/*" = ["small"]
+            "sdk-harness" = ["sdk-harness"]
+            "#,
+        )
+        .map_err(|e| wrap!(Box::new(e)))?;
+
+        let root = std::path::Path::new("/sway");
+        let benchmarks = || {
+            [
+                "sway-lib-std",
+                "examples/counter",
+                "sdk-harness",
+                "untagged",
+            ]
+            .map(|path| {
+                let path = root.join(path);
+                let name = path.file_name().unwrap().to_str().unwrap().to_string();
+                Benchmark::new(&name, path)
+            })
+            .to_vec()
+        };
+
+        // Without selected tags every benchmark is kept and tagged
+        let all = suite.select(benchmarks(), root, &[])?;
+        assert_eq!(all.len(), 4);
+        assert_eq!(all[0].tags, vec!["small", "stdlib"]);
+        assert!(all[3].tags.is_empty());
+
+        let small = suite.select(benchmarks(), root, &["small".to_string()])?;
+        assert_eq!(
+            small.iter().map(|b| b.name.as_str()).collect::<Vec<_>>(),
+            vec!["sway-lib-std", "counter"]
+        );
+
+        assert!(suite.check_tags(&["small".to_string()]).is_ok());
+        assert!(suite.check_tags(&["nightly".to_string()]).is_err());
+        assert!(Suite::default().check_tags(&["small".to_string()]).is_err());
+        assert!(Suite::default().check_tags(&[]).is_ok());
+
+        Ok(())
+    }
+}
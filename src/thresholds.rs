@@ -0,0 +1,447 @@
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+use crate::significance::Verdict;
+use crate::stats::{Change, Collection, Stats};
+use crate::wrap;
+
+/// The limit on how much a metric may regress before it is reported as a violation
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(try_from = "RawThreshold", into = "String")]
+pub enum Threshold {
+    /// The maximum allowed increase of the metric, in the unit of the metric
+    Absolute(f64),
+    /// The maximum allowed increase of the metric, as a percentage of the previous value
+    Percentage(f64),
+}
+
+/// A threshold as written in a thresholds file, either `"5%"`, `"1024"` or `1024`
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum RawThreshold {
+    Number(f64),
+    Text(String),
+}
+
+impl TryFrom<RawThreshold> for Threshold {
+    type Error = String;
+
+    fn try_from(raw: RawThreshold) -> Result<Self, Self::Error> {
+        match raw {
+            RawThreshold::Number(value) => Ok(Threshold::Absolute(value)),
+            RawThreshold::Text(text) => text.parse(),
+        }
+    }
+}
+
+impl From<Threshold> for String {
+    fn from(threshold: Threshold) -> Self {
+        threshold.to_string()
+    }
+}
+
+impl std::str::FromStr for Threshold {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+
+        let (value, percentage) = match s.strip_suffix('%') {
+            Some(value) => (value.trim(), true),
+            None => (s, false),
+        };
+
+        let value: f64 = value
+            .parse()
+            .map_err(|_| format!("Invalid threshold \"{s}\", expected a number or a percentage"))?;
+
+        if !value.is_finite() || value < 0.0 {
+            return Err(format!(
+                "Invalid threshold \"{s}\", expected a non-negative value"
+            ));
+        }
+
+        Ok(if percentage {
+            Threshold::Percentage(value)
+        } else {
+            Threshold::Absolute(value)
+        })
+    }
+}
+
+impl std::fmt::Display for Threshold {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Threshold::Absolute(value) => write!(f, "{value}"),
+            Threshold::Percentage(value) => write!(f, "{value}%"),
+        }
+    }
+}
+
+impl Threshold {
+    /// Check if the change in a metric is greater than the threshold
+    pub fn is_exceeded_by(&self, change: &Change) -> bool {
+        match self {
+            Threshold::Absolute(value) => change.change > *value,
+            Threshold::Percentage(value) => change.percentage_change > *value,
+        }
+    }
+}
+
+/// Parse a `metric=threshold` command line argument
+///
+/// # Errors
+///
+/// If the argument is not of the form `metric=threshold`
+///
+/// If the metric is unknown
+///
+/// If the threshold is invalid
+///
+pub fn parse_metric_threshold(s: &str) -> Result<(String, Threshold), String> {
+    let (metric, threshold) = s
+        .split_once('=')
+        .ok_or_else(|| format!("Invalid threshold \"{s}\", expected METRIC=VALUE"))?;
+
+    let metric = metric.trim();
+    if !Stats::METRICS.contains(&metric) {
+        return Err(format!(
+            "Unknown metric \"{metric}\", expected one of: {}",
+            Stats::METRICS.join(", ")
+        ));
+    }
+
+    Ok((metric.to_string(), threshold.parse()?))
+}
+
+/// The regression thresholds of each metric
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+pub struct Thresholds {
+    /// The thresholds applied to every benchmark
+    #[serde(default)]
+    pub default: BTreeMap<String, Threshold>,
+    /// The thresholds of specific benchmarks, keyed by benchmark path or name, overriding the defaults
+    #[serde(default)]
+    pub projects: BTreeMap<String, BTreeMap<String, Threshold>>,
+}
+
+impl Thresholds {
+    /// Load the thresholds from a JSON file
+    ///
+    /// # Errors
+    ///
+    /// If the file cannot be read
+    ///
+    /// If the file cannot be deserialized
+    ///
+    /// If the file contains an unknown metric
+    ///
+    pub fn load(path: &std::path::Path) -> crate::error::Result<Self> {
+        let thresholds = std::fs::read_to_string(path).map_err(|e| wrap!(e.into()))?;
+        let thresholds: Self = serde_json::from_str(&thresholds).map_err(|e| wrap!(e.into()))?;
+
+        thresholds.validate(path).map_err(|e| wrap!(e))?;
+
+        Ok(thresholds)
+    }
+
+    /// Check that every threshold is set on a known metric
+    ///
+    /// # Errors
+    ///
+    /// If a threshold is set on an unknown metric
+    ///
+    pub fn validate(&self, path: &std::path::Path) -> crate::error::Result<()> {
+        for metric in self
+            .default
+            .keys()
+            .chain(self.projects.values().flat_map(BTreeMap::keys))
+        {
+            if !Stats::METRICS.contains(&metric.as_str()) {
+                return Err(Box::new(wrap!(format!(
+                    "Unknown metric \"{metric}\" in {}",
+                    path.display()
+                )
+                .into())));
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Combine the configured thresholds with a thresholds file, if any, and the thresholds
+    /// given on the command line, each overriding the defaults of the previous
+    ///
+    /// # Errors
+    ///
+    /// If the thresholds file cannot be loaded
+    ///
+    pub fn resolve(
+        configured: &Self,
+        path: Option<&std::path::Path>,
+        overrides: &[(String, Threshold)],
+    ) -> crate::error::Result<Self> {
+        let mut thresholds = configured.clone();
+
+        if let Some(path) = path {
+            let loaded = Self::load(path).map_err(|e| wrap!(e))?;
+
+            thresholds.default.extend(loaded.default);
+            for (project, project_thresholds) in loaded.projects {
+                thresholds
+                    .projects
+                    .entry(project)
+                    .or_default()
+                    .extend(project_thresholds);
+            }
+        }
+
+        thresholds.default.extend(overrides.iter().cloned());
+
+        Ok(thresholds)
+    }
+
+    /// Whether any threshold is set
+    pub fn is_empty(&self) -> bool {
+        self.default.is_empty() && self.projects.values().all(BTreeMap::is_empty)
+    }
+
+    /// Get the threshold of a metric for a benchmark, falling back to the thresholds of its
+    /// project across workloads, and then to the default threshold
+    fn get(&self, benchmark: &str, metric: &str) -> Option<&Threshold> {
+        let (project, _) = crate::stats::split_workload(benchmark);
+
+        let name = std::path::Path::new(project)
+            .file_name()
+            .and_then(|name| name.to_str())
+            .unwrap_or(project);
+
+        self.projects
+            .get(benchmark)
+            .or_else(|| self.projects.get(project))
+            .or_else(|| self.projects.get(name))
+            .and_then(|project| project.get(metric))
+            .or_else(|| self.default.get(metric))
+    }
+}
+
+/// A metric of a benchmark that regressed beyond its threshold
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Violation {
+    pub benchmark: String,
+    pub metric: String,
+    pub threshold: Threshold,
+    pub change: f64,
+    pub percentage_change: f64,
+}
+
+/// The outcome of checking a comparison, which fails a CI run when it is not empty
+#[derive(Debug, Default)]
+pub struct Outcome {
+    /// The metrics that regressed beyond their thresholds
+    pub violations: Vec<Violation>,
+    /// The benchmarks whose build failed or timed out in the current run
+    pub failed: Vec<String>,
+}
+
+impl Outcome {
+    /// Check a comparison against the thresholds, and for builds that failed in the current run
+    ///
+    /// # Arguments
+    ///
+    /// * `collection` - The comparison of two benchmark runs
+    ///
+    /// * `thresholds` - The thresholds of each metric
+    ///
+    pub fn check(collection: &Collection, thresholds: &Thresholds) -> Self {
+        Self {
+            violations: evaluate(collection, thresholds),
+            failed: failed_builds(collection),
+        }
+    }
+
+    /// Whether the comparison passed
+    pub fn is_empty(&self) -> bool {
+        self.violations.is_empty() && self.failed.is_empty()
+    }
+}
+
+/// Find the compared benchmarks whose build failed or timed out in the current run
+///
+/// A benchmark that only failed in the previous run was fixed, so it is not reported.
+///
+/// # Arguments
+///
+/// * `collection` - The comparison of two benchmark runs
+///
+pub fn failed_builds(collection: &Collection) -> Vec<String> {
+    collection
+        .failed
+        .iter()
+        .filter(|failed| failed.current_failure.is_some())
+        .map(|failed| failed.current.clone())
+        .collect()
+}
+
+/// Find the metrics of the compared benchmarks that regressed beyond their thresholds
+///
+/// Changes that were tested and found not to be real regressions are never reported.
+///
+/// # Arguments
+///
+/// * `collection` - The comparison of two benchmark runs
+///
+/// * `thresholds` - The thresholds of each metric
+///
+pub fn evaluate(collection: &Collection, thresholds: &Thresholds) -> Vec<Violation> {
+    let compared = collection
+        .matched
+        .iter()
+        .map(|(benchmark, stats)| (benchmark, stats))
+        .chain(
+            collection
+                .renamed
+                .iter()
+                .map(|renamed| (&renamed.current, &renamed.stats)),
+        );
+
+    let mut violations = Vec::new();
+
+    for (benchmark, stats) in compared {
+        for (metric, change) in stats.metrics() {
+            let Some(threshold) = thresholds.get(benchmark, metric) else {
+                continue;
+            };
+
+            if !matches!(change.verdict(), None | Some(Verdict::Regressed)) {
+                continue;
+            }
+
+            if threshold.is_exceeded_by(change) {
+                violations.push(Violation {
+                    benchmark: benchmark.clone(),
+                    metric: metric.to_string(),
+                    threshold: *threshold,
+                    change: change.change,
+                    percentage_change: change.percentage_change,
+                });
+            }
+        }
+    }
+
+    violations
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_threshold() {
+        assert_eq!("5%".parse(), Ok(Threshold::Percentage(5.0)));
+        assert_eq!(" 1024 ".parse(), Ok(Threshold::Absolute(1024.0)));
+        assert!("-1".parse::<Threshold>().is_err());
+        assert!("fast".parse::<Threshold>().is_err());
+
+        assert_eq!(
+            parse_metric_threshold("time=10%"),
+            Ok(("time".to_string(), Threshold::Percentage(10.0)))
+        );
+        assert!(parse_metric_threshold("speed=10%").is_err());
+
+        let thresholds: Thresholds = serde_json::from_str(
+            r#"{ "default": { "time": "5%", "bytecode_size": 16 }, "projects": { "hashing": { "time": "20%" } } }"#,
+        )
+        .unwrap();
+        assert_eq!(
+            thresholds.default["bytecode_size"],
+            Threshold::Absolute(16.0)
+        );
+        assert_eq!(
+            thresholds.get("sdk/hashing", "time"),
+            Some(&Threshold::Percentage(20.0))
+        );
+        assert_eq!(
+            thresholds.get("storage", "time"),
+            Some(&Threshold::Percentage(5.0))
+        );
+
+        // The thresholds of a project apply to every workload of it
+        assert_eq!(
+            thresholds.get("sdk/hashing [release]", "time"),
+            Some(&Threshold::Percentage(20.0))
+        );
+    }
+
+    #[test]
+    fn test_evaluate() {
+        let change = |change: f64, percentage_change: f64| Change {
+            change,
+            percentage_change,
+            significance: None,
+        };
+
+        let collection = Collection {
+            matched: vec![
+                (
+                    "hashing".to_string(),
+                    Stats {
+                        time: change(30.0, 30.0),
+                        bytecode_size: change(-8.0, -1.0),
+                        ..Default::default()
+                    },
+                ),
+                (
+                    "storage".to_string(),
+                    Stats {
+                        time: change(3.0, 3.0),
+                        ..Default::default()
+                    },
+                ),
+            ],
+            ..Default::default()
+        };
+
+        let mut thresholds = Thresholds::default();
+        thresholds
+            .default
+            .insert("time".to_string(), Threshold::Percentage(5.0));
+        thresholds
+            .default
+            .insert("bytecode_size".to_string(), Threshold::Absolute(0.0));
+
+        let violations = evaluate(&collection, &thresholds);
+        assert_eq!(violations.len(), 1);
+        assert_eq!(violations[0].benchmark, "hashing");
+        assert_eq!(violations[0].metric, "time");
+    }
+
+    #[test]
+    fn test_check_failed_builds() {
+        let failed = |name: &str, previous_failure: Option<&str>, current_failure: Option<&str>| {
+            crate::stats::Failed {
+                previous: name.to_string(),
+                current: name.to_string(),
+                previous_failure: previous_failure.map(ToString::to_string),
+                current_failure: current_failure.map(ToString::to_string),
+            }
+        };
+
+        let collection = Collection {
+            failed: vec![
+                failed("hashing", None, Some("exit code 1")),
+                failed("storage", Some("timeout"), None),
+            ],
+            ..Default::default()
+        };
+
+        // A build that fails in the current run fails the check even without thresholds, while
+        // a build that only failed in the previous run was fixed
+        let outcome = Outcome::check(&collection, &Thresholds::default());
+        assert!(outcome.violations.is_empty());
+        assert_eq!(outcome.failed, vec!["hashing"]);
+        assert!(!outcome.is_empty());
+
+        assert!(Outcome::check(&Collection::default(), &Thresholds::default()).is_empty());
+    }
+}
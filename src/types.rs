@@ -1,12 +1,14 @@
-use crate::wrap;
+use crate::{asm::AsmInformation, workload::Workload, wrap};
 use crossbeam_channel::{unbounded, Receiver, Sender};
 use inferno::{collapse::Collapse, flamegraph::from_reader};
 use serde::{Deserialize, Serialize};
 use std::{
+    collections::{BTreeMap, HashMap, VecDeque},
     io::{BufRead, BufReader, BufWriter},
     path::PathBuf,
-    process::{Child, Command, Stdio},
+    process::{Child, Command, ExitStatus, Stdio},
     sync::{Arc, Mutex},
+    thread::JoinHandle,
     time::{Duration, Instant},
 };
 use sysinfo::Pid;
@@ -16,6 +18,10 @@ use inferno::collapse::perf::Folder;
 #[cfg(target_os = "macos")]
 use inferno::collapse::sample::Folder;
 
+/// How long to wait in total for the lines of the command's output still in flight once it has
+/// exited
+const OUTPUT_DRAIN_TIMEOUT: Duration = Duration::from_millis(50);
+
 /// A collection of benchmarks and system specifications.
 #[derive(Debug, Serialize, Deserialize)]
 pub struct Benchmarks {
@@ -31,6 +37,18 @@ pub struct Benchmarks {
     pub compiler_hash: String,
     /// The time that the benchmarks were run
     pub benchmarks_datetime: String,
+    /// The target root folder the benchmarks were discovered in
+    #[serde(default)]
+    pub target: PathBuf,
+    /// The label of the run, usually the git ref of the compiler
+    #[serde(default)]
+    pub label: Option<String>,
+    /// The number of benchmarks built concurrently, or `None` for a serial run
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub jobs: Option<u32>,
+    /// The noise reduction settings that the builds were measured with
+    #[serde(default)]
+    pub environment: crate::environment::Environment,
 }
 
 /// A collection of system hardware specifications.
@@ -105,6 +123,8 @@ pub struct LoadAverage {
 }
 
 /// Benchmark metadata and phase-specific performance data.
+// The only unsafe code of its methods is the expansion of `crossbeam_channel::select!`
+#[allow(clippy::unsafe_derive_deserialize)]
 #[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct Benchmark {
     /// The name of the benchmark.
@@ -120,13 +140,246 @@ pub struct Benchmark {
     /// The performance frames collected from the benchmark.
     pub frames: Arc<Mutex<Vec<BenchmarkFrame>>>,
     /// The bytecode information
-    pub asm_information: Option<serde_json::Value>,
+    pub asm_information: Option<AsmInformation>,
     /// The hyperfine information
     pub hyperfine: Option<serde_json::Value>,
+    /// The measured iterations of the benchmark.
+    #[serde(default)]
+    pub iterations: Vec<BenchmarkIteration>,
+    /// The statistical aggregation of the measured iterations of the benchmark.
+    #[serde(default)]
+    pub aggregate: Option<BenchmarkAggregate>,
+    /// The instant events reported by the build.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub events: Vec<BenchmarkEvent>,
+    /// The problems found in the phase markers and messages of the build, which did not stop the run.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub warnings: Vec<String>,
+    /// The exit status and output of the latest build, or of the build that failed.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub status: Option<BuildStatus>,
+    /// The CPUs that the builds were pinned to, when built concurrently with other benchmarks.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub cpus: Vec<usize>,
+    /// How the project was prepared before each build, or `None` if recorded before build modes.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub mode: Option<BuildMode>,
+    /// The forc command run against the project, the default build for older benchmarks.
+    #[serde(default)]
+    pub workload: Workload,
+    /// The tags of the project in the suite manifest.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub tags: Vec<String>,
 }
 
-/// A named collection of performance frames representing a single phase of a benchmark.
+/// How the build outputs and caches of a project are prepared before each build.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum BuildMode {
+    /// The build outputs are removed before each build.
+    Cold,
+    /// A discarded priming build runs before the measured builds, which reuse its outputs.
+    #[default]
+    Warm,
+    /// A source file is touched before each build, after a discarded priming build.
+    Incremental,
+}
+
+impl BuildMode {
+    /// Returns the number of unmeasured builds before the measured builds.
+    ///
+    /// The warm and incremental modes always prime the outputs with at least one build.
+    #[must_use]
+    pub fn warmups(self, warmup: u32) -> u32 {
+        match self {
+            BuildMode::Cold => warmup,
+            BuildMode::Warm | BuildMode::Incremental => warmup.max(1),
+        }
+    }
+}
+
+impl std::str::FromStr for BuildMode {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim() {
+            "cold" => Ok(BuildMode::Cold),
+            "warm" => Ok(BuildMode::Warm),
+            "incremental" => Ok(BuildMode::Incremental),
+            _ => Err(format!(
+                "Invalid build mode \"{s}\", expected `cold`, `warm` or `incremental`"
+            )),
+        }
+    }
+}
+
+impl std::fmt::Display for BuildMode {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            BuildMode::Cold => write!(f, "cold"),
+            BuildMode::Warm => write!(f, "warm"),
+            BuildMode::Incremental => write!(f, "incremental"),
+        }
+    }
+}
+
+/// A format the comparison of a run is output in.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum OutputFormat {
+    /// The stats file stored in the output folder.
+    Json,
+    /// The tables printed to the terminal.
+    Table,
+}
+
+impl std::str::FromStr for OutputFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim() {
+            "json" => Ok(OutputFormat::Json),
+            "table" => Ok(OutputFormat::Table),
+            _ => Err(format!(
+                "Invalid output format \"{s}\", expected `json` or `table`"
+            )),
+        }
+    }
+}
+
+impl std::fmt::Display for OutputFormat {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            OutputFormat::Json => write!(f, "json"),
+            OutputFormat::Table => write!(f, "table"),
+        }
+    }
+}
+
+/// The exit status and bounded output of a single build.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct BuildStatus {
+    /// Whether the build exited successfully.
+    pub success: bool,
+    /// The exit code of the build, or `None` if it was terminated by a signal.
+    pub exit_code: Option<i32>,
+    /// Whether the build was killed for running past the timeout of the benchmark.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub timed_out: bool,
+    /// The latest lines of the stdout of the build, without the `/dyno` markers.
+    #[serde(default)]
+    pub stdout: BuildLog,
+    /// The latest lines of the stderr of the build.
+    #[serde(default)]
+    pub stderr: BuildLog,
+}
+
+/// The latest lines of an output stream of a build, bounded in count and length.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct BuildLog {
+    /// The kept lines of the output.
+    pub lines: VecDeque<String>,
+    /// The number of earlier lines that were dropped to bound the log.
+    #[serde(default)]
+    pub dropped: usize,
+}
+
+/// An instant event reported by the build through the phase protocol.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct BenchmarkEvent {
+    /// The name of the event.
+    pub name: String,
+    /// The time that the event was received.
+    pub timestamp: Duration,
+    /// The path of the phase the event happened in, if any.
+    pub phase: Option<String>,
+    /// The key/value metadata of the event.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub metadata: BTreeMap<String, serde_json::Value>,
+}
+
+/// The time window of a single measured iteration of a benchmark.
 #[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct BenchmarkIteration {
+    /// The start time of the iteration.
+    pub start_time: Duration,
+    /// The end time of the iteration.
+    pub end_time: Duration,
+    /// The performance counters of the whole build, if they were collected.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub counters: Option<HardwareCounters>,
+}
+
+/// The hardware and software performance counters of a build or of a phase of a build.
+///
+/// Counters that the system does not support are left empty.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct HardwareCounters {
+    /// The number of retired instructions.
+    pub instructions: Option<u64>,
+    /// The number of CPU cycles.
+    pub cycles: Option<u64>,
+    /// The number of last level cache misses.
+    pub cache_misses: Option<u64>,
+    /// The number of mispredicted branches.
+    pub branch_misses: Option<u64>,
+    /// The number of context switches.
+    pub context_switches: Option<u64>,
+    /// The number of page faults.
+    pub page_faults: Option<u64>,
+}
+
+/// Summary statistics of a metric across the measured iterations of a benchmark.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct Summary {
+    /// The minimum value of the metric.
+    pub min: f64,
+    /// The maximum value of the metric.
+    pub max: f64,
+    /// The mean value of the metric.
+    pub mean: f64,
+    /// The median value of the metric.
+    pub median: f64,
+    /// The sample standard deviation of the metric.
+    pub stddev: f64,
+}
+
+/// Aggregated metrics across the measured iterations of a benchmark.
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct BenchmarkAggregate {
+    /// The number of measured iterations.
+    pub iterations: usize,
+    /// The wall time (in milliseconds) of each iteration.
+    pub wall_time: Summary,
+    /// The time-weighted mean process-specific CPU usage of each iteration.
+    pub cpu_usage: Summary,
+    /// The peak process-specific memory usage (in bytes) of each iteration.
+    pub memory_usage: Summary,
+    /// The peak process-specific virtual memory usage (in bytes) of each iteration.
+    pub virtual_memory_usage: Summary,
+    /// The total number of bytes the process has written to disk by the end of each iteration.
+    pub disk_total_written_bytes: Summary,
+    /// The sum of the bytes the process has written to disk between each refresh of each iteration.
+    pub disk_written_bytes: Summary,
+    /// The total number of bytes the process has read from disk by the end of each iteration.
+    pub disk_total_read_bytes: Summary,
+    /// The sum of the bytes the process has read from disk between each refresh of each iteration.
+    pub disk_read_bytes: Summary,
+}
+
+/// The state of the phases started through the phase protocol during a single build.
+#[derive(Default)]
+struct ProtocolPhases {
+    /// The indices of each phase in the phase trees of the benchmark, by phase id.
+    indices: HashMap<u64, Vec<usize>>,
+    /// The counters at the start of each phase that has not ended yet, by phase id.
+    counters: HashMap<u64, HardwareCounters>,
+}
+
+/// A named collection of performance frames representing a single phase of a benchmark.
+///
+/// Phases reported through the phase protocol form a tree, each phase holding the phases nested in it.
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
 pub struct BenchmarkPhase {
     /// The name of the benchmark phase.
     pub name: String,
@@ -134,6 +387,18 @@ pub struct BenchmarkPhase {
     pub start_time: Option<Duration>,
     /// The end time of the benchmark phase.
     pub end_time: Option<Duration>,
+    /// The performance counters of the benchmark phase, if they were collected.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub counters: Option<HardwareCounters>,
+    /// The key/value metadata reported for the benchmark phase, such as item or function counts.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub metadata: BTreeMap<String, serde_json::Value>,
+    /// The named counters reported for the benchmark phase.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub counts: BTreeMap<String, f64>,
+    /// The phases nested in the benchmark phase.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub children: Vec<BenchmarkPhase>,
 }
 
 /// A single frame of performance information for a benchmark phase.
@@ -143,6 +408,9 @@ pub struct BenchmarkFrame {
     pub timestamp: Duration,
     /// The relative to the benchmark timestamp.
     pub relative_timestamp: Duration,
+    /// How long after its scheduled tick the frame was captured.
+    #[serde(default)]
+    pub lateness: Duration,
     /// The process-specific CPU usage at the time the frame was captured.
     pub cpu_usage: f32,
     /// The total process-specific memory usage (in bytes) at the time the frame was captured.
@@ -157,11 +425,198 @@ pub struct BenchmarkFrame {
     pub disk_total_read_bytes: u64,
     /// The number of bytes the process has read from disk since the last refresh at the time the frame was captured.
     pub disk_read_bytes: u64,
+    /// The usage of each process of the tree, when the build ran more than one process.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub processes: Vec<ProcessFrame>,
+}
+
+/// The performance information of a single process of the tree at the time a frame was captured.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct ProcessFrame {
+    /// The pid of the process.
+    pub pid: u32,
+    /// The command name of the process.
+    pub name: String,
+    /// The CPU usage of the process.
+    pub cpu_usage: f32,
+    /// The memory usage of the process in bytes.
+    pub memory_usage: u64,
+    /// The virtual memory usage of the process in bytes.
+    pub virtual_memory_usage: u64,
+    /// The number of bytes the process has written to disk since the last refresh.
+    pub disk_written_bytes: u64,
+    /// The number of bytes the process has read from disk since the last refresh.
+    pub disk_read_bytes: u64,
+}
+
+/// The usage of a process tree over a single sampling interval.
+#[derive(Clone, Debug, Default)]
+pub struct ProcessTreeUsage {
+    /// The usage of the whole tree.
+    pub total: ProcessUsage,
+    /// The usage of each process of the tree.
+    pub processes: Vec<ProcessFrame>,
+}
+
+/// The usage of a process over a single sampling interval.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct ProcessUsage {
+    /// The CPU usage, as a percentage of all CPUs.
+    pub cpu_usage: f32,
+    /// The resident memory usage in bytes.
+    pub memory_usage: u64,
+    /// The virtual memory usage in bytes.
+    pub virtual_memory_usage: u64,
+    /// The total number of bytes written to disk.
+    pub disk_total_written_bytes: u64,
+    /// The number of bytes written to disk during the interval.
+    pub disk_written_bytes: u64,
+    /// The total number of bytes read from disk.
+    pub disk_total_read_bytes: u64,
+    /// The number of bytes read from disk during the interval.
+    pub disk_read_bytes: u64,
+}
+
+impl BenchmarkPhase {
+    /// Returns every phase of the trees along with its path of names, parents before their children.
+    ///
+    /// The path of a nested phase joins the names of its ancestors with `/`, i.e. `compile/type check`.
+    #[must_use]
+    pub fn walk(phases: &[BenchmarkPhase]) -> Vec<(String, &BenchmarkPhase)> {
+        let mut result = vec![];
+
+        for phase in phases {
+            result.push((phase.name.clone(), phase));
+
+            for (path, child) in Self::walk(&phase.children) {
+                result.push((format!("{}/{path}", phase.name), child));
+            }
+        }
+
+        result
+    }
+
+    /// Closes every phase of the trees that started but never stopped at `end_time`.
+    ///
+    /// Returns the paths of the closed phases.
+    fn close_dangling(phases: &mut [BenchmarkPhase], end_time: Duration) -> Vec<String> {
+        let mut closed = vec![];
+
+        for phase in phases {
+            if phase.start_time.is_some() && phase.end_time.is_none() {
+                phase.end_time = Some(end_time);
+                closed.push(phase.name.clone());
+            }
+
+            for path in Self::close_dangling(&mut phase.children, end_time) {
+                closed.push(format!("{}/{path}", phase.name));
+            }
+        }
+
+        closed
+    }
+
+    /// Returns the phase at the `indices` of the trees, each index selecting a child of the previous phase.
+    fn at_mut<'a>(
+        phases: &'a mut [BenchmarkPhase],
+        indices: &[usize],
+    ) -> Option<&'a mut BenchmarkPhase> {
+        let (first, rest) = indices.split_first()?;
+        let phase = phases.get_mut(*first)?;
+
+        if rest.is_empty() {
+            Some(phase)
+        } else {
+            Self::at_mut(&mut phase.children, rest)
+        }
+    }
+
+    /// Returns the path of names of the phase at the `indices` of the trees.
+    fn path_at(phases: &[BenchmarkPhase], indices: &[usize]) -> Option<String> {
+        let mut names = vec![];
+        let mut phases = phases;
+
+        for index in indices {
+            let phase = phases.get(*index)?;
+            names.push(phase.name.as_str());
+            phases = &phase.children;
+        }
+
+        Some(names.join("/"))
+    }
+}
+
+impl HardwareCounters {
+    /// Returns the counts since the `start` counters were read.
+    #[must_use]
+    pub fn since(&self, start: &HardwareCounters) -> HardwareCounters {
+        let delta = |end: Option<u64>, start: Option<u64>| Some(end?.saturating_sub(start?));
+
+        HardwareCounters {
+            instructions: delta(self.instructions, start.instructions),
+            cycles: delta(self.cycles, start.cycles),
+            cache_misses: delta(self.cache_misses, start.cache_misses),
+            branch_misses: delta(self.branch_misses, start.branch_misses),
+            context_switches: delta(self.context_switches, start.context_switches),
+            page_faults: delta(self.page_faults, start.page_faults),
+        }
+    }
+}
+
+impl BenchmarkIteration {
+    /// Returns the wall time of the iteration.
+    #[must_use]
+    pub fn duration(&self) -> Duration {
+        self.end_time.saturating_sub(self.start_time)
+    }
+
+    /// Returns whether the `timestamp` falls within the iteration.
+    #[must_use]
+    pub fn contains(&self, timestamp: Duration) -> bool {
+        timestamp >= self.start_time && timestamp <= self.end_time
+    }
+}
+
+impl BuildStatus {
+    /// Describes how the build exited, e.g. `exit code 1`.
+    #[must_use]
+    pub fn describe(&self) -> String {
+        if self.timed_out {
+            return "timed out".to_string();
+        }
+
+        match self.exit_code {
+            Some(exit_code) => format!("exit code {exit_code}"),
+            None => "terminated by a signal".to_string(),
+        }
+    }
+}
+
+impl BuildLog {
+    /// The maximum number of lines kept in a log.
+    pub const MAX_LINES: usize = 200;
+
+    /// The maximum number of characters kept of a single line.
+    pub const MAX_LINE_LENGTH: usize = 1024;
+
+    /// Appends a line to the log, dropping the oldest line once the log is full.
+    pub fn push(&mut self, line: &str) {
+        if self.lines.len() == Self::MAX_LINES {
+            self.lines.pop_front();
+            self.dropped += 1;
+        }
+
+        self.lines
+            .push_back(line.chars().take(Self::MAX_LINE_LENGTH).collect());
+    }
 }
 
 impl BenchmarkFrame {
-    /// The minimum duration of a performance frame.
-    pub const MINIMUM_DURATION: Duration = Duration::from_millis(100);
+    /// The default interval between performance frames.
+    pub const DEFAULT_SAMPLING_INTERVAL: Duration = Duration::from_millis(100);
+
+    /// The shortest supported interval between performance frames.
+    pub const MINIMUM_SAMPLING_INTERVAL: Duration = Duration::from_millis(1);
 }
 
 impl Benchmark {
@@ -177,11 +632,93 @@ impl Benchmark {
             frames: Arc::new(Mutex::new(Vec::new())),
             asm_information: None,
             hyperfine: None,
+            iterations: vec![],
+            aggregate: None,
+            events: vec![],
+            warnings: vec![],
+            status: None,
+            cpus: vec![],
+            mode: None,
+            workload: Workload::default(),
+            tags: vec![],
+        }
+    }
+
+    /// Returns the path of the benchmark, followed by its workload unless it is the default.
+    pub(crate) fn display_path(&self) -> String {
+        if self.workload.is_default() {
+            self.path.display().to_string()
+        } else {
+            format!("{} [{}]", self.path.display(), self.workload.name)
         }
     }
 
+    /// Returns the stem of the flamegraph files of the benchmark, unique for each workload.
+    pub(crate) fn file_stem(&self) -> String {
+        if self.workload.is_default() {
+            self.name.clone()
+        } else {
+            format!("{}-{}", self.name, self.workload.name)
+        }
+    }
+
+    /// Returns whether a build of the benchmark failed.
+    ///
+    /// Benchmarks recorded before build statuses were tracked are treated as successful.
+    #[must_use]
+    pub(crate) fn failed(&self) -> bool {
+        self.status.as_ref().is_some_and(|status| !status.success)
+    }
+
+    /// Returns the measured iterations of the benchmark.
+    ///
+    /// Benchmarks recorded before iterations were tracked are treated as a single iteration.
+    pub(crate) fn iteration_windows(&self) -> Vec<BenchmarkIteration> {
+        if !self.iterations.is_empty() {
+            return self.iterations.clone();
+        }
+
+        match (self.start_time, self.end_time) {
+            (Some(start_time), Some(end_time)) => vec![BenchmarkIteration {
+                start_time,
+                end_time,
+                counters: None,
+            }],
+            _ => vec![],
+        }
+    }
+
+    /// Returns the frames captured during each measured iteration of the benchmark.
+    ///
+    /// # Panics
+    ///
+    /// If the lock on the frames fails.
+    pub(crate) fn iteration_frames(&self) -> Vec<Vec<BenchmarkFrame>> {
+        let frames = self.frames.lock().expect("Failed to get lock for frames");
+
+        // Benchmarks recorded before iterations were tracked keep all of their frames
+        if self.iterations.is_empty() {
+            return vec![frames.clone()];
+        }
+
+        self.iterations
+            .iter()
+            .map(|iteration| {
+                frames
+                    .iter()
+                    .filter(|frame| iteration.contains(frame.timestamp))
+                    .cloned()
+                    .collect()
+            })
+            .collect()
+    }
+
     /// Runs the benchmark.
     ///
+    /// The project is built `options.warmup` times without being measured, followed by
+    /// `options.iterations` measured builds whose frames and phases are all kept. Every build is
+    /// prepared according to `options.mode`.
+    ///
     /// # Arguments
     ///
     /// * `epoch` - The epoch time of the benchmark.
@@ -189,6 +726,10 @@ impl Benchmark {
     /// # Errors
     ///
     /// If the benchmark's path is not a directory.
+    ///
+    /// If the project cannot be prepared for a build.
+    ///
+    /// If a warmup or measured build of the benchmark fails to run.
     pub(crate) fn run(
         &mut self,
         epoch: &Instant,
@@ -202,198 +743,791 @@ impl Benchmark {
             self.path.display()
         );
 
-        // Set the start time of the benchmark
-        self.start_time = Some(epoch.elapsed());
-
         let forc_path = std::fs::canonicalize(&options.forc_path).map_err(|e| wrap!(e.into()))?;
 
-        // Spawn the `forc build` child command in the benchmark's directory
-        // NOTE: stdin and stdout are piped so that we can use them to signal individual phases
-        let mut command = Command::new(forc_path)
-            .arg("build")
-            .arg("--profile")
-            .arg("--log-level")
-            .arg("5")
-            .stdin(Stdio::piped())
-            .stdout(Stdio::piped())
-            .current_dir(self.path.clone())
-            .spawn()
-            .map_err(|e| wrap!(e.into()))?;
+        self.mode = Some(options.mode);
+        let touched = self
+            .touched_source(options.mode, options.touch.as_deref())
+            .map_err(|e| wrap!(e))?;
 
-        // Create an unbounded channel to send/receive line strings between the readline thread and the main thread
-        let (readline_tx, readline_rx) = unbounded();
+        let deadline = options.deadline(epoch, &self.path);
 
-        // Create an unbounded channel to send/receive STOP signals between the readline thread and the main thread
-        let (stop_readline_tx, stop_readline_rx) = unbounded();
+        // The total timeout of the run may already have run out
+        if deadline.is_some_and(|deadline| Instant::now() >= deadline) {
+            self.time_out();
+            return Ok(());
+        }
 
-        // Get the pid of the spawned child command's process
-        let pid = Pid::from_u32(command.id());
+        for _ in 0..options.mode.warmups(options.warmup) {
+            self.prepare(touched.as_deref()).map_err(|e| wrap!(e))?;
 
-        // Create a channel to send/receive STOP signals between the perf thread and the main thread
-        let (stop_perf_tx, stop_perf_rx) = unbounded();
+            self.run_warmup(&forc_path, deadline, &options.environment)
+                .map_err(|e| wrap!(e))?;
 
-        let phase_epoch = Instant::now();
-        Self::spawn_perf_thread(
-            epoch,
-            &phase_epoch,
-            pid,
-            stop_perf_rx,
-            stop_readline_rx.clone(),
-            self.frames.clone(),
-        );
+            if self.failed() {
+                break;
+            }
+        }
 
-        // Spawn a thread to read lines from the command's stdout without blocking the main thread
-        Self::spawn_readline_thread(&mut command, stop_readline_rx, readline_tx)
-            .map_err(|e| wrap!(e))?;
+        for _ in 0..options.iterations.max(1) {
+            // The benchmark may have failed or timed out during its warmup
+            if self.failed() {
+                break;
+            }
 
-        #[cfg(target_os = "linux")]
-        let mut perf_process = if options.flamegraph {
-            Some(
-                Command::new("perf")
-                    .arg("record")
-                    .arg("--call-graph")
-                    .arg("dwarf")
-                    .arg("-p")
-                    .arg(pid.to_string())
-                    .spawn()
-                    .map_err(|e| wrap!(e.into()))?,
-            )
-        } else {
-            None
-        };
+            self.prepare(touched.as_deref()).map_err(|e| wrap!(e))?;
 
-        #[cfg(target_os = "macos")]
-        // Create a channel to signal the sampling thread to stop
-        let (sample_stop_tx, sample_stop_rx): (Sender<()>, Receiver<()>) = unbounded();
+            let iteration = self
+                .run_iteration(
+                    epoch,
+                    &forc_path,
+                    options.sampling_interval,
+                    options.counters,
+                    deadline,
+                    &options.environment,
+                )
+                .map_err(|e| wrap!(e))?;
 
-        #[cfg(target_os = "macos")]
-        let sample_output = if options.flamegraph {
-            println!("Starting sample command for flamegraph generation");
-            Some(std::thread::spawn(move || {
-                let sample_output = Self::run_sample(pid.as_u32()).map_err(|e| wrap!(e)).ok();
-                sample_stop_rx.recv().ok();
-                sample_output
-            }))
-        } else {
-            None
-        };
+            self.push_iteration(iteration);
 
-        // Collect frames for each phase of the command
-        self.wait(
-            epoch,
-            &mut command,
-            &stop_readline_tx,
-            &stop_perf_tx,
-            &readline_rx,
-        )
-        .map_err(|e| wrap!(e))?;
+            // The remaining builds would fail the same way, so the failed build is the last one
+            if self.failed() {
+                break;
+            }
+        }
 
-        #[cfg(target_os = "macos")]
-        // Signal the sampling thread to stop
-        let _ = sample_stop_tx.send(());
+        // Profiling skews the build, so the flamegraph is recorded in an extra unmeasured build
+        if options.flamegraph && !self.failed() {
+            self.prepare(touched.as_deref()).map_err(|e| wrap!(e))?;
+            self.record_flamegraph(&forc_path, deadline, &options.environment, exec_path)
+                .map_err(|e| wrap!(e))?;
+        }
 
-        // Set the end time of the benchmark
-        self.end_time = Some(epoch.elapsed());
+        self.aggregate = Some(crate::stats::aggregate(self));
 
-        let flamegraph_folder = exec_path
-            .strip_suffix(".json")
-            .ok_or_else(|| wrap!("Failed to strip suffix".into()))?;
+        Ok(())
+    }
 
-        let flamegraph_folder = flamegraph_folder.replace(
-            crate::BENCHMARKS_RUN_FOLDER,
-            crate::BENCHMARKS_FLAMEGRAPH_FOLDER,
+    /// Runs the benchmark interleaved with the same benchmark built by a baseline forc binary.
+    ///
+    /// The measured builds of both binaries alternate in an ABBA order, so that thermal and
+    /// cache drift during the run affects both binaries equally.
+    ///
+    /// # Arguments
+    ///
+    /// * `baseline` - The benchmark of the same project, built by the baseline binary.
+    ///
+    /// * `epoch` - The epoch time of the benchmark.
+    ///
+    /// * `baseline_forc_path` - The path to the baseline forc binary.
+    ///
+    /// * `baseline_exec_path` - The run path of the baseline benchmarks.
+    ///
+    /// # Errors
+    ///
+    /// If the benchmark's path is not a directory.
+    ///
+    /// If the project cannot be prepared for a build.
+    ///
+    /// If a warmup or measured build of either binary fails to run.
+    pub(crate) fn run_interleaved(
+        &mut self,
+        baseline: &mut Benchmark,
+        epoch: &Instant,
+        options: &crate::cli::Options,
+        baseline_forc_path: &std::path::Path,
+        exec_path: &str,
+        baseline_exec_path: &str,
+    ) -> crate::error::Result<()> {
+        // Ensure the benchmark's path is a directory we can run `forc build` in
+        assert!(
+            self.verify_path(),
+            "Project directory \"{}\" does not contain a Toml file.",
+            self.path.display()
         );
 
-        let flamegraph_folder = std::path::Path::new(&flamegraph_folder);
+        let forc_path = std::fs::canonicalize(&options.forc_path).map_err(|e| wrap!(e.into()))?;
+        let baseline_forc_path =
+            std::fs::canonicalize(baseline_forc_path).map_err(|e| wrap!(e.into()))?;
 
-        if !flamegraph_folder.exists() {
-            // Create the flamegraph folder
-            std::fs::create_dir(flamegraph_folder).map_err(|e| wrap!(e.into()))?;
-        }
+        self.mode = Some(options.mode);
+        baseline.mode = Some(options.mode);
+        let touched = self
+            .touched_source(options.mode, options.touch.as_deref())
+            .map_err(|e| wrap!(e))?;
 
-        #[cfg(target_os = "macos")]
-        if let Some(sample_output) = sample_output {
-            if let Ok(Some(sample_output)) = sample_output.join() {
-                // Collapse the sample output
-                let mut collapsed = Vec::new();
-                let mut folder = Folder::default();
-                let reader = BufReader::new(&sample_output[..]);
-                let writer = BufWriter::new(&mut collapsed);
+        // Both binaries build within the same deadline, so a hang in either one stops both
+        let deadline = options.deadline(epoch, &self.path);
 
-                folder
-                    .collapse(reader, writer)
-                    .map_err(|e| wrap!(e.into()))?;
+        // The total timeout of the run may already have run out
+        if deadline.is_some_and(|deadline| Instant::now() >= deadline) {
+            baseline.time_out();
+            self.time_out();
+            return Ok(());
+        }
 
-                let file_name = format!("{}.svg", self.name);
+        for _ in 0..options.mode.warmups(options.warmup) {
+            baseline.prepare(touched.as_deref()).map_err(|e| wrap!(e))?;
 
-                // Create the flamegraph folder
-                let output_file_path = flamegraph_folder.join(file_name);
+            baseline
+                .run_warmup(&baseline_forc_path, deadline, &options.environment)
+                .map_err(|e| wrap!(e))?;
 
-                let output_file =
-                    std::fs::File::create(&output_file_path).map_err(|e| wrap!(e.into()))?;
+            if baseline.failed() {
+                break;
+            }
 
-                let mut writer = BufWriter::new(output_file);
-                let reader = BufReader::new(&collapsed[..]);
+            self.prepare(touched.as_deref()).map_err(|e| wrap!(e))?;
 
-                from_reader(
-                    &mut inferno::flamegraph::Options::default(),
-                    reader,
-                    &mut writer,
-                )
-                .map_err(|e| wrap!(e.into()))?;
+            self.run_warmup(&forc_path, deadline, &options.environment)
+                .map_err(|e| wrap!(e))?;
 
-                println!("Flamegraph generated at {}", output_file_path.display());
+            if self.failed() {
+                break;
             }
         }
 
-        #[cfg(target_os = "linux")]
-        if let Some(mut perf) = perf_process.take() {
-            let _ = perf.wait();
-
-            let perf_script_output = {
-                let out = Command::new("perf")
-                    .arg("script")
-                    .stdout(Stdio::piped())
-                    .spawn()
-                    .map_err(|e| wrap!(e.into()))?
-                    .wait_with_output()
-                    .map_err(|e| wrap!(e.into()))?;
-
-                if !out.status.success() {
-                    return Err(Box::new(wrap!("perf script failed".into())));
-                }
-                out
-            };
-
-            // Collapse the perf script output
-            let mut collapsed = Vec::new();
-            let mut folder = Folder::default();
-            let reader = BufReader::new(&perf_script_output.stdout[..]);
-            let writer = BufWriter::new(&mut collapsed);
+        for iteration in 0..options.iterations.max(1) {
+            // Either benchmark may have failed or timed out during its warmup
+            if self.failed() || baseline.failed() {
+                break;
+            }
 
-            folder
-                .collapse(reader, writer)
-                .map_err(|e| wrap!(e.into()))?;
+            // Alternate which binary builds first: baseline, current, current, baseline, ...
+            let baseline_first = iteration % 2 == 0;
+
+            if baseline_first {
+                baseline.prepare(touched.as_deref()).map_err(|e| wrap!(e))?;
+
+                let baseline_iteration = baseline
+                    .run_iteration(
+                        epoch,
+                        &baseline_forc_path,
+                        options.sampling_interval,
+                        options.counters,
+                        deadline,
+                        &options.environment,
+                    )
+                    .map_err(|e| wrap!(e))?;
+                baseline.push_iteration(baseline_iteration);
+            }
+
+            self.prepare(touched.as_deref()).map_err(|e| wrap!(e))?;
+
+            let current_iteration = self
+                .run_iteration(
+                    epoch,
+                    &forc_path,
+                    options.sampling_interval,
+                    options.counters,
+                    deadline,
+                    &options.environment,
+                )
+                .map_err(|e| wrap!(e))?;
+            self.push_iteration(current_iteration);
+
+            if !baseline_first {
+                baseline.prepare(touched.as_deref()).map_err(|e| wrap!(e))?;
+
+                let baseline_iteration = baseline
+                    .run_iteration(
+                        epoch,
+                        &baseline_forc_path,
+                        options.sampling_interval,
+                        options.counters,
+                        deadline,
+                        &options.environment,
+                    )
+                    .map_err(|e| wrap!(e))?;
+                baseline.push_iteration(baseline_iteration);
+            }
+
+            // The remaining builds would fail the same way, so the failed build is the last one
+            if self.failed() || baseline.failed() {
+                break;
+            }
+        }
+
+        // Profiling skews the builds, so the flamegraphs are recorded in extra unmeasured builds
+        if options.flamegraph && !self.failed() && !baseline.failed() {
+            baseline.prepare(touched.as_deref()).map_err(|e| wrap!(e))?;
+            baseline
+                .record_flamegraph(
+                    &baseline_forc_path,
+                    deadline,
+                    &options.environment,
+                    baseline_exec_path,
+                )
+                .map_err(|e| wrap!(e))?;
+
+            self.prepare(touched.as_deref()).map_err(|e| wrap!(e))?;
+            self.record_flamegraph(&forc_path, deadline, &options.environment, exec_path)
+                .map_err(|e| wrap!(e))?;
+        }
+
+        self.aggregate = Some(crate::stats::aggregate(self));
+        baseline.aggregate = Some(crate::stats::aggregate(baseline));
+
+        Ok(())
+    }
+
+    /// Gets the source file touched before each build of an incremental benchmark.
+    ///
+    /// # Arguments
+    ///
+    /// * `mode` - The build mode of the benchmark.
+    ///
+    /// * `touch` - The source file to touch, relative to the project, or `None` for the entry
+    ///   point of the project, `src/main.sw` or `src/lib.sw`.
+    ///
+    /// # Returns
+    ///
+    /// The path of the source file, or `None` if the mode is not incremental.
+    ///
+    /// # Errors
+    ///
+    /// If the source file does not exist.
+    fn touched_source(
+        &self,
+        mode: BuildMode,
+        touch: Option<&std::path::Path>,
+    ) -> crate::error::Result<Option<PathBuf>> {
+        if mode != BuildMode::Incremental {
+            return Ok(None);
+        }
+
+        let candidates = match touch {
+            Some(touch) => vec![self.path.join(touch)],
+            None => vec![self.path.join("src/main.sw"), self.path.join("src/lib.sw")],
+        };
+
+        let Some(path) = candidates.into_iter().find(|path| path.is_file()) else {
+            return Err(Box::new(wrap!(format!(
+                "No source file to touch in \"{}\", pass --touch",
+                self.path.display()
+            )
+            .into())));
+        };
+
+        Ok(Some(path))
+    }
+
+    /// Prepares the project for a build according to the build mode of the benchmark.
+    ///
+    /// Cold builds start without the `out` folder of the project and of any member of its
+    /// workspace, and incremental builds start right after the `touched` source file changed.
+    ///
+    /// # Errors
+    ///
+    /// If the build outputs cannot be removed or the source file cannot be touched.
+    fn prepare(&self, touched: Option<&std::path::Path>) -> crate::error::Result<()> {
+        match self.mode {
+            Some(BuildMode::Cold) => {
+                let manifests = walkdir::WalkDir::new(&self.path)
+                    .into_iter()
+                    .filter_entry(|entry| entry.file_name() != "out")
+                    .filter_map(std::result::Result::ok)
+                    .filter(|entry| {
+                        entry.file_type().is_file() && entry.file_name() == "Forc.toml"
+                    });
+
+                for manifest in manifests {
+                    let Some(project) = manifest.path().parent() else {
+                        continue;
+                    };
+
+                    let out = project.join("out");
+                    if out.exists() {
+                        std::fs::remove_dir_all(&out).map_err(|e| wrap!(e.into()))?;
+                    }
+                }
+            }
+            Some(BuildMode::Incremental) => {
+                if let Some(touched) = touched {
+                    std::fs::OpenOptions::new()
+                        .append(true)
+                        .open(touched)
+                        .and_then(|file| file.set_modified(std::time::SystemTime::now()))
+                        .map_err(|e| wrap!(e.into()))?;
+                }
+            }
+            Some(BuildMode::Warm) | None => {}
+        }
+
+        Ok(())
+    }
+
+    /// Records a measured iteration, extending the start and end times of the benchmark.
+    fn push_iteration(&mut self, iteration: BenchmarkIteration) {
+        if self.start_time.is_none() {
+            self.start_time = Some(iteration.start_time);
+        }
+        self.end_time = Some(iteration.end_time);
+        self.iterations.push(iteration);
+    }
+
+    /// Runs a single unmeasured build of the benchmark to warm up the system.
+    ///
+    /// The measured builds would start from the outputs of a broken build, so a warmup build
+    /// that fails or runs past the `deadline` marks the benchmark as failed, like a measured
+    /// build does. The latest lines of its stderr are kept with the status of the benchmark.
+    fn run_warmup(
+        &mut self,
+        forc_path: &std::path::Path,
+        deadline: Option<Instant>,
+        controls: &crate::environment::Controls,
+    ) -> crate::error::Result<()> {
+        let mut build = Command::new(forc_path);
+        controls.apply(&mut build, &self.cpus);
+
+        let mut command = build
+            .args(&self.workload.args)
+            .envs(&self.workload.env)
+            .stdin(Stdio::null())
+            .stdout(Stdio::null())
+            .stderr(Stdio::piped())
+            .current_dir(self.path.clone())
+            .spawn()
+            .map_err(|e| wrap!(e.into()))?;
+
+        let stderr = Arc::new(Mutex::new(BuildLog::default()));
+        let (stderr_thread, stderr_done) =
+            Self::spawn_stderr_thread(&mut command, stderr.clone()).map_err(|e| wrap!(e))?;
+
+        let pid = command.id();
+        let exit_rx = Self::spawn_exit_thread(command);
+
+        let exit_status = match deadline {
+            Some(deadline) => exit_rx.recv_deadline(deadline).ok(),
+            None => exit_rx.recv().ok(),
+        };
+
+        let Some(exit_status) = exit_status else {
+            Self::kill_process_tree(pid);
+            let _ = exit_rx.recv();
+            self.time_out();
+            return Ok(());
+        };
+
+        let exit_status = exit_status.map_err(|e| wrap!(e.into()))?;
+
+        if !exit_status.success() {
+            let status = BuildStatus {
+                success: false,
+                exit_code: exit_status.code(),
+                timed_out: false,
+                stdout: BuildLog::default(),
+                stderr: Self::join_stderr_thread(stderr_thread, &stderr_done, &stderr),
+            };
+
+            self.warn(format!(
+                "The warmup build failed with {}, so the benchmark is left out of the comparison",
+                status.describe()
+            ));
+
+            self.status = Some(status);
+        }
+
+        Ok(())
+    }
+
+    /// Spawns a thread that waits for the command to exit and sends its exit status.
+    ///
+    /// Waiting on a thread lets the main thread block on the exit along with its other
+    /// channels, instead of polling the command.
+    fn spawn_exit_thread(mut command: Child) -> Receiver<std::io::Result<ExitStatus>> {
+        let (exit_tx, exit_rx) = crossbeam_channel::bounded(1);
+
+        std::thread::spawn(move || {
+            let _ = exit_tx.send(command.wait());
+        });
+
+        exit_rx
+    }
+
+    /// Marks the benchmark as timed out before its builds completed.
+    fn time_out(&mut self) {
+        self.status = Some(BuildStatus {
+            timed_out: true,
+            ..Default::default()
+        });
+
+        self.warn(
+            "The builds ran past the timeout, so the benchmark is left out of the comparison"
+                .to_string(),
+        );
+    }
+
+    /// Kills the process with the given `pid` along with every process it spawned.
+    fn kill_process_tree(pid: u32) {
+        #[cfg(target_os = "linux")]
+        crate::procfs::kill_tree(pid);
+
+        #[cfg(not(target_os = "linux"))]
+        {
+            let mut system = sysinfo::System::new();
+            system.refresh_processes(sysinfo::ProcessesToUpdate::All, true);
+
+            // Kill the root first, so that it does not spawn new children while they are killed
+            let root = Pid::from_u32(pid);
+            let mut parents = vec![root];
+
+            while let Some(parent) = parents.pop() {
+                if let Some(process) = system.process(parent) {
+                    process.kill();
+                }
+
+                for (pid, process) in system.processes() {
+                    if process.parent() == Some(parent) {
+                        parents.push(*pid);
+                    }
+                }
+            }
+        }
+    }
+
+    /// Runs a single measured build of the benchmark.
+    ///
+    /// # Arguments
+    ///
+    /// * `epoch` - The epoch time of the benchmark.
+    ///
+    /// * `forc_path` - The canonical path to the forc binary.
+    ///
+    /// * `sampling_interval` - The interval between performance frames.
+    ///
+    /// * `counters` - Whether to collect the performance counters of the build and its phases.
+    ///
+    /// * `deadline` - The time after which the build is killed and marked as timed out.
+    ///
+    /// * `controls` - The noise reduction controls applied to the build.
+    ///
+    /// # Errors
+    ///
+    /// If the `forc build` command fails to spawn.
+    fn run_iteration(
+        &mut self,
+        epoch: &Instant,
+        forc_path: &std::path::Path,
+        sampling_interval: Duration,
+        counters: bool,
+        deadline: Option<Instant>,
+        controls: &crate::environment::Controls,
+    ) -> crate::error::Result<BenchmarkIteration> {
+        let mut build = Command::new(forc_path);
+
+        // The controls are inherited by every process that forc spawns
+        controls.apply(&mut build, &self.cpus);
+
+        // Listen for the messages of the phase protocol, falling back to the stdout markers alone
+        let listener = match crate::protocol::Listener::bind(epoch) {
+            Ok(listener) => {
+                build.env(crate::protocol::SOCKET_ENV_VAR, listener.path());
+                Some(listener)
+            }
+            Err(e) => {
+                eprintln!("Failed to listen for phase messages: {e}");
+                None
+            }
+        };
+
+        // Open the counters before forc executes, so that its startup is counted as well
+        let counters = if counters {
+            match crate::counters::Counters::prepare(&mut build) {
+                Ok(counters) => Some(counters),
+                Err(e) => {
+                    eprintln!("Performance counters are unavailable: {e}");
+                    None
+                }
+            }
+        } else {
+            None
+        };
+
+        // Set the start time of the iteration
+        let start_time = epoch.elapsed();
+
+        // Spawn the forc command of the workload in the benchmark's directory
+        // NOTE: stdin and stdout are piped so that we can use them to signal individual phases,
+        // and stderr is piped to keep the latest lines of it with the status of the build
+        let mut command = build
+            .args(&self.workload.args)
+            .envs(&self.workload.env)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .current_dir(self.path.clone())
+            .spawn()
+            .map_err(|e| wrap!(e.into()))?;
+
+        // Create an unbounded channel to send/receive line strings between the readline thread and the main thread
+        let (readline_tx, readline_rx) = unbounded();
 
-            let file_name = format!("{}.svg", self.name,);
+        // Create an unbounded channel to send/receive STOP signals between the readline thread and the main thread
+        let (stop_readline_tx, stop_readline_rx) = unbounded();
 
-            // Create the flamegraph folder
-            let output_file_path = flamegraph_folder.join(file_name);
+        // Get the pid of the spawned child command's process
+        let pid = Pid::from_u32(command.id());
+
+        // Create a channel to send/receive STOP signals between the perf thread and the main thread
+        let (stop_perf_tx, stop_perf_rx) = unbounded();
+
+        let counters = counters.and_then(|counters| match counters.open() {
+            Ok(counters) => Some(counters),
+            Err(e) => {
+                eprintln!("Performance counters are unavailable: {e}");
+                None
+            }
+        });
+
+        let phase_epoch = Instant::now();
+        Self::spawn_perf_thread(
+            epoch,
+            &phase_epoch,
+            pid,
+            stop_perf_rx,
+            stop_readline_rx.clone(),
+            self.frames.clone(),
+            sampling_interval,
+        );
 
-            let output_file =
-                std::fs::File::create(&output_file_path).map_err(|e| wrap!(e.into()))?;
+        // Spawn a thread to read lines from the command's stdout without blocking the main thread
+        Self::spawn_readline_thread(&mut command, stop_readline_rx, readline_tx)
+            .map_err(|e| wrap!(e))?;
 
-            let mut writer = BufWriter::new(output_file);
-            let reader = BufReader::new(&collapsed[..]);
+        // Spawn a thread to keep the latest lines of the command's stderr
+        let stderr = Arc::new(Mutex::new(BuildLog::default()));
+        let (stderr_thread, stderr_done) =
+            Self::spawn_stderr_thread(&mut command, stderr.clone()).map_err(|e| wrap!(e))?;
 
-            from_reader(
-                &mut inferno::flamegraph::Options::default(),
-                reader,
-                &mut writer,
+        // Collect frames for each phase of the command
+        let mut stdout = BuildLog::default();
+        let (exit_status, timed_out) = self
+            .wait(
+                epoch,
+                command,
+                &stop_readline_tx,
+                &stop_perf_tx,
+                &readline_rx,
+                listener,
+                counters.as_ref(),
+                deadline,
+                &mut stdout,
             )
+            .map_err(|e| wrap!(e))?;
+
+        let status = BuildStatus {
+            success: exit_status.success() && !timed_out,
+            exit_code: exit_status.code(),
+            timed_out,
+            stdout,
+            stderr: Self::join_stderr_thread(stderr_thread, &stderr_done, &stderr),
+        };
+
+        if timed_out {
+            self.warn(
+                "The build ran past the timeout and was killed, so the benchmark is left out of the comparison"
+                    .to_string(),
+            );
+        } else if !status.success {
+            self.warn(format!(
+                "The build failed with {}, so the benchmark is left out of the comparison",
+                status.describe()
+            ));
+        }
+
+        self.status = Some(status);
+
+        // Set the end time of the iteration
+        let end_time = epoch.elapsed();
+
+        Ok(BenchmarkIteration {
+            start_time,
+            end_time,
+            counters: counters.as_ref().map(crate::counters::Counters::read),
+        })
+    }
+
+    /// Records a flamegraph of the benchmark in an unmeasured build.
+    ///
+    /// Profiling slows the build down and changes its frames and phases, so the profiled build
+    /// is kept out of the measured iterations. A build that runs past the `deadline` is killed
+    /// without a flamegraph, and without marking the benchmark as timed out.
+    ///
+    /// # Arguments
+    ///
+    /// * `forc_path` - The canonical path to the forc binary.
+    ///
+    /// * `deadline` - The time after which the build is killed.
+    ///
+    /// * `controls` - The noise reduction controls applied to the build.
+    ///
+    /// * `exec_path` - The run path of the benchmarks, from which the flamegraph folder is named.
+    ///
+    /// # Errors
+    ///
+    /// If the build fails to spawn.
+    ///
+    /// If the flamegraph generation fails.
+    fn record_flamegraph(
+        &mut self,
+        forc_path: &std::path::Path,
+        deadline: Option<Instant>,
+        controls: &crate::environment::Controls,
+        exec_path: &str,
+    ) -> crate::error::Result<()> {
+        let flamegraph_folder = exec_path
+            .strip_suffix(".json")
+            .ok_or_else(|| wrap!("Failed to strip suffix".into()))?;
+
+        let flamegraph_folder = flamegraph_folder.replace(
+            crate::BENCHMARKS_RUN_FOLDER,
+            crate::BENCHMARKS_FLAMEGRAPH_FOLDER,
+        );
+
+        let flamegraph_folder = std::path::Path::new(&flamegraph_folder);
+
+        if !flamegraph_folder.exists() {
+            // Create the flamegraph folder, which a concurrent benchmark may be creating as well
+            std::fs::create_dir_all(flamegraph_folder).map_err(|e| wrap!(e.into()))?;
+        }
+
+        // The perf data is written from the benchmark's directory, so its path must be absolute
+        #[cfg(target_os = "linux")]
+        let perf_data = std::fs::canonicalize(flamegraph_folder)
+            .map_err(|e| wrap!(e.into()))?
+            .join(format!("{}.perf.data", self.file_stem()));
+
+        // `perf record` follows every process that forc spawns
+        #[cfg(target_os = "linux")]
+        let mut build = {
+            let mut build = Command::new("perf");
+            build
+                .arg("record")
+                .arg("--call-graph")
+                .arg("dwarf")
+                .arg("--output")
+                .arg(&perf_data)
+                .arg("--")
+                .arg(forc_path);
+            build
+        };
+
+        #[cfg(not(target_os = "linux"))]
+        let mut build = Command::new(forc_path);
+
+        // The controls are inherited by `perf` and every process that forc spawns
+        controls.apply(&mut build, &self.cpus);
+
+        let command = build
+            .args(&self.workload.args)
+            .envs(&self.workload.env)
+            .stdin(Stdio::null())
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .current_dir(self.path.clone())
+            .spawn()
             .map_err(|e| wrap!(e.into()))?;
+
+        let pid = command.id();
+
+        #[cfg(target_os = "macos")]
+        let sample_output = {
+            println!("Starting sample command for flamegraph generation");
+            std::thread::spawn(move || Self::run_sample(pid).map_err(|e| wrap!(e)).ok())
+        };
+
+        let exit_rx = Self::spawn_exit_thread(command);
+
+        let exited = match deadline {
+            Some(deadline) => exit_rx.recv_deadline(deadline).is_ok(),
+            None => exit_rx.recv().is_ok(),
+        };
+
+        if !exited {
+            Self::kill_process_tree(pid);
+            let _ = exit_rx.recv();
+
+            #[cfg(target_os = "linux")]
+            let _ = std::fs::remove_file(&perf_data);
+
+            self.warn(
+                "The flamegraph build ran past the timeout and was killed, so no flamegraph was recorded"
+                    .to_string(),
+            );
+
+            return Ok(());
+        }
+
+        #[cfg(target_os = "macos")]
+        if let Ok(Some(sample_output)) = sample_output.join() {
+            self.write_flamegraph(flamegraph_folder, &sample_output)
+                .map_err(|e| wrap!(e))?;
         }
 
+        #[cfg(target_os = "linux")]
+        {
+            let perf_script_output = Command::new("perf")
+                .arg("script")
+                .arg("--input")
+                .arg(&perf_data)
+                .stdout(Stdio::piped())
+                .spawn()
+                .map_err(|e| wrap!(e.into()))?
+                .wait_with_output()
+                .map_err(|e| wrap!(e.into()))?;
+
+            if !perf_script_output.status.success() {
+                return Err(Box::new(wrap!("perf script failed".into())));
+            }
+
+            self.write_flamegraph(flamegraph_folder, &perf_script_output.stdout)
+                .map_err(|e| wrap!(e))?;
+
+            std::fs::remove_file(&perf_data).map_err(|e| wrap!(e.into()))?;
+        }
+
+        Ok(())
+    }
+
+    /// Collapses the stacks recorded by the profiler and renders them as the flamegraph of the
+    /// benchmark.
+    ///
+    /// # Errors
+    ///
+    /// If the stacks cannot be collapsed, or the flamegraph cannot be written.
+    #[cfg(any(target_os = "linux", target_os = "macos"))]
+    fn write_flamegraph(
+        &self,
+        flamegraph_folder: &std::path::Path,
+        stacks: &[u8],
+    ) -> crate::error::Result<()> {
+        // Collapse the profiler output
+        let mut collapsed = Vec::new();
+        let mut folder = Folder::default();
+        let reader = BufReader::new(stacks);
+        let writer = BufWriter::new(&mut collapsed);
+
+        folder
+            .collapse(reader, writer)
+            .map_err(|e| wrap!(e.into()))?;
+
+        let output_file_path = flamegraph_folder.join(format!("{}.svg", self.file_stem()));
+
+        let output_file = std::fs::File::create(&output_file_path).map_err(|e| wrap!(e.into()))?;
+
+        let mut writer = BufWriter::new(output_file);
+        let reader = BufReader::new(&collapsed[..]);
+
+        from_reader(
+            &mut inferno::flamegraph::Options::default(),
+            reader,
+            &mut writer,
+        )
+        .map_err(|e| wrap!(e.into()))?;
+
+        println!("Flamegraph generated at {}", output_file_path.display());
+
         Ok(())
     }
 
@@ -465,7 +1599,12 @@ impl Benchmark {
             let command_stdout = std::io::BufReader::new(command_stdout);
 
             for line in command_stdout.lines() {
-                let line = line.expect("Failed to get line").trim_end().to_string();
+                // Lines that are not valid UTF-8 cannot be markers
+                let line = match line {
+                    Ok(line) => line.trim_end().to_string(),
+                    Err(e) if e.kind() == std::io::ErrorKind::InvalidData => continue,
+                    Err(_) => break,
+                };
 
                 // Attempt to send the line to the main thread, or stop looping and allow
                 // the readline thread to exit if it fails
@@ -482,80 +1621,440 @@ impl Benchmark {
         Ok(())
     }
 
+    /// Spawns a thread to keep the latest lines of the command's stderr in `stderr`.
+    ///
+    /// Returns the handle of the thread, and a channel that receives once the thread has read
+    /// the whole stderr.
+    fn spawn_stderr_thread(
+        command: &mut Child,
+        stderr: Arc<Mutex<BuildLog>>,
+    ) -> crate::error::Result<(JoinHandle<()>, Receiver<()>)> {
+        let command_stderr = command.stderr.take().ok_or(wrap!(
+            "Failed to take stderr for spawn_stderr_thread command".into()
+        ))?;
+
+        let (done_tx, done_rx) = unbounded();
+
+        let thread = std::thread::spawn(move || {
+            let mut command_stderr = std::io::BufReader::new(command_stderr);
+            let mut line = Vec::new();
+
+            // Errors are not always valid UTF-8, so the lines are read as bytes
+            while let Ok(read) = command_stderr.read_until(b'\n', &mut line) {
+                if read == 0 {
+                    break;
+                }
+
+                let Ok(mut stderr) = stderr.lock() else {
+                    break;
+                };
+
+                stderr.push(String::from_utf8_lossy(&line).trim_end());
+                line.clear();
+            }
+
+            let _ = done_tx.send(());
+        });
+
+        Ok((thread, done_rx))
+    }
+
+    /// Waits for the stderr thread to read the lines still in flight once the command has
+    /// exited, and returns the kept lines of the command's stderr.
+    ///
+    /// A leftover process can keep the stderr open after the command exited, so the thread is
+    /// only joined when it finishes within `OUTPUT_DRAIN_TIMEOUT`, and is left to end on its own
+    /// otherwise.
+    fn join_stderr_thread(
+        thread: JoinHandle<()>,
+        done: &Receiver<()>,
+        stderr: &Mutex<BuildLog>,
+    ) -> BuildLog {
+        if done.recv_timeout(OUTPUT_DRAIN_TIMEOUT).is_ok() {
+            let _ = thread.join();
+        }
+
+        stderr
+            .lock()
+            .map(|stderr| stderr.clone())
+            .unwrap_or_default()
+    }
+
     /// Collects frames for each phase of the command.
+    ///
+    /// Phases are signalled either by the `/dyno` markers on the command's stdout, or by the
+    /// messages of the phase protocol received by the `listener`. When `counters` are given,
+    /// the counts between the start and the stop of each phase are recorded with the phase.
+    ///
+    /// Unbalanced or malformed markers and messages are recorded as warnings of the benchmark,
+    /// and the phases still running when the command exits are closed at its exit. The lines
+    /// of the command's stdout that are not markers are kept in `stdout`.
+    ///
+    /// If the command runs past the `deadline`, it is killed along with every process it
+    /// spawned, and the frames and phases collected until then are kept.
+    ///
+    /// The calling thread blocks on the exit of the command, its stdout lines, its phase
+    /// messages and the deadline at once, so waiting costs no CPU time between events and the
+    /// harness stays out of the measured CPU budget.
+    ///
+    /// Returns the exit status of the command, and whether it was killed at the `deadline`.
+    #[allow(clippy::too_many_arguments)]
     fn wait(
         &mut self,
         epoch: &Instant,
-        command: &mut Child,
+        command: Child,
         stop_readline_tx: &Sender<()>,
         stop_perf_tx: &Sender<()>,
         readline_rx: &Receiver<String>,
-    ) -> crate::error::Result<()> {
-        // Loop until the command has exited
-        loop {
-            // If the command has exited, tell the readline thread to stop and stop looping
-            if command.try_wait().map_err(|e| wrap!(e.into()))?.is_some() {
-                if stop_readline_tx.send(()).is_err() {
-                    break;
+        listener: Option<crate::protocol::Listener>,
+        counters: Option<&crate::counters::Counters>,
+        deadline: Option<Instant>,
+        stdout: &mut BuildLog,
+    ) -> crate::error::Result<(ExitStatus, bool)> {
+        // The counters at the start of each phase that has not stopped yet, by phase index
+        let mut phase_counters: Vec<(usize, HardwareCounters)> = vec![];
+
+        // The phases started through the phase protocol
+        let mut protocol_phases = ProtocolPhases::default();
+
+        let pid = command.id();
+        let exit_rx = Self::spawn_exit_thread(command);
+
+        // Closed channels are replaced by channels that never receive, so that they are not selected again
+        let mut lines_rx = readline_rx.clone();
+        let mut messages_rx = listener
+            .as_ref()
+            .map_or_else(crossbeam_channel::never, |listener| {
+                listener.receiver().clone()
+            });
+        let deadline_rx = deadline.map_or_else(crossbeam_channel::never, crossbeam_channel::at);
+
+        // Block until the command has exited, handling its lines and messages as they arrive
+        let (exit_status, timed_out) = loop {
+            crossbeam_channel::select! {
+                recv(exit_rx) -> exit_status => {
+                    let exit_status = exit_status
+                        .map_err(|e| wrap!(e.into()))?
+                        .map_err(|e| wrap!(e.into()))?;
+                    break (exit_status, false);
                 }
+                recv(lines_rx) -> line => match line {
+                    Ok(line) => self.handle_line(epoch, &line, &mut phase_counters, counters, stdout),
+                    Err(_) => lines_rx = crossbeam_channel::never(),
+                },
+                recv(messages_rx) -> received => match received {
+                    Ok(received) => self.handle_received(&mut protocol_phases, received, counters),
+                    Err(_) => messages_rx = crossbeam_channel::never(),
+                },
+                // If the command has run past the deadline, kill it and everything it spawned
+                recv(deadline_rx) -> _ => {
+                    Self::kill_process_tree(pid);
+
+                    let exit_status = exit_rx
+                        .recv()
+                        .map_err(|e| wrap!(e.into()))?
+                        .map_err(|e| wrap!(e.into()))?;
+                    break (exit_status, true);
+                }
+            }
+        };
 
-                if stop_perf_tx.send(()).is_err() {
-                    break;
+        let exit_time = epoch.elapsed();
+
+        // Tell the perf thread to stop
+        let _ = stop_perf_tx.send(());
+
+        // Handle the lines that were still buffered when the command exited, then tell the
+        // readline thread to stop in case a leftover process keeps the stdout open. The drain
+        // has an overall deadline, since a leftover process may keep writing to the stdout
+        let drain_deadline = Instant::now() + OUTPUT_DRAIN_TIMEOUT;
+        while let Ok(line) = readline_rx.recv_deadline(drain_deadline) {
+            self.handle_line(epoch, &line, &mut phase_counters, counters, stdout);
+        }
+
+        let _ = stop_readline_tx.send(());
+
+        // Handle the messages that were still in flight when the command exited
+        if let Some(listener) = listener {
+            for received in listener.finish() {
+                self.handle_received(&mut protocol_phases, received, counters);
+            }
+        }
+
+        // The phases still running are closed at the exit of the command
+        if let Some(counters) = counters.map(crate::counters::Counters::read) {
+            for (index, start) in phase_counters {
+                if let Some(phase) = self.phases.get_mut(index) {
+                    phase.counters = Some(counters.since(&start));
                 }
+            }
 
-                break;
+            for (id, start) in protocol_phases.counters {
+                if let Some(phase) = protocol_phases
+                    .indices
+                    .get(&id)
+                    .and_then(|indices| BenchmarkPhase::at_mut(&mut self.phases, indices))
+                {
+                    phase.counters = Some(counters.since(&start));
+                }
             }
+        }
 
-            // Attempt to receive a line from the readline thread
-            let Ok(line) = readline_rx.try_recv() else {
-                continue;
-            };
+        for path in BenchmarkPhase::close_dangling(&mut self.phases, exit_time) {
+            self.warn(format!(
+                "Phase \"{path}\" never stopped, closing it at the exit of the build"
+            ));
+        }
 
-            let line = line.trim_start();
+        Ok((exit_status, timed_out))
+    }
 
-            if line.starts_with("/dyno start ") {
-                // Get the name of the phase from the end of the line
-                let name = line.trim_start_matches("/dyno start ").trim_end();
+    /// Handles a single line of the command's stdout, keeping it in `stdout` unless it is a marker.
+    fn handle_line(
+        &mut self,
+        epoch: &Instant,
+        line: &str,
+        phase_counters: &mut Vec<(usize, HardwareCounters)>,
+        counters: Option<&crate::counters::Counters>,
+        stdout: &mut BuildLog,
+    ) {
+        if line.trim_start().starts_with("/dyno ") {
+            self.handle_marker(epoch, line, phase_counters, counters);
+        } else {
+            stdout.push(line);
+        }
+    }
 
-                // Add the phase to the current benchmark
-                self.phases.push(BenchmarkPhase {
-                    name: name.into(),
-                    start_time: Some(epoch.elapsed()),
-                    end_time: None,
-                });
-            } else if line.starts_with("/dyno stop ") {
-                // Get the name of the phase from the end of the line
-                let name = line.trim_start_matches("/dyno stop ").trim_end();
-
-                // Get the current benchmark phase
-                let phase = self
-                    .phases
-                    .iter_mut()
-                    .rev()
-                    .find(|phase| name == phase.name)
-                    .ok_or(wrap!("Failed to find phase".into()))?;
-
-                // Ensure the received name matches the name of the current phase
-                assert!(
-                    name == phase.name,
-                    "Received phase name \"{}\" does not match current phase name \"{}\"",
-                    name,
-                    phase.name,
+    /// Records a warning about the benchmark, which does not stop the run.
+    fn warn(&mut self, warning: String) {
+        eprintln!("Warning in benchmark \"{}\": {warning}", self.name);
+        self.warnings.push(warning);
+    }
+
+    /// Handles a single line of the command's stdout, which may be a `/dyno` marker.
+    ///
+    /// A stop marker closes the latest running phase of its name, and a stop marker without a
+    /// running phase of its name is recorded as a warning.
+    fn handle_marker(
+        &mut self,
+        epoch: &Instant,
+        line: &str,
+        phase_counters: &mut Vec<(usize, HardwareCounters)>,
+        counters: Option<&crate::counters::Counters>,
+    ) {
+        let line = line.trim();
+
+        if let Some(name) = line.strip_prefix("/dyno start ") {
+            // Add the phase to the current benchmark
+            self.phases.push(BenchmarkPhase {
+                name: name.trim().into(),
+                start_time: Some(epoch.elapsed()),
+                ..Default::default()
+            });
+
+            if let Some(counters) = counters {
+                phase_counters.push((self.phases.len() - 1, counters.read()));
+            }
+        } else if let Some(name) = line.strip_prefix("/dyno stop ") {
+            let name = name.trim();
+
+            // Get the latest running phase of the name
+            let Some(index) = self
+                .phases
+                .iter()
+                .rposition(|phase| phase.name == name && phase.end_time.is_none())
+            else {
+                self.warn(format!(
+                    "Ignoring the stop of phase \"{name}\", which is not running"
+                ));
+                return;
+            };
+
+            // Phases started later than the stopped phase should have stopped before it
+            if let Some(running) = self.phases[index + 1..]
+                .iter()
+                .rev()
+                .find(|phase| phase.end_time.is_none() && phase.start_time.is_some())
+            {
+                let warning = format!(
+                    "Phase \"{name}\" stopped while phase \"{}\" started after it is still running",
+                    running.name
                 );
+                self.warn(warning);
+            }
+
+            let phase = &mut self.phases[index];
 
-                // Set the end time of the benchmark
-                phase.end_time = Some(epoch.elapsed());
-            } else if line.starts_with("/dyno info ") {
-                let asm_information: &str = line.trim_start_matches("/dyno info ").trim_end();
-                self.asm_information =
-                    Some(serde_json::from_str(asm_information).map_err(|e| wrap!(e.into()))?);
+            // Set the end time of the benchmark
+            phase.end_time = Some(epoch.elapsed());
+
+            if let (Some(counters), Some(position)) = (
+                counters,
+                phase_counters.iter().position(|(i, _)| *i == index),
+            ) {
+                let (_, start) = phase_counters.remove(position);
+                phase.counters = Some(counters.read().since(&start));
+            }
+        } else if let Some(asm_information) = line.strip_prefix("/dyno info ") {
+            match serde_json::from_str(asm_information.trim())
+                .map_err(|e| e.to_string())
+                .and_then(AsmInformation::parse)
+            {
+                Ok(asm_information) => self.asm_information = Some(asm_information),
+                Err(e) => self.warn(format!("Ignoring malformed bytecode information: {e}")),
             }
         }
+    }
 
-        Ok(())
+    /// Handles a single message or warning received through the phase protocol.
+    ///
+    /// Messages that refer to an unknown phase are recorded as warnings and skipped.
+    fn handle_received(
+        &mut self,
+        protocol_phases: &mut ProtocolPhases,
+        received: crate::protocol::Received,
+        counters: Option<&crate::counters::Counters>,
+    ) {
+        use crate::protocol::{Message, Received};
+
+        let (time, message) = match received {
+            Received::Message(time, message) => (time, message),
+            Received::Warning(warning) => {
+                self.warn(warning);
+                return;
+            }
+        };
+
+        match message {
+            Message::Hello { .. } => {}
+
+            Message::PhaseStart {
+                id,
+                parent,
+                name,
+                metadata,
+            } => {
+                let phase = BenchmarkPhase {
+                    name,
+                    start_time: Some(time),
+                    metadata,
+                    ..Default::default()
+                };
+
+                let parent_indices = parent.and_then(|parent| protocol_phases.indices.get(&parent));
+
+                let indices = if let Some(mut indices) = parent_indices.cloned() {
+                    let Some(parent) = BenchmarkPhase::at_mut(&mut self.phases, &indices) else {
+                        return;
+                    };
+                    parent.children.push(phase);
+                    indices.push(parent.children.len() - 1);
+                    indices
+                } else {
+                    if let Some(parent) = parent {
+                        self.warn(format!(
+                            "Phase {id} started in unknown phase {parent}, recording it as a top-level phase"
+                        ));
+                    }
+                    self.phases.push(phase);
+                    vec![self.phases.len() - 1]
+                };
+
+                if protocol_phases.indices.insert(id, indices).is_some() {
+                    self.warn(format!("Phase id {id} was started more than once"));
+                }
+
+                if let Some(counters) = counters {
+                    protocol_phases.counters.insert(id, counters.read());
+                }
+            }
+
+            Message::PhaseEnd { id, metadata } => {
+                let Some(phase) = protocol_phases
+                    .indices
+                    .get(&id)
+                    .and_then(|indices| BenchmarkPhase::at_mut(&mut self.phases, indices))
+                else {
+                    self.warn(format!("Ignoring the end of unknown phase {id}"));
+                    return;
+                };
+
+                if phase.end_time.is_some() {
+                    let warning = format!("Ignoring the repeated end of phase \"{}\"", phase.name);
+                    self.warn(warning);
+                    return;
+                }
+
+                phase.end_time = Some(time);
+                phase.metadata.extend(metadata);
+
+                if let (Some(counters), Some(start)) =
+                    (counters, protocol_phases.counters.remove(&id))
+                {
+                    phase.counters = Some(counters.read().since(&start));
+                }
+            }
+
+            Message::Counter { phase, name, value } => {
+                let Some(phase) = protocol_phases
+                    .indices
+                    .get(&phase)
+                    .and_then(|indices| BenchmarkPhase::at_mut(&mut self.phases, indices))
+                else {
+                    self.warn(format!(
+                        "Ignoring counter \"{name}\" of unknown phase {phase}"
+                    ));
+                    return;
+                };
+
+                *phase.counts.entry(name).or_default() += value;
+            }
+
+            Message::Event {
+                name,
+                phase,
+                metadata,
+            } => {
+                let phase = match phase {
+                    Some(id) => {
+                        let path = protocol_phases
+                            .indices
+                            .get(&id)
+                            .and_then(|indices| BenchmarkPhase::path_at(&self.phases, indices));
+
+                        if path.is_none() {
+                            self.warn(format!("Event \"{name}\" happened in unknown phase {id}"));
+                        }
+
+                        path
+                    }
+                    None => None,
+                };
+
+                self.events.push(BenchmarkEvent {
+                    name,
+                    timestamp: time,
+                    phase,
+                    metadata,
+                });
+            }
+
+            Message::Info { data } => match AsmInformation::parse(data) {
+                Ok(asm_information) => self.asm_information = Some(asm_information),
+                Err(e) => self.warn(format!("Ignoring malformed bytecode information: {e}")),
+            },
+        }
     }
 
     /// Spawns a thread to collect performance frames for the command.
+    ///
+    /// Frames are scheduled on a fixed grid of `sampling_interval` ticks, and each frame records
+    /// how late it was taken relative to its tick. On Linux the `/proc` files of the process are
+    /// read directly, which is cheap enough for millisecond intervals.
+    ///
+    /// Every frame aggregates the usage of the whole process tree of the command, and breaks it
+    /// down per process when the command has spawned other processes.
+    #[allow(clippy::too_many_arguments)]
     fn spawn_perf_thread(
         epoch: &Instant,
         phase_epoch: &Instant,
@@ -563,6 +2062,7 @@ impl Benchmark {
         stop_perf_rx: Receiver<()>,
         stop_readline_rx: Receiver<()>,
         frames: Arc<Mutex<Vec<BenchmarkFrame>>>,
+        sampling_interval: Duration,
     ) {
         let epoch = *epoch;
         let phase_epoch = *phase_epoch;
@@ -574,62 +2074,565 @@ impl Benchmark {
             system.cpus().len()
         };
 
+        #[cfg(target_os = "linux")]
+        let mut sampler = crate::procfs::TreeSampler::new(pid.as_u32(), num_cpus);
+
+        std::thread::spawn(move || {
+            let mut deadline = Instant::now();
+
+            loop {
+                let frame_start = Instant::now();
+
+                // If we receive a STOP signal, stop looping and allow the perf thread to exit
+                if stop_perf_rx.try_recv().is_ok() {
+                    break;
+                }
+
+                if stop_readline_rx.try_recv().is_ok() {
+                    break;
+                }
+
+                #[cfg(target_os = "linux")]
+                let usage = sampler.sample();
+
+                #[cfg(not(target_os = "linux"))]
+                let usage = Self::sample_process_tree(&mut system, pid, num_cpus);
+
+                let Some(ProcessTreeUsage {
+                    total: usage,
+                    mut processes,
+                }) = usage
+                else {
+                    break;
+                };
+
+                // A single process is already described by the frame itself
+                if processes.len() < 2 {
+                    processes.clear();
+                }
+
+                frames
+                    .lock()
+                    .expect("Failed to get lock for frames")
+                    .push(BenchmarkFrame {
+                        timestamp: frame_start.duration_since(epoch),
+                        relative_timestamp: frame_start.duration_since(phase_epoch),
+                        lateness: frame_start.saturating_duration_since(deadline),
+                        cpu_usage: usage.cpu_usage,
+                        memory_usage: usage.memory_usage,
+                        virtual_memory_usage: usage.virtual_memory_usage,
+                        disk_total_written_bytes: usage.disk_total_written_bytes,
+                        disk_written_bytes: usage.disk_written_bytes,
+                        disk_total_read_bytes: usage.disk_total_read_bytes,
+                        disk_read_bytes: usage.disk_read_bytes,
+                        processes,
+                    });
+
+                // Schedule the next frame on the next tick, skipping the ticks that were missed
+                deadline += sampling_interval;
+                let now = Instant::now();
+                if deadline < now {
+                    deadline = now;
+                }
+
+                std::thread::sleep(deadline - now);
+            }
+        });
+    }
+
+    /// Samples the usage of the process tree through `sysinfo`.
+    ///
+    /// The storage totals only cover the processes of the tree that are still running.
+    ///
+    /// # Returns
+    ///
+    /// `None` if the root process has exited.
+    #[cfg(not(target_os = "linux"))]
+    fn sample_process_tree(
+        system: &mut sysinfo::System,
+        root: sysinfo::Pid,
+        num_cpus: usize,
+    ) -> Option<ProcessTreeUsage> {
         let refresh_kind = sysinfo::ProcessRefreshKind::new()
             .with_cpu()
             .with_memory()
             .with_disk_usage();
 
-        std::thread::spawn(move || loop {
-            let frame_start = std::time::Instant::now();
+        system.refresh_processes_specifics(sysinfo::ProcessesToUpdate::All, true, refresh_kind);
 
-            // If we receive a STOP signal, stop looping and allow the perf thread to exit
-            if stop_perf_rx.try_recv().is_ok() {
-                break;
-            }
+        system.process(root)?;
 
-            if stop_readline_rx.try_recv().is_ok() {
-                break;
-            }
+        // Walk the tree from the root, parents before their children
+        let mut pids = vec![root];
+        let mut parents = vec![root];
 
-            if system.refresh_processes_specifics(
-                sysinfo::ProcessesToUpdate::Some(&[pid]),
-                true,
-                refresh_kind,
-            ) != 1
-            {
-                break;
+        while let Some(parent) = parents.pop() {
+            for (pid, process) in system.processes() {
+                if process.parent() == Some(parent) {
+                    pids.push(*pid);
+                    parents.push(*pid);
+                }
             }
+        }
 
+        let mut total = ProcessUsage::default();
+        let mut processes = Vec::with_capacity(pids.len());
+
+        for pid in pids {
             let Some(process) = system.process(pid) else {
-                panic!("Failed to find process with pid {pid}");
+                continue;
             };
 
-            let cpu_usage = process.cpu_usage() / num_cpus as f32;
-            let memory_usage = process.memory();
-            let virtual_memory_usage = process.virtual_memory();
             let disk_usage = process.disk_usage();
+            let cpu_usage = process.cpu_usage() / num_cpus as f32;
+
+            total.cpu_usage += cpu_usage;
+            total.memory_usage += process.memory();
+            total.virtual_memory_usage += process.virtual_memory();
+            total.disk_total_written_bytes += disk_usage.total_written_bytes;
+            total.disk_written_bytes += disk_usage.written_bytes;
+            total.disk_total_read_bytes += disk_usage.total_read_bytes;
+            total.disk_read_bytes += disk_usage.read_bytes;
+
+            processes.push(ProcessFrame {
+                pid: pid.as_u32(),
+                name: process.name().to_string_lossy().into_owned(),
+                cpu_usage,
+                memory_usage: process.memory(),
+                virtual_memory_usage: process.virtual_memory(),
+                disk_written_bytes: disk_usage.written_bytes,
+                disk_read_bytes: disk_usage.read_bytes,
+            });
+        }
+
+        Some(ProcessTreeUsage { total, processes })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::protocol::{Message, Received};
+
+    #[test]
+    #[allow(clippy::float_cmp)]
+    fn test_handle_phase_messages() {
+        let mut benchmark = Benchmark::new(&"hashing", "hashing");
+        let mut protocol_phases = ProtocolPhases::default();
+
+        let messages = [
+            Message::PhaseStart {
+                id: 1,
+                parent: None,
+                name: "compile".to_string(),
+                metadata: BTreeMap::new(),
+            },
+            Message::PhaseStart {
+                id: 2,
+                parent: Some(1),
+                name: "type check".to_string(),
+                metadata: BTreeMap::from([("items".to_string(), serde_json::json!(4))]),
+            },
+            Message::Counter {
+                phase: 2,
+                name: "functions".to_string(),
+                value: 3.0,
+            },
+            Message::Counter {
+                phase: 2,
+                name: "functions".to_string(),
+                value: 2.0,
+            },
+            Message::Event {
+                name: "cache miss".to_string(),
+                phase: Some(2),
+                metadata: BTreeMap::new(),
+            },
+            Message::PhaseEnd {
+                id: 2,
+                metadata: BTreeMap::new(),
+            },
+            Message::PhaseEnd {
+                id: 3,
+                metadata: BTreeMap::new(),
+            },
+            Message::PhaseEnd {
+                id: 1,
+                metadata: BTreeMap::from([("entries".to_string(), serde_json::json!(1))]),
+            },
+        ];
+
+        for (millis, message) in messages.into_iter().enumerate() {
+            benchmark.handle_received(
+                &mut protocol_phases,
+                Received::Message(Duration::from_millis(millis as u64), message),
+                None,
+            );
+        }
+
+        let phases: Vec<(String, Option<Duration>, Option<Duration>)> =
+            BenchmarkPhase::walk(&benchmark.phases)
+                .into_iter()
+                .map(|(path, phase)| (path, phase.start_time, phase.end_time))
+                .collect();
+
+        assert_eq!(
+            phases,
+            vec![
+                (
+                    "compile".to_string(),
+                    Some(Duration::from_millis(0)),
+                    Some(Duration::from_millis(7))
+                ),
+                (
+                    "compile/type check".to_string(),
+                    Some(Duration::from_millis(1)),
+                    Some(Duration::from_millis(5))
+                ),
+            ]
+        );
+
+        let type_check = &benchmark.phases[0].children[0];
+        assert_eq!(type_check.counts["functions"], 5.0);
+        assert_eq!(type_check.metadata["items"], serde_json::json!(4));
+        assert_eq!(
+            benchmark.phases[0].metadata["entries"],
+            serde_json::json!(1)
+        );
+
+        assert_eq!(benchmark.events.len(), 1);
+        assert_eq!(
+            benchmark.events[0].phase.as_deref(),
+            Some("compile/type check")
+        );
+
+        // The end of the unknown phase 3 is a warning
+        assert_eq!(benchmark.warnings.len(), 1);
+    }
+
+    #[test]
+    fn test_bound_build_log() {
+        let mut log = BuildLog::default();
+
+        for line in 0..BuildLog::MAX_LINES + 5 {
+            log.push(&line.to_string());
+        }
+
+        assert_eq!(log.lines.len(), BuildLog::MAX_LINES);
+        assert_eq!(log.dropped, 5);
+        assert_eq!(log.lines.front().map(String::as_str), Some("5"));
+
+        log.push(&"x".repeat(BuildLog::MAX_LINE_LENGTH * 2));
+        assert_eq!(
+            log.lines.back().map(String::len),
+            Some(BuildLog::MAX_LINE_LENGTH)
+        );
+    }
+
+    #[test]
+    fn test_join_stderr_thread() -> crate::error::Result<()> {
+        let mut command = Command::new("sh")
+            .args(["-c", "for line in 1 2 3; do echo \"line $line\" >&2; done"])
+            .stderr(Stdio::piped())
+            .spawn()
+            .map_err(|e| wrap!(e.into()))?;
+
+        let stderr = Arc::new(Mutex::new(BuildLog::default()));
+        let (thread, done) = Benchmark::spawn_stderr_thread(&mut command, stderr.clone())?;
+        command.wait().map_err(|e| wrap!(e.into()))?;
+
+        // Every line is kept, including the ones still in flight when the command exited
+        let log = Benchmark::join_stderr_thread(thread, &done, &stderr);
+        assert_eq!(
+            log.lines.iter().map(String::as_str).collect::<Vec<_>>(),
+            vec!["line 1", "line 2", "line 3"]
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_fail_warmup() -> crate::error::Result<()> {
+        let warmup = |script: &str| -> crate::error::Result<Benchmark> {
+            let mut benchmark = Benchmark::new(&"warmup", std::env::temp_dir());
+            benchmark.workload.args = vec!["-c".to_string(), script.to_string()];
+            benchmark.run_warmup(
+                std::path::Path::new("sh"),
+                None,
+                &crate::environment::Controls::default(),
+            )?;
+            Ok(benchmark)
+        };
+
+        assert!(!warmup("exit 0")?.failed());
+
+        // The measured builds would start from a half-built tree, so the benchmark fails
+        let benchmark = warmup("echo broken >&2; exit 3")?;
+        assert!(benchmark.failed());
+        assert_eq!(benchmark.warnings.len(), 1);
+
+        let status = benchmark.status.expect("The failed warmup has no status");
+        assert_eq!(status.exit_code, Some(3));
+        assert_eq!(
+            status
+                .stderr
+                .lines
+                .iter()
+                .map(String::as_str)
+                .collect::<Vec<_>>(),
+            vec!["broken"]
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_handle_unbalanced_markers() {
+        let mut benchmark = Benchmark::new(&"hashing", "hashing");
+        let epoch = Instant::now();
+        let mut phase_counters = vec![];
+
+        for line in [
+            "/dyno stop parse",
+            "/dyno start parse",
+            "/dyno start type check",
+            "/dyno stop parse",
+            "/dyno info {not json",
+            "/dyno info {\"bytecode_size\":8}",
+            "/dyno start codegen",
+        ] {
+            benchmark.handle_marker(&epoch, line, &mut phase_counters, None);
+        }
+
+        // The stray stop, the out of order stop and the malformed info are warnings
+        assert_eq!(benchmark.warnings.len(), 3);
+        assert_eq!(
+            benchmark.asm_information,
+            Some(AsmInformation {
+                bytecode_size: 8,
+                ..Default::default()
+            })
+        );
 
-            frames
+        let closed = BenchmarkPhase::close_dangling(&mut benchmark.phases, epoch.elapsed());
+        assert_eq!(
+            closed,
+            vec!["type check".to_string(), "codegen".to_string()]
+        );
+        assert!(benchmark
+            .phases
+            .iter()
+            .all(|phase| phase.end_time.is_some()));
+    }
+
+    /// The environment variable marking the process of the test binary that runs a test alone
+    const RUN_ALONE_ENV_VAR: &str = "DYNO_TEST_RUN_ALONE";
+
+    /// Runs the test with the given path alone in a new process of the test binary, so that the
+    /// CPU time of the process is not shared with the tests running next to it.
+    ///
+    /// Returns `true` in the process that runs the test alone, and `false` in the process that
+    /// spawned it once the test passed there.
+    fn run_alone(test: &str) -> crate::error::Result<bool> {
+        if std::env::var_os(RUN_ALONE_ENV_VAR).is_some() {
+            return Ok(true);
+        }
+
+        let output = Command::new(std::env::current_exe().map_err(|e| wrap!(e.into()))?)
+            .args([test, "--exact", "--test-threads=1", "--nocapture"])
+            .env(RUN_ALONE_ENV_VAR, "1")
+            .output()
+            .map_err(|e| wrap!(e.into()))?;
+
+        assert!(
+            output.status.success(),
+            "{test} failed when run alone:\n{}{}",
+            String::from_utf8_lossy(&output.stdout),
+            String::from_utf8_lossy(&output.stderr)
+        );
+
+        Ok(false)
+    }
+
+    /// Returns the CPU time spent by every thread of this process, leaving out its children.
+    fn process_cpu_time() -> Duration {
+        // SAFETY: an all-zero `rusage` is valid, and `usage` outlives the call
+        let mut usage: libc::rusage = unsafe { std::mem::zeroed() };
+        unsafe {
+            libc::getrusage(libc::RUSAGE_SELF, std::ptr::from_mut(&mut usage));
+        }
+
+        let time = |time: libc::timeval| {
+            Duration::new(
+                u64::try_from(time.tv_sec).unwrap_or(0),
+                u32::try_from(time.tv_usec * 1000).unwrap_or(0),
+            )
+        };
+
+        time(usage.ru_utime) + time(usage.ru_stime)
+    }
+
+    /// Spawns a shell script and waits for it as a measured build, capturing its frames every
+    /// `sampling_interval` when given.
+    fn wait_for_script(
+        benchmark: &mut Benchmark,
+        script: &str,
+        deadline: Option<Instant>,
+        sampling_interval: Option<Duration>,
+    ) -> crate::error::Result<(ExitStatus, bool)> {
+        let epoch = Instant::now();
+
+        let mut command = Command::new("sh")
+            .arg("-c")
+            .arg(script)
+            .stdout(Stdio::piped())
+            .spawn()
+            .map_err(|e| wrap!(e.into()))?;
+
+        let (readline_tx, readline_rx) = unbounded();
+        let (stop_readline_tx, stop_readline_rx) = unbounded();
+        let (stop_perf_tx, stop_perf_rx) = unbounded();
+
+        if let Some(sampling_interval) = sampling_interval {
+            Benchmark::spawn_perf_thread(
+                &epoch,
+                &epoch,
+                Pid::from_u32(command.id()),
+                stop_perf_rx,
+                stop_readline_rx.clone(),
+                benchmark.frames.clone(),
+                sampling_interval,
+            );
+        }
+
+        Benchmark::spawn_readline_thread(&mut command, stop_readline_rx, readline_tx)?;
+
+        benchmark.wait(
+            &epoch,
+            command,
+            &stop_readline_tx,
+            &stop_perf_tx,
+            &readline_rx,
+            None,
+            None,
+            deadline,
+            &mut BuildLog::default(),
+        )
+    }
+
+    #[test]
+    fn test_wait_footprint() -> crate::error::Result<()> {
+        if !run_alone("types::tests::test_wait_footprint")? {
+            return Ok(());
+        }
+
+        let build_time = Duration::from_secs(1);
+
+        for sampling_interval in [
+            BenchmarkFrame::DEFAULT_SAMPLING_INTERVAL,
+            Duration::from_millis(1),
+        ] {
+            let mut benchmark = Benchmark::new(&"sleep", "sleep");
+
+            let start = process_cpu_time();
+            let (exit_status, timed_out) = wait_for_script(
+                &mut benchmark,
+                &format!(
+                    "echo /dyno start sleep; sleep {}; echo /dyno stop sleep",
+                    build_time.as_secs_f64()
+                ),
+                None,
+                Some(sampling_interval),
+            )?;
+            let cpu_time = process_cpu_time().saturating_sub(start);
+
+            assert!(exit_status.success());
+            assert!(!timed_out);
+            assert_eq!(benchmark.phases.len(), 1);
+            assert!(benchmark.phases[0].end_time.is_some());
+            assert!(!benchmark
+                .frames
                 .lock()
-                .expect("Failed to get lock for frames")
-                .push(BenchmarkFrame {
-                    timestamp: frame_start.duration_since(epoch),
-                    relative_timestamp: frame_start.duration_since(phase_epoch),
-                    cpu_usage,
-                    memory_usage,
-                    virtual_memory_usage,
-                    disk_total_written_bytes: disk_usage.total_written_bytes,
-                    disk_written_bytes: disk_usage.written_bytes,
-                    disk_total_read_bytes: disk_usage.total_read_bytes,
-                    disk_read_bytes: disk_usage.read_bytes,
-                });
+                .map_or(true, |frames| frames.is_empty()));
+
+            // Waiting blocks between events, and the frames of the sampler are cheap, so the
+            // whole process stays far below the CPU time of a build that would busy-loop
+            let budget = if sampling_interval >= BenchmarkFrame::DEFAULT_SAMPLING_INTERVAL {
+                build_time / 20
+            } else {
+                build_time / 4
+            };
 
-            let frame_elapsed = frame_start.elapsed();
+            assert!(
+                cpu_time < budget,
+                "Waiting for a {build_time:?} build sampled every {sampling_interval:?} took {cpu_time:?} of CPU time"
+            );
+        }
 
-            // Ensure that we don't loop any faster than the minimum frame duration
-            if frame_elapsed < BenchmarkFrame::MINIMUM_DURATION {
-                std::thread::sleep(BenchmarkFrame::MINIMUM_DURATION - frame_elapsed);
-            }
-        });
+        Ok(())
+    }
+
+    #[test]
+    #[cfg(target_os = "linux")]
+    fn test_wait_timeout() -> crate::error::Result<()> {
+        let pid_file = std::env::temp_dir().join(format!("dyno-timeout-{}", std::process::id()));
+
+        // A build that runs past its deadline is killed along with every process it spawned
+        let mut benchmark = Benchmark::new(&"hang", "hang");
+        let start = Instant::now();
+        let (exit_status, timed_out) = wait_for_script(
+            &mut benchmark,
+            &format!("sleep 30 & echo $! > {}; wait", pid_file.display()),
+            Some(Instant::now() + Duration::from_millis(200)),
+            None,
+        )?;
+
+        assert!(!exit_status.success());
+        assert!(timed_out);
+        assert!(start.elapsed() < Duration::from_secs(5));
+
+        let pid = std::fs::read_to_string(&pid_file).map_err(|e| wrap!(e.into()))?;
+        std::fs::remove_file(&pid_file).map_err(|e| wrap!(e.into()))?;
+
+        // The killed helper process is gone, or waits to be reaped by its new parent
+        let stat = std::path::Path::new("/proc").join(pid.trim()).join("stat");
+        let running = || {
+            std::fs::read_to_string(&stat).is_ok_and(|stat| {
+                stat.rsplit_once(')')
+                    .is_some_and(|(_, fields)| !fields.trim_start().starts_with('Z'))
+            })
+        };
+
+        let start = Instant::now();
+        while running() && start.elapsed() < Duration::from_secs(2) {
+            std::thread::sleep(Duration::from_millis(10));
+        }
+
+        assert!(!running(), "The helper process outlived the killed build");
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_drain_leftover_output() -> crate::error::Result<()> {
+        // A leftover process keeps writing to the stdout more often than the drain timeout
+        let mut benchmark = Benchmark::new(&"leftover", "leftover");
+        let start = Instant::now();
+        let (exit_status, timed_out) = wait_for_script(
+            &mut benchmark,
+            "(for i in $(seq 500); do echo leftover; sleep 0.01; done) & echo exited",
+            None,
+            None,
+        )?;
+
+        assert!(exit_status.success());
+        assert!(!timed_out);
+        assert!(
+            start.elapsed() < Duration::from_secs(2),
+            "Waiting for the command took {:?}",
+            start.elapsed()
+        );
+
+        Ok(())
     }
 }
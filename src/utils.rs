@@ -2,21 +2,25 @@ use crate::error::Result;
 use crate::types::{Benchmark, SystemSpecs};
 use crate::wrap;
 
-/// Collect all profiling targets in the given directory and return a map of the target name to the path canonical path.
+/// Resolve the root folder that the profiling targets are discovered in.
+///
+/// A target pointing at the `src` folder of a project resolves to the project folder itself.
 ///
 /// # Arguments
 ///
-/// * `path` - A path to the directory containing the profiling targets.
+/// * `path` - A path to the target folder.
 ///
 /// # Returns
 ///
-/// A `Result` containing a `Vec` of `Benchmark` structs.
+/// A `Result` containing the root folder of the targets.
 ///
 /// # Errors
 ///
-/// If the path is not a valid directory.
+/// If the last component of the path cannot be retrieved.
 ///
-pub fn generate_benchmarks<P: AsRef<std::path::Path>>(path: P) -> Result<Vec<Benchmark>> {
+/// If the parent of a `src` folder cannot be retrieved.
+///
+pub fn target_root<P: AsRef<std::path::Path>>(path: P) -> Result<std::path::PathBuf> {
     let mut path = path.as_ref();
 
     if path
@@ -33,8 +37,215 @@ pub fn generate_benchmarks<P: AsRef<std::path::Path>>(path: P) -> Result<Vec<Ben
             .ok_or_else(|| wrap!("Failed to get parent of path.".into()))?;
     }
 
+    Ok(path.to_path_buf())
+}
+
+/// Keep the benchmarks whose path relative to the target root matches the include globs and
+/// none of the exclude globs.
+///
+/// A benchmark at the target root itself is matched by its name.
+///
+/// # Arguments
+///
+/// * `benchmarks` - The benchmarks discovered in the target root.
+///
+/// * `target_root` - The root folder the benchmarks were discovered in.
+///
+/// * `include` - The globs of the benchmarks to keep, or all benchmarks when empty.
+///
+/// * `exclude` - The globs of the benchmarks to skip.
+///
+/// # Returns
+///
+/// A `Result` containing the `Vec` of kept `Benchmark` structs.
+///
+/// # Errors
+///
+/// If a glob is invalid.
+///
+pub fn filter_benchmarks(
+    benchmarks: Vec<Benchmark>,
+    target_root: &std::path::Path,
+    include: &[String],
+    exclude: &[String],
+) -> Result<Vec<Benchmark>> {
+    let include_set = glob_set(include).map_err(|e| wrap!(e))?;
+    let exclude_set = glob_set(exclude).map_err(|e| wrap!(e))?;
+
+    Ok(benchmarks
+        .into_iter()
+        .filter(|benchmark| {
+            let relative_path = relative_project_path(benchmark, target_root);
+
+            (include.is_empty() || include_set.is_match(relative_path))
+                && !exclude_set.is_match(relative_path)
+        })
+        .collect())
+}
+
+/// Build a set of globs, in the order they are given.
+///
+/// # Errors
+///
+/// If a glob is invalid.
+///
+pub fn glob_set(globs: &[String]) -> Result<globset::GlobSet> {
+    let mut builder = globset::GlobSetBuilder::new();
+    for glob in globs {
+        builder.add(globset::Glob::new(glob).map_err(|e| wrap!(e.into()))?);
+    }
+    Ok(builder.build().map_err(|e| wrap!(e.into()))?)
+}
+
+/// Get the path of a benchmark relative to the target root, which globs are matched against.
+///
+/// A benchmark at the target root itself is identified by its name.
+///
+#[must_use]
+pub fn relative_project_path<'a>(
+    benchmark: &'a Benchmark,
+    target_root: &std::path::Path,
+) -> &'a std::path::Path {
+    benchmark
+        .path
+        .strip_prefix(target_root)
+        .ok()
+        .filter(|path| !path.as_os_str().is_empty())
+        .unwrap_or(std::path::Path::new(&benchmark.name))
+}
+
+/// Get the members of a workspace.
+///
+/// # Arguments
+///
+/// * `project` - The folder of a project, which is a workspace if its `Forc.toml` has a
+///   `[workspace]` table.
+///
+/// # Returns
+///
+/// A `Result` containing the canonical paths of the members that exist, or an empty `Vec` if the
+/// project is not a workspace.
+///
+/// # Errors
+///
+/// If the `Forc.toml` of the project cannot be read or parsed.
+///
+pub fn workspace_members(project: &std::path::Path) -> Result<Vec<std::path::PathBuf>> {
+    let manifest = std::fs::read_to_string(project.join("Forc.toml"))
+        .map_err(|e| wrap!(e.into()))?
+        .parse::<toml::Table>()
+        .map_err(|e| wrap!(Box::new(e)))?;
+
+    let members = manifest
+        .get("workspace")
+        .and_then(|workspace| workspace.get("members"))
+        .and_then(toml::Value::as_array)
+        .map(Vec::as_slice)
+        .unwrap_or_default();
+
+    Ok(members
+        .iter()
+        .filter_map(toml::Value::as_str)
+        .filter_map(|member| std::fs::canonicalize(project.join(member)).ok())
+        .collect())
+}
+
+/// Skip the benchmarks that are members of a workspace which is benchmarked itself, since
+/// building the workspace builds every member.
+///
+/// # Arguments
+///
+/// * `benchmarks` - The benchmarks left after filtering.
+///
+/// # Returns
+///
+/// The `Vec` of kept `Benchmark` structs. A benchmark whose `Forc.toml` cannot be read or
+/// parsed is treated as a project rather than a workspace, with a warning.
+///
+pub fn skip_workspace_members(benchmarks: Vec<Benchmark>) -> Vec<Benchmark> {
+    let mut members = std::collections::BTreeSet::new();
+
+    for benchmark in &benchmarks {
+        match workspace_members(&benchmark.path) {
+            Ok(workspace_members) => members.extend(workspace_members),
+            Err(e) => eprintln!(
+                "Warning: Treating \"{}\" as a project rather than a workspace, since its manifest is unreadable: {e}",
+                benchmark.name
+            ),
+        }
+    }
+
+    let count = benchmarks.len();
+    let benchmarks = benchmarks
+        .into_iter()
+        .filter(|benchmark| !members.contains(&benchmark.path))
+        .collect::<Vec<_>>();
+
+    if benchmarks.len() < count {
+        println!(
+            "Skipping {} workspace members, which are built with their workspace.",
+            count - benchmarks.len()
+        );
+    }
+
+    benchmarks
+}
+
+/// Get the deepest folder that contains all of the given folders.
+///
+/// # Arguments
+///
+/// * `paths` - The canonical paths of the folders.
+///
+pub fn common_root(paths: &[std::path::PathBuf]) -> std::path::PathBuf {
+    let Some((first, rest)) = paths.split_first() else {
+        return std::path::PathBuf::new();
+    };
+
+    let mut root = first.clone();
+
+    for path in rest {
+        while !path.starts_with(&root) {
+            if !root.pop() {
+                break;
+            }
+        }
+    }
+
+    root
+}
+
+/// Collect all profiling targets in the given directory and return a map of the target name to the path canonical path.
+///
+/// # Arguments
+///
+/// * `path` - A path to the directory containing the profiling targets.
+///
+/// * `max_depth` - The maximum depth of the project folders below the target root, which is at
+///   depth 0, or `None` to walk the whole tree.
+///
+/// # Returns
+///
+/// A `Result` containing a `Vec` of `Benchmark` structs.
+///
+/// # Errors
+///
+/// If the path is not a valid directory.
+///
+pub fn generate_benchmarks<P: AsRef<std::path::Path>>(
+    path: P,
+    max_depth: Option<usize>,
+) -> Result<Vec<Benchmark>> {
+    let path = target_root(path).map_err(|e| wrap!(e))?;
+
+    // The `Forc.toml` of a project is one level below its folder
+    let mut walk = walkdir::WalkDir::new(path);
+    if let Some(max_depth) = max_depth {
+        walk = walk.max_depth(max_depth + 1);
+    }
+
     let mut targets = Vec::new();
-    for entry in walkdir::WalkDir::new(path)
+    for entry in walk
         .into_iter()
         .filter_map(std::result::Result::ok)
         .filter(|e| e.file_type().is_file() && e.file_name() == "Forc.toml")
@@ -295,6 +506,7 @@ struct MetricRow {
     metric: &'static str,
     value_change: String,
     percentage_change: String,
+    verdict: String,
 }
 
 #[derive(Tabled)]
@@ -304,89 +516,325 @@ struct AsmRow {
     current: String,
 }
 
+#[derive(Tabled)]
+struct ChangeRow {
+    change: &'static str,
+    previous: String,
+    current: String,
+}
+
+#[derive(Tabled)]
+struct PhaseRow {
+    path: String,
+    phase: String,
+    metric: &'static str,
+    value_change: String,
+    percentage_change: String,
+    verdict: String,
+}
+
+#[derive(Tabled)]
+struct SizeRow {
+    path: String,
+    item: String,
+    name: String,
+    previous: String,
+    current: String,
+    change: String,
+}
+
+/// The number of top growers and of top shrinkers printed for each benchmark
+const SIZE_REPORT_LIMIT: usize = 10;
+
+/// Push the rows of the top growing and shrinking bytecode items of a single benchmark's stats.
+fn push_size_rows(size_rows: &mut Vec<SizeRow>, path: &str, benchmark: &crate::stats::Stats) {
+    let growers = benchmark
+        .sizes
+        .iter()
+        .filter(|size| size.delta() > 0)
+        .take(SIZE_REPORT_LIMIT);
+
+    let shrinkers = benchmark
+        .sizes
+        .iter()
+        .rev()
+        .filter(|size| size.delta() < 0)
+        .take(SIZE_REPORT_LIMIT);
+
+    let format_size =
+        |size: Option<u64>| size.map_or_else(|| "-".to_string(), |size| size.to_string());
+
+    for size in growers.chain(shrinkers) {
+        size_rows.push(SizeRow {
+            path: path.to_string(),
+            item: size.item.to_string(),
+            name: size.name.clone(),
+            previous: format_size(size.previous),
+            current: format_size(size.current),
+            change: format!("{:+}", size.delta()),
+        });
+    }
+}
+
+/// Push the phase rows of a single benchmark's stats.
+fn push_phase_rows(phase_rows: &mut Vec<PhaseRow>, path: &str, benchmark: &crate::stats::Stats) {
+    for (phase, stats) in &benchmark.phases {
+        let mut metrics = vec![
+            ("Duration", &stats.duration),
+            ("Peak Memory Usage", &stats.peak_memory_usage),
+            ("Mean Memory Usage", &stats.mean_memory_usage),
+            ("Mean CPU Usage", &stats.mean_cpu_usage),
+            ("Disk Read Bytes", &stats.disk_read_bytes),
+            ("Disk Written Bytes", &stats.disk_written_bytes),
+        ];
+
+        if let Some(counters) = &stats.counters {
+            metrics.extend(counter_metrics(counters));
+        }
+
+        for (metric, change) in metrics {
+            let row = metric_row(metric, change);
+            phase_rows.push(PhaseRow {
+                path: path.to_string(),
+                phase: phase.clone(),
+                metric,
+                value_change: row.value_change,
+                percentage_change: row.percentage_change,
+                verdict: row.verdict,
+            });
+        }
+    }
+}
+
+/// Create the row of a single metric change.
+fn metric_row(metric: &'static str, change: &crate::stats::Change) -> MetricRow {
+    MetricRow {
+        metric,
+        value_change: change.change.to_string(),
+        percentage_change: change.percentage_change.to_string(),
+        verdict: change
+            .verdict()
+            .map(|verdict| verdict.to_string())
+            .unwrap_or_default(),
+    }
+}
+
+/// Push the metric rows of a single benchmark's stats.
+fn push_metric_rows(
+    metric_rows: &mut Vec<MetricRow>,
+    path: String,
+    benchmark: &crate::stats::Stats,
+) {
+    metric_rows.push(MetricRow {
+        metric: "Path",
+        value_change: path,
+        percentage_change: String::new(),
+        verdict: String::new(),
+    });
+    metric_rows.push(metric_row("CPU Usage Peak", &benchmark.cpu_usage_peak));
+    metric_rows.push(metric_row("CPU Usage Mean", &benchmark.cpu_usage_mean));
+    metric_rows.push(metric_row("CPU Usage P95", &benchmark.cpu_usage_p95));
+    metric_rows.push(metric_row(
+        "CPU Usage Integral",
+        &benchmark.cpu_usage_integral,
+    ));
+    metric_rows.push(metric_row(
+        "Memory Usage Peak",
+        &benchmark.memory_usage_peak,
+    ));
+    metric_rows.push(metric_row(
+        "Memory Usage Mean",
+        &benchmark.memory_usage_mean,
+    ));
+    metric_rows.push(metric_row("Memory Usage P95", &benchmark.memory_usage_p95));
+    metric_rows.push(metric_row(
+        "Memory Usage Integral",
+        &benchmark.memory_usage_integral,
+    ));
+    metric_rows.push(metric_row(
+        "Virtual Memory Usage Peak",
+        &benchmark.virtual_memory_usage_peak,
+    ));
+    metric_rows.push(metric_row(
+        "Disk Total Written Bytes",
+        &benchmark.disk_total_written_bytes,
+    ));
+    metric_rows.push(metric_row(
+        "Disk Written Bytes",
+        &benchmark.disk_written_bytes,
+    ));
+    metric_rows.push(metric_row(
+        "Disk Total Read Bytes",
+        &benchmark.disk_total_read_bytes,
+    ));
+    metric_rows.push(metric_row("Disk Read Bytes", &benchmark.disk_read_bytes));
+    metric_rows.push(metric_row("Bytecode Size", &benchmark.bytecode_size));
+    metric_rows.push(metric_row(
+        "Data Section Size",
+        &benchmark.data_section_size,
+    ));
+    metric_rows.push(metric_row(
+        "Data Section Used",
+        &benchmark.data_section_used,
+    ));
+    metric_rows.push(metric_row(
+        "Data Section Values",
+        &benchmark.data_section_values,
+    ));
+    metric_rows.push(metric_row("Entries", &benchmark.entries));
+    metric_rows.push(metric_row("Time", &benchmark.time));
+
+    if let Some(counters) = &benchmark.counters {
+        for (metric, change) in counter_metrics(counters) {
+            metric_rows.push(metric_row(metric, change));
+        }
+    }
+}
+
+/// Get the display name and change of each compared performance counter.
+fn counter_metrics(
+    counters: &crate::stats::CounterStats,
+) -> Vec<(&'static str, &crate::stats::Change)> {
+    [
+        ("Instructions", &counters.instructions),
+        ("Cycles", &counters.cycles),
+        ("Cache Misses", &counters.cache_misses),
+        ("Branch Misses", &counters.branch_misses),
+        ("Context Switches", &counters.context_switches),
+        ("Page Faults", &counters.page_faults),
+    ]
+    .into_iter()
+    .filter_map(|(metric, change)| Some((metric, change.as_ref()?)))
+    .collect()
+}
+
 /// Print the performance regression or improvements.
 ///
 /// # Arguments
 ///
 /// * `stats_result` - A reference to a `stats::Collection`.
 ///
-/// * `previous_benchmarks` - A reference to a vector of `types::Benchmark`.
-///
-/// * `current_benchmarks` - A reference to a vector of `types::Benchmark`.
-///
-/// # Errors
-///
-/// If the function is unable to get the asm information.
-///
-pub fn print_stats(
-    stats_result: &crate::stats::Collection,
-    previous_benchmarks: &[crate::types::Benchmark],
-    current_benchmarks: &[crate::types::Benchmark],
-) -> Result<()> {
+pub fn print_stats(stats_result: &crate::stats::Collection) {
     println!("Printing performance regression or improvements");
 
     // Create a vector to hold the metric rows
     let mut metric_rows = Vec::new();
 
+    // Create a vector to hold the phase rows
+    let mut phase_rows = Vec::new();
+
+    // Create a vector to hold the rows of the bytecode items whose size changed
+    let mut size_rows = Vec::new();
+
     // Add a row for each metric
-    for (path, benchmark) in &stats_result.0 {
-        metric_rows.push(MetricRow {
-            metric: "Path",
-            value_change: path.clone(),
-            percentage_change: String::new(),
-        });
-        metric_rows.push(MetricRow {
-            metric: "CPU Usage",
-            value_change: benchmark.cpu_usage.0.to_string(),
-            percentage_change: benchmark.cpu_usage.1.to_string(),
-        });
-        metric_rows.push(MetricRow {
-            metric: "Memory Usage",
-            value_change: benchmark.memory_usage.0.to_string(),
-            percentage_change: benchmark.memory_usage.1.to_string(),
-        });
-        metric_rows.push(MetricRow {
-            metric: "Virtual Memory Usage",
-            value_change: benchmark.virtual_memory_usage.0.to_string(),
-            percentage_change: benchmark.virtual_memory_usage.1.to_string(),
-        });
-        metric_rows.push(MetricRow {
-            metric: "Disk Total Written Bytes",
-            value_change: benchmark.disk_total_written_bytes.0.to_string(),
-            percentage_change: benchmark.disk_total_written_bytes.1.to_string(),
-        });
-        metric_rows.push(MetricRow {
-            metric: "Disk Written Bytes",
-            value_change: benchmark.disk_written_bytes.0.to_string(),
-            percentage_change: benchmark.disk_written_bytes.1.to_string(),
-        });
-        metric_rows.push(MetricRow {
-            metric: "Disk Total Read Bytes",
-            value_change: benchmark.disk_total_read_bytes.0.to_string(),
-            percentage_change: benchmark.disk_total_read_bytes.1.to_string(),
+    for (path, benchmark) in &stats_result.matched {
+        push_metric_rows(&mut metric_rows, path.clone(), benchmark);
+        push_phase_rows(&mut phase_rows, path, benchmark);
+        push_size_rows(&mut size_rows, path, benchmark);
+    }
+
+    for renamed in &stats_result.renamed {
+        let path = format!("{} -> {}", renamed.previous, renamed.current);
+        push_metric_rows(&mut metric_rows, path.clone(), &renamed.stats);
+        push_phase_rows(&mut phase_rows, &path, &renamed.stats);
+        push_size_rows(&mut size_rows, &path, &renamed.stats);
+    }
+
+    // Create a vector to hold the rows of benchmarks that changed between the runs
+    let mut change_rows = Vec::new();
+
+    for added in &stats_result.added {
+        change_rows.push(ChangeRow {
+            change: "Added",
+            previous: String::new(),
+            current: added.clone(),
         });
-        metric_rows.push(MetricRow {
-            metric: "Disk Read Bytes",
-            value_change: benchmark.disk_read_bytes.0.to_string(),
-            percentage_change: benchmark.disk_read_bytes.1.to_string(),
+    }
+
+    for removed in &stats_result.removed {
+        change_rows.push(ChangeRow {
+            change: "Removed",
+            previous: removed.clone(),
+            current: String::new(),
         });
-        metric_rows.push(MetricRow {
-            metric: "Bytecode Size",
-            value_change: benchmark.bytecode_size.0.to_string(),
-            percentage_change: benchmark.bytecode_size.1.to_string(),
+    }
+
+    for renamed in &stats_result.renamed {
+        change_rows.push(ChangeRow {
+            change: "Renamed",
+            previous: renamed.previous.clone(),
+            current: renamed.current.clone(),
         });
-        metric_rows.push(MetricRow {
-            metric: "Data Section Size",
-            value_change: benchmark.data_section_size.0.to_string(),
-            percentage_change: benchmark.data_section_size.1.to_string(),
+    }
+
+    for failed in &stats_result.failed {
+        let describe = |id: &str, failure: &Option<String>| match failure {
+            Some(failure) => format!("{id} ({failure})"),
+            None => id.to_string(),
+        };
+
+        change_rows.push(ChangeRow {
+            change: "Failed",
+            previous: describe(&failed.previous, &failed.previous_failure),
+            current: describe(&failed.current, &failed.current_failure),
         });
-        metric_rows.push(MetricRow {
-            metric: "Time",
-            value_change: benchmark.time.0.to_string(),
-            percentage_change: benchmark.time.1.to_string(),
+    }
+
+    // Benchmarks recorded before build modes were built without priming
+    let describe_mode = |name: &str, mode: Option<crate::types::BuildMode>| {
+        format!(
+            "{name} ({})",
+            mode.map_or_else(|| "unprimed".to_string(), |mode| mode.to_string())
+        )
+    };
+
+    for mismatched in &stats_result.mismatched {
+        change_rows.push(ChangeRow {
+            change: "Mode",
+            previous: describe_mode(&mismatched.previous, mismatched.previous_mode),
+            current: describe_mode(&mismatched.current, mismatched.current_mode),
         });
     }
 
+    // Create and print the metric table
+    let metric_table = Table::new(metric_rows);
+    println!("{metric_table}");
+
+    // Create and print the phase table
+    if !phase_rows.is_empty() {
+        let phase_table = Table::new(phase_rows);
+        println!("{phase_table}");
+    }
+
+    // Create and print the table of the top growing and shrinking bytecode items
+    if !size_rows.is_empty() {
+        let size_table = Table::new(size_rows);
+        println!("{size_table}");
+    }
+
+    // Create and print the table of added, removed, renamed, failed and mismatched benchmarks
+    if !change_rows.is_empty() {
+        let change_table = Table::new(change_rows);
+        println!("{change_table}");
+    }
+}
+
+/// Print the assembly information of the benchmarks of two runs side by side.
+///
+/// # Arguments
+///
+/// * `previous_benchmarks` - A reference to the previous `types::Benchmarks`.
+///
+/// * `current_benchmarks` - A reference to the current `types::Benchmarks`.
+///
+/// # Errors
+///
+/// If the function is unable to get the asm information.
+///
+pub fn print_asm_information(
+    previous_benchmarks: &crate::types::Benchmarks,
+    current_benchmarks: &crate::types::Benchmarks,
+) -> Result<()> {
     // Create a vector to hold the ASM rows
     let mut asm_rows = Vec::new();
     asm_rows.push(AsmRow {
@@ -395,53 +843,188 @@ pub fn print_stats(
         current: "Current".to_string(),
     });
 
-    let benchmarks = previous_benchmarks.iter().zip(current_benchmarks);
-    for (previous_benchmark, current_benchmark) in benchmarks {
-        asm_rows.push(AsmRow {
-            metric: "Bytecode Size",
-            previous: previous_benchmark.asm_information.as_ref().ok_or_else(|| {
-                wrap!("Failed to get asm information for previous bytecode size".into())
-            })?["bytecode_size"]
-                .to_string(),
-            current: current_benchmark.asm_information.as_ref().ok_or_else(|| {
-                wrap!("Failed to get asm information for current bytecode size".into())
-            })?["bytecode_size"]
-                .to_string(),
-        });
-        asm_rows.push(AsmRow {
-            metric: "Data Section Size",
-            previous: previous_benchmark.asm_information.as_ref().ok_or_else(|| {
-                wrap!("Failed to get asm information for previous data section size".into())
-            })?["data_section"]["size"]
-                .to_string(),
-            current: current_benchmark.asm_information.as_ref().ok_or_else(|| {
-                wrap!("Failed to get asm information for current data section size".into())
-            })?["data_section"]["size"]
-                .to_string(),
-        });
+    let matches = crate::stats::match_benchmarks(previous_benchmarks, current_benchmarks);
+    for pair in &matches.pairs {
+        let previous_benchmark = pair.previous;
+        let current_benchmark = pair.current;
+
+        // Failed builds have no asm information to compare
+        if previous_benchmark.failed() || current_benchmark.failed() {
+            continue;
+        }
+
         asm_rows.push(AsmRow {
-            metric: "Data Section Used",
-            previous: previous_benchmark.asm_information.as_ref().ok_or_else(|| {
-                wrap!("Failed to get asm information for previous data section used".into())
-            })?["data_section"]["used"]
-                .to_string(),
-            current: current_benchmark.asm_information.as_ref().ok_or_else(|| {
-                wrap!("Failed to get asm information for current data section used".into())
-            })?["data_section"]["used"]
-                .to_string(),
+            metric: "Path",
+            previous: pair.previous_id.to_string(),
+            current: pair.current_id.to_string(),
         });
-    }
 
-    // Create and print the metric table
-    let metric_table = Table::new(metric_rows);
-    println!("{metric_table}");
+        let Some(previous_asm) = previous_benchmark.asm_information.as_ref() else {
+            return Err(Box::new(wrap!(format!(
+                "Failed to get the previous asm information of {}",
+                pair.previous_id
+            )
+            .into())));
+        };
+
+        let Some(current_asm) = current_benchmark.asm_information.as_ref() else {
+            return Err(Box::new(wrap!(format!(
+                "Failed to get the current asm information of {}",
+                pair.current_id
+            )
+            .into())));
+        };
+
+        for ((metric, previous), (_, current)) in asm_fields(previous_asm)
+            .into_iter()
+            .zip(asm_fields(current_asm))
+        {
+            asm_rows.push(AsmRow {
+                metric,
+                previous,
+                current,
+            });
+        }
+    }
 
     // Create and print the ASM table
     let asm_table = Table::new(asm_rows);
     println!("{asm_table}");
+
     Ok(())
 }
 
+/// Get the display name and value of each field of the assembly information.
+fn asm_fields(asm: &crate::asm::AsmInformation) -> [(&'static str, String); 7] {
+    [
+        ("Schema Version", asm.schema_version.to_string()),
+        (
+            "Program Kind",
+            asm.program_kind().unwrap_or("-").to_string(),
+        ),
+        ("Bytecode Size", asm.bytecode_size.to_string()),
+        ("Data Section Size", asm.data_section.size.to_string()),
+        ("Data Section Used", asm.data_section.used.to_string()),
+        ("Data Section Values", asm.data_section_values().to_string()),
+        ("Entries", asm.entries.len().to_string()),
+    ]
+}
+
+#[derive(Tabled)]
+struct ViolationRow {
+    benchmark: String,
+    metric: String,
+    threshold: String,
+    value_change: String,
+    percentage_change: String,
+}
+
+/// Print the summary of the metrics that regressed beyond their thresholds.
+///
+/// # Arguments
+///
+/// * `violations` - A slice of `thresholds::Violation`.
+///
+fn print_violations(violations: &[crate::thresholds::Violation]) {
+    println!(
+        "{} metric(s) regressed beyond their thresholds",
+        violations.len()
+    );
+
+    let violation_rows = violations.iter().map(|violation| ViolationRow {
+        benchmark: violation.benchmark.clone(),
+        metric: violation.metric.clone(),
+        threshold: violation.threshold.to_string(),
+        value_change: violation.change.to_string(),
+        percentage_change: violation.percentage_change.to_string(),
+    });
+
+    let violation_table = Table::new(violation_rows);
+    println!("{violation_table}");
+}
+
+/// Print the metrics that regressed beyond their thresholds, and the benchmarks whose build
+/// failed in the current run, if any.
+///
+/// # Arguments
+///
+/// * `outcome` - A reference to a `thresholds::Outcome`.
+///
+pub fn print_outcome(outcome: &crate::thresholds::Outcome) {
+    if !outcome.violations.is_empty() {
+        print_violations(&outcome.violations);
+    }
+
+    if !outcome.failed.is_empty() {
+        println!(
+            "{} benchmark(s) failed to build in the current run: {}",
+            outcome.failed.len(),
+            outcome.failed.join(", ")
+        );
+    }
+}
+
+#[derive(Tabled)]
+struct WarningRow {
+    benchmark: String,
+    warning: String,
+}
+
+/// Print the warnings recorded while running the benchmarks, if any.
+///
+/// # Arguments
+///
+/// * `benchmarks` - A slice of `types::Benchmark`.
+///
+pub fn print_warnings(benchmarks: &[Benchmark]) {
+    let warning_rows: Vec<WarningRow> = benchmarks
+        .iter()
+        .flat_map(|benchmark| {
+            benchmark.warnings.iter().map(|warning| WarningRow {
+                benchmark: benchmark.display_path(),
+                warning: warning.clone(),
+            })
+        })
+        .collect();
+
+    if warning_rows.is_empty() {
+        return;
+    }
+
+    println!(
+        "{} warning(s) while running the benchmarks",
+        warning_rows.len()
+    );
+
+    let warning_table = Table::new(warning_rows);
+    println!("{warning_table}");
+}
+
+/// A row of the table of stored runs.
+#[derive(Tabled)]
+pub struct HistoryRow {
+    pub run: String,
+    pub date: String,
+    pub forc_version: String,
+    pub compiler_hash: String,
+    pub label: String,
+}
+
+/// Print the table of stored runs.
+///
+/// # Arguments
+///
+/// * `history_rows` - The rows of the stored runs, newest first.
+///
+pub fn print_history(history_rows: Vec<HistoryRow>) {
+    println!("{} stored run(s)", history_rows.len());
+
+    if !history_rows.is_empty() {
+        let history_table = Table::new(history_rows);
+        println!("{history_table}");
+    }
+}
+
 #[cfg(test)]
 mod tests {
 
@@ -451,4 +1034,103 @@ mod tests {
         let version = super::get_forc_version(forc_path).expect("Failed to get forc version");
         assert_eq!("0.63.1", version);
     }
+
+    #[test]
+    fn test_filter_benchmarks() {
+        let root = std::path::Path::new("/projects");
+        let benchmarks = || {
+            ["sdk/hashing", "sdk/storage", "should_fail/storage"]
+                .iter()
+                .map(|path| {
+                    let path = root.join(path);
+                    let name = path.file_name().unwrap().to_string_lossy().to_string();
+                    crate::types::Benchmark::new(&name, path)
+                })
+                .collect::<Vec<_>>()
+        };
+        let paths = |benchmarks: Vec<crate::types::Benchmark>| {
+            benchmarks
+                .into_iter()
+                .map(|benchmark| benchmark.path.strip_prefix(root).unwrap().to_path_buf())
+                .collect::<Vec<_>>()
+        };
+
+        let kept =
+            super::filter_benchmarks(benchmarks(), root, &[], &["should_fail/**".into()]).unwrap();
+        assert_eq!(
+            paths(kept),
+            [
+                std::path::PathBuf::from("sdk/hashing"),
+                std::path::PathBuf::from("sdk/storage")
+            ]
+        );
+
+        let kept =
+            super::filter_benchmarks(benchmarks(), root, &["**/storage".into()], &[]).unwrap();
+        assert_eq!(kept.len(), 2);
+
+        assert!(super::filter_benchmarks(benchmarks(), root, &["[".into()], &[]).is_err());
+
+        assert_eq!(
+            super::common_root(&[
+                std::path::PathBuf::from("/projects/sdk/hashing"),
+                std::path::PathBuf::from("/projects/should_fail"),
+            ]),
+            std::path::PathBuf::from("/projects")
+        );
+    }
+
+    #[test]
+    fn test_discover_workspaces() -> crate::Result<()> {
+        let root = std::env::temp_dir().join(format!("dyno-discover-{}", std::process::id()));
+
+        for (project, manifest) in [
+            ("standalone", "[project]\nname = \"standalone\"\n"),
+            ("harness", "[workspace]\nmembers = [\"hashing\"]\n"),
+            ("harness/hashing", "[project]\nname = \"hashing\"\n"),
+            ("deep/nested/project", "[project]\nname = \"project\"\n"),
+            ("broken", "[workspace\nmembers = [\"standalone\"\n"),
+        ] {
+            let folder = root.join(project);
+            std::fs::create_dir_all(&folder).map_err(|e| crate::wrap!(e.into()))?;
+            std::fs::write(folder.join("Forc.toml"), manifest)
+                .map_err(|e| crate::wrap!(e.into()))?;
+        }
+
+        let root = std::fs::canonicalize(&root).map_err(|e| crate::wrap!(e.into()))?;
+        let names = |benchmarks: &[crate::types::Benchmark]| {
+            let mut names = benchmarks
+                .iter()
+                .map(|benchmark| benchmark.name.clone())
+                .collect::<Vec<_>>();
+            names.sort();
+            names
+        };
+
+        let shallow = super::generate_benchmarks(&root, Some(1))?;
+        assert_eq!(names(&shallow), vec!["broken", "harness", "standalone"]);
+
+        // A member is only benchmarked on its own when its workspace is not, and a malformed
+        // manifest is not a workspace
+        let all = super::generate_benchmarks(&root, None)?;
+        assert_eq!(all.len(), 5);
+        assert!(super::workspace_members(&root.join("broken")).is_err());
+
+        let kept = super::skip_workspace_members(all.clone());
+        assert_eq!(
+            names(&kept),
+            vec!["broken", "harness", "project", "standalone"]
+        );
+
+        let members = super::filter_benchmarks(all, &root, &[], &["harness".into()])?;
+        let kept = super::skip_workspace_members(members);
+        assert_eq!(
+            names(&kept),
+            vec!["broken", "hashing", "project", "standalone"]
+        );
+
+        std::fs::remove_dir_all(&root).map_err(|e| crate::wrap!(e.into()))?;
+
+        Ok(())
+    }
 }
@@ -0,0 +1,285 @@
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+/// The name of the workload that builds the project with the profiler markers
+pub const DEFAULT_WORKLOAD: &str = "build";
+
+/// A forc command run against each project, identified by name across runs
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Workload {
+    /// The name of the workload
+    pub name: String,
+    /// The arguments passed to forc, starting with the subcommand
+    pub args: Vec<String>,
+    /// The environment variables set for forc
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub env: BTreeMap<String, String>,
+}
+
+impl Default for Workload {
+    /// The `forc build --profile --log-level 5` workload, which benchmarks recorded before
+    /// workloads were tracked ran
+    fn default() -> Self {
+        Self {
+            name: DEFAULT_WORKLOAD.to_string(),
+            args: ["build", "--profile", "--log-level", "5"]
+                .map(ToString::to_string)
+                .to_vec(),
+            env: BTreeMap::new(),
+        }
+    }
+}
+
+impl Workload {
+    /// Returns whether this is the default build workload.
+    #[must_use]
+    pub fn is_default(&self) -> bool {
+        self.name == DEFAULT_WORKLOAD
+    }
+}
+
+impl std::fmt::Display for Workload {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let env = self.env.iter().map(|(key, value)| format!("{key}={value}"));
+        let command = env
+            .chain(std::iter::once("forc".to_string()))
+            .chain(self.args.iter().cloned())
+            .collect::<Vec<_>>();
+
+        write!(f, "{}: {}", self.name, command.join(" "))
+    }
+}
+
+/// A workload as defined in the `[workloads.<name>]` tables of the configuration
+#[derive(Debug, Default, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct WorkloadConfig {
+    /// The arguments passed to forc, starting with the subcommand
+    pub args: Vec<String>,
+    /// The environment variables set for forc
+    #[serde(default)]
+    pub env: BTreeMap<String, String>,
+}
+
+/// A workload given on the command line, either by name or with its definition
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct WorkloadArg {
+    /// The name of the workload
+    pub name: String,
+    /// The definition of the workload, or `None` to use a configured or built-in workload
+    pub definition: Option<Workload>,
+}
+
+impl std::str::FromStr for WorkloadArg {
+    type Err = String;
+
+    /// Parse `NAME` or `NAME=[KEY=VALUE...] SUBCOMMAND [ARGS...]`.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (name, command) = match s.split_once('=') {
+            Some((name, command)) => (name.trim(), Some(command)),
+            None => (s.trim(), None),
+        };
+
+        let name = validate_workload_name(name)?;
+
+        let Some(command) = command else {
+            return Ok(Self {
+                name,
+                definition: None,
+            });
+        };
+
+        // Leading `KEY=VALUE` words are environment variables, like the `env` command takes them
+        let mut words = command.split_whitespace().peekable();
+        let mut env = BTreeMap::new();
+
+        while let Some((key, value)) = words.peek().and_then(|word| word.split_once('=')) {
+            env.insert(key.to_string(), value.to_string());
+            words.next();
+        }
+
+        let args = words.map(ToString::to_string).collect::<Vec<_>>();
+
+        if args.is_empty() {
+            return Err(format!(
+                "Invalid workload \"{s}\", expected a forc subcommand such as \"{name}=build --release\""
+            ));
+        }
+
+        Ok(Self {
+            name: name.clone(),
+            definition: Some(Workload { name, args, env }),
+        })
+    }
+}
+
+/// Check that a workload name can identify the workload in file names and printed stats
+///
+/// # Errors
+///
+/// If the name is empty or contains anything other than alphanumerics, `-`, `_` and `.`
+///
+pub fn validate_workload_name(name: &str) -> Result<String, String> {
+    if name.is_empty()
+        || !name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.'))
+    {
+        return Err(format!(
+            "Invalid workload name \"{name}\", expected alphanumerics, `-`, `_` and `.`"
+        ));
+    }
+
+    Ok(name.to_string())
+}
+
+/// Resolve the workloads of a run
+///
+/// The workloads given on the command line take precedence, followed by every workload of the
+/// configuration, and finally the default build workload. A workload named on the command line
+/// without a definition refers to a configured workload or to the default `build` workload.
+///
+/// # Arguments
+///
+/// * `args` - The workloads given on the command line
+///
+/// * `configured` - The workloads of the configuration, by name
+///
+/// # Errors
+///
+/// If a named workload is not configured, a configured workload has no arguments or is
+/// misnamed, or the same workload is given twice.
+///
+pub fn resolve(
+    args: &[WorkloadArg],
+    configured: &BTreeMap<String, WorkloadConfig>,
+) -> Result<Vec<Workload>, String> {
+    let from_config = |name: &str| -> Result<Option<Workload>, String> {
+        let Some(config) = configured.get(name) else {
+            return Ok(None);
+        };
+
+        if config.args.is_empty() {
+            return Err(format!(
+                "The configured workload \"{name}\" has no arguments"
+            ));
+        }
+
+        Ok(Some(Workload {
+            name: validate_workload_name(name)?,
+            args: config.args.clone(),
+            env: config.env.clone(),
+        }))
+    };
+
+    let workloads = if !args.is_empty() {
+        args.iter()
+            .map(|arg| match &arg.definition {
+                Some(definition) => Ok(definition.clone()),
+                None => match from_config(&arg.name)? {
+                    Some(workload) => Ok(workload),
+                    None if arg.name == DEFAULT_WORKLOAD => Ok(Workload::default()),
+                    None => Err(format!("Unknown workload \"{}\"", arg.name)),
+                },
+            })
+            .collect::<Result<Vec<_>, _>>()?
+    } else if !configured.is_empty() {
+        configured
+            .keys()
+            .filter_map(|name| from_config(name).transpose())
+            .collect::<Result<Vec<_>, _>>()?
+    } else {
+        vec![Workload::default()]
+    };
+
+    for (index, workload) in workloads.iter().enumerate() {
+        if workloads[..index]
+            .iter()
+            .any(|other| other.name == workload.name)
+        {
+            return Err(format!("The workload \"{}\" is given twice", workload.name));
+        }
+    }
+
+    Ok(workloads)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_workload_arg() {
+        let arg: WorkloadArg = "release".parse().unwrap();
+        assert_eq!(arg.name, "release");
+        assert_eq!(arg.definition, None);
+
+        let arg: WorkloadArg = "experimental=FORC_DEBUG=1 build --experimental new_encoding"
+            .parse()
+            .unwrap();
+        let definition = arg.definition.unwrap();
+        assert_eq!(definition.name, "experimental");
+        assert_eq!(
+            definition.args,
+            vec!["build", "--experimental", "new_encoding"]
+        );
+        assert_eq!(definition.env["FORC_DEBUG"], "1");
+        assert_eq!(
+            definition.to_string(),
+            "experimental: FORC_DEBUG=1 forc build --experimental new_encoding"
+        );
+
+        assert!("release=".parse::<WorkloadArg>().is_err());
+        assert!("my workload".parse::<WorkloadArg>().is_err());
+    }
+
+    #[test]
+    fn test_resolve_workloads() {
+        let configured = BTreeMap::from([
+            (
+                "test".to_string(),
+                WorkloadConfig {
+                    args: vec!["test".to_string()],
+                    env: BTreeMap::new(),
+                },
+            ),
+            (
+                "fmt".to_string(),
+                WorkloadConfig {
+                    args: vec!["fmt".to_string(), "--check".to_string()],
+                    env: BTreeMap::new(),
+                },
+            ),
+        ]);
+
+        // Without workloads, only the default build workload runs
+        assert_eq!(
+            resolve(&[], &BTreeMap::new()),
+            Ok(vec![Workload::default()])
+        );
+
+        // Every configured workload runs by default
+        let workloads = resolve(&[], &configured).unwrap();
+        assert_eq!(
+            workloads
+                .iter()
+                .map(|w| w.name.as_str())
+                .collect::<Vec<_>>(),
+            vec!["fmt", "test"]
+        );
+
+        let args = ["build", "test", "release=build --release"]
+            .map(|arg| arg.parse::<WorkloadArg>().unwrap());
+        let workloads = resolve(&args, &configured).unwrap();
+        assert_eq!(workloads[0], Workload::default());
+        assert_eq!(workloads[1].args, vec!["test"]);
+        assert_eq!(workloads[2].args, vec!["build", "--release"]);
+
+        assert!(resolve(&["missing".parse().unwrap()], &configured).is_err());
+        assert!(resolve(
+            &["test".parse().unwrap(), "test".parse().unwrap()],
+            &configured
+        )
+        .is_err());
+    }
+}